@@ -0,0 +1,512 @@
+"use strict";
+
+// Tables managed from the dashboard, with the schema of their records in /api/openapi.json.
+const TABLES = [
+  { title: "Events", path: "events", schema: "Event" },
+  { title: "Quotes", path: "quotes", schema: "Quote" },
+  { title: "Feeds", path: "feeds", schema: "Feed" },
+  { title: "Bets", path: "f1bets", schema: "Bet" },
+  { title: "Results", path: "results", schema: "Bet" },
+  { title: "Train schedules", path: "train_schedules", schema: "TrainSchedule" },
+];
+const VIEWS = [
+  ["status", "Status"],
+  ...TABLES.map((t) => [t.path, t.title]),
+  ["say", "Say"],
+  ["live", "Live"],
+  ["logs", "Logs"],
+];
+const PAGE_SIZE = 25;
+const REFRESH = 5000;
+// Lines of channel activity kept on the live view.
+const LIVE_LINES = 500;
+
+let key = sessionStorage.getItem("gluon_key");
+let schemas = {};
+let refresh = null;
+let live = null;
+
+const $ = (id) => document.getElementById(id);
+
+// Creates an element with the given properties and children, text children are never parsed as
+// HTML.
+function el(tag, props = {}, ...children) {
+  const element = document.createElement(tag);
+
+  for (const [name, value] of Object.entries(props)) {
+    if (name.startsWith("on")) {
+      element.addEventListener(name.slice(2), value);
+    } else {
+      element[name] = value;
+    }
+  }
+
+  for (const child of children) {
+    element.append(child instanceof Node ? child : String(child ?? ""));
+  }
+
+  return element;
+}
+
+// Calls the API with the key, throwing the error of the response if any.
+async function api(method, path, body) {
+  const options = { method, headers: { "x-api-key": key } };
+
+  if (body !== undefined) {
+    options.headers["Content-Type"] = "application/json";
+    options.body = JSON.stringify(body);
+  }
+
+  const response = await fetch(`/api/${path}`, options);
+  const text = await response.text();
+  let data = null;
+
+  try {
+    data = text ? JSON.parse(text) : null;
+  } catch {
+    data = text;
+  }
+
+  if (!response.ok) {
+    const error = new Error(data && data.error ? data.error : `HTTP ${response.status}`);
+
+    error.status = response.status;
+    throw error;
+  }
+
+  return { data, total: Number(response.headers.get("X-Total-Count") ?? 0) };
+}
+
+function showError(error) {
+  $("error").textContent = error ? error.message : "";
+
+  if (error && error.status === 401) {
+    logout();
+  }
+}
+
+async function login(candidate) {
+  key = candidate;
+
+  try {
+    // Only admin keys can read the audit log, which the dashboard needs.
+    await api("GET", "audit?limit=1");
+    schemas = (await (await fetch("/api/openapi.json")).json()).components.schemas;
+  } catch (error) {
+    key = null;
+    $("login-error").textContent =
+      error.status === 403 ? "The dashboard needs a key with the admin scope." : error.message;
+
+    return;
+  }
+
+  sessionStorage.setItem("gluon_key", key);
+  $("login").hidden = true;
+  $("dashboard").hidden = false;
+  $("logout").hidden = false;
+  $("login-error").textContent = "";
+  buildNav();
+  show("status");
+}
+
+function logout() {
+  live?.abort();
+  key = null;
+  sessionStorage.removeItem("gluon_key");
+  clearInterval(refresh);
+  $("dashboard").hidden = true;
+  $("logout").hidden = true;
+  $("login").hidden = false;
+}
+
+function buildNav() {
+  $("nav").replaceChildren(
+    ...VIEWS.map(([name, title]) => {
+      const button = el("button", { onclick: () => show(name) }, title);
+
+      button.dataset.view = name;
+
+      return button;
+    })
+  );
+}
+
+function show(name) {
+  clearInterval(refresh);
+  live?.abort();
+  showError(null);
+
+  for (const button of $("nav").children) {
+    button.classList.toggle("active", button.dataset.view === name);
+  }
+
+  const table = TABLES.find((t) => t.path === name);
+
+  if (table) {
+    showTable(table, 0);
+  } else if (name === "status") {
+    showStatus();
+    refresh = setInterval(showStatus, REFRESH);
+  } else if (name === "say") {
+    showSay();
+  } else if (name === "live") {
+    showLive();
+  } else if (name === "logs") {
+    showLogs();
+  }
+}
+
+async function showStatus() {
+  try {
+    const { data: reports } = await api("GET", "status");
+
+    $("view").replaceChildren(
+      ...reports.map((report) =>
+        el(
+          "section",
+          {},
+          el("h2", {}, `${report.network} (${report.connected ? "connected" : "disconnected"})`),
+          el(
+            "p",
+            {},
+            `Nick: ${report.nick}. Connected since: ${report.connected_since ?? "never"}. ` +
+              `Uptime: ${report.uptime}s. Pending reminders: ${report.pending_reminders}.`
+          ),
+          el("h3", {}, "Channels"),
+          rows(["Channel", "Users"], report.channels.map((c) => [c.name, c.users])),
+          el("h3", {}, "Tasks"),
+          rows(["Task", "State"], Object.entries(report.tasks)),
+          el("h3", {}, "Feeds"),
+          rows(["Id", "URL", "Polled"], report.feeds.map((f) => [f.id, f.url, f.polled]))
+        )
+      )
+    );
+  } catch (error) {
+    showError(error);
+  }
+}
+
+function rows(headers, values) {
+  return el(
+    "table",
+    {},
+    el("thead", {}, el("tr", {}, ...headers.map((h) => el("th", {}, h)))),
+    el("tbody", {}, ...values.map((row) => el("tr", {}, ...row.map((v) => el("td", {}, v)))))
+  );
+}
+
+async function showTable(table, offset) {
+  const schema = schemas[table.schema];
+  const fields = Object.keys(schema.properties);
+
+  try {
+    const { data: records, total } = await api("GET", `${table.path}?offset=${offset}&limit=${PAGE_SIZE}`);
+    const toolbar = el(
+      "div",
+      { className: "toolbar" },
+      el("button", { onclick: () => showForm(table, null) }, "New"),
+      el("button", { disabled: offset === 0, onclick: () => showTable(table, Math.max(0, offset - PAGE_SIZE)) }, "Previous"),
+      el("span", {}, `${total ? offset + 1 : 0}-${offset + records.length} of ${total}`),
+      el("button", { disabled: offset + PAGE_SIZE >= total, onclick: () => showTable(table, offset + PAGE_SIZE) }, "Next")
+    );
+    const body = records.map((record) =>
+      el(
+        "tr",
+        {},
+        ...fields.map((f) => el("td", {}, Array.isArray(record[f]) ? record[f].join(", ") : record[f])),
+        el(
+          "td",
+          {},
+          el("button", { onclick: () => showForm(table, record) }, "Edit"),
+          " ",
+          el("button", { onclick: () => remove(table, record, offset) }, "Delete")
+        )
+      )
+    );
+
+    $("view").replaceChildren(
+      el("h2", {}, table.title),
+      toolbar,
+      el(
+        "table",
+        {},
+        el("thead", {}, el("tr", {}, ...fields.map((f) => el("th", {}, f)), el("th"))),
+        el("tbody", {}, ...body)
+      )
+    );
+  } catch (error) {
+    showError(error);
+  }
+}
+
+// A form with an input for every field of the schema, creating a record when record is null.
+function showForm(table, record) {
+  const schema = schemas[table.schema];
+  const inputs = {};
+  const form = el("form", { className: "record" });
+
+  for (const [name, property] of Object.entries(schema.properties)) {
+    if (name === "id") {
+      continue;
+    }
+
+    const value = record ? record[name] : undefined;
+    let input;
+
+    if (property.type === "boolean") {
+      input = el("input", { type: "checkbox", checked: Boolean(value) });
+    } else if (property.type === "integer" || property.type === "number") {
+      input = el("input", { type: "number", value: value ?? "" });
+    } else if (property.type === "array") {
+      input = el("input", { type: "text", value: (value ?? []).join(", "), placeholder: "Separated by commas" });
+    } else {
+      input = el("input", {
+        type: "text",
+        value: value ?? "",
+        placeholder: property.format === "date-time" ? "2025-03-16T04:00:00Z" : "",
+      });
+    }
+
+    input.id = `field-${name}`;
+    inputs[name] = [input, property];
+    form.append(el("label", { htmlFor: input.id }, name), input);
+  }
+
+  form.append(
+    el("span"),
+    el(
+      "div",
+      {},
+      el("button", { type: "submit" }, record ? "Save" : "Create"),
+      " ",
+      el("button", { type: "button", onclick: () => showTable(table, 0) }, "Cancel")
+    )
+  );
+  form.addEventListener("submit", async (event) => {
+    event.preventDefault();
+
+    const values = {};
+
+    for (const [name, [input, property]] of Object.entries(inputs)) {
+      if (property.type === "boolean") {
+        values[name] = input.checked;
+      } else if (property.type === "integer" || property.type === "number") {
+        values[name] = Number(input.value);
+      } else if (property.type === "array") {
+        values[name] = input.value.split(",").map((v) => v.trim()).filter((v) => v);
+      } else if (input.value !== "" || (schema.required ?? []).includes(name)) {
+        values[name] = input.value;
+      }
+    }
+
+    try {
+      if (record) {
+        await api("PUT", `${table.path}/${record.id}`, values);
+      } else {
+        await api("POST", table.path, values);
+      }
+
+      showError(null);
+      showTable(table, 0);
+    } catch (error) {
+      showError(error);
+    }
+  });
+
+  $("view").replaceChildren(el("h2", {}, record ? `${table.title} ${record.id}` : `New ${table.title.toLowerCase()}`), form);
+}
+
+async function remove(table, record, offset) {
+  if (!confirm(`Delete ${table.title.toLowerCase()} ${record.id}?`)) {
+    return;
+  }
+
+  try {
+    await api("DELETE", `${table.path}/${record.id}`);
+    showTable(table, offset);
+  } catch (error) {
+    showError(error);
+  }
+}
+
+async function showSay() {
+  let networks = [];
+
+  try {
+    networks = (await api("GET", "status")).data.map((r) => r.network);
+  } catch (error) {
+    showError(error);
+  }
+
+  const network = el("select", { id: "say-network" }, ...networks.map((n) => el("option", { value: n }, n)));
+  const channel = el("input", { id: "say-channel", type: "text", placeholder: "#channel", required: true });
+  const body = el("input", { id: "say-body", type: "text", required: true });
+  const result = el("p");
+  const form = el(
+    "form",
+    { className: "record" },
+    el("label", { htmlFor: "say-network" }, "network"),
+    network,
+    el("label", { htmlFor: "say-channel" }, "channel"),
+    channel,
+    el("label", { htmlFor: "say-body" }, "message"),
+    body,
+    el("span"),
+    el("div", {}, el("button", { type: "submit" }, "Send"))
+  );
+
+  form.addEventListener("submit", async (event) => {
+    event.preventDefault();
+
+    try {
+      const { data } = await api("POST", "say", { network: network.value, channel: channel.value, body: body.value });
+
+      result.textContent = data === "Success" ? `Sent to ${channel.value}.` : "The message could not be sent.";
+
+      if (data === "Success") {
+        body.value = "";
+      }
+    } catch (error) {
+      showError(error);
+    }
+  });
+
+  $("view").replaceChildren(el("h2", {}, "Say"), form, result);
+}
+
+// Follows /api/stream. EventSource can't send the key header, so the stream is read with fetch.
+function showLive() {
+  const channel = el("input", { type: "text", placeholder: "All channels" });
+  const lines = el("div", { className: "log" });
+  const follow = () => {
+    live?.abort();
+    lines.replaceChildren();
+    live = new AbortController();
+    readStream(channel.value.trim(), live.signal, (event) => {
+      const nick = event.nick ?? "";
+      const text = {
+        message: `<${nick}> ${event.text}`,
+        command: `<${nick}> ${event.text}`,
+        output: `<${nick}> ${event.text}`,
+        join: `${nick} joined`,
+        part: `${nick} left (${event.text})`,
+        quit: `${nick} quit (${event.text})`,
+        nick: `${nick} is now ${event.text}`,
+      }[event.type];
+
+      lines.append(el("div", { className: event.type }, `${event.time.slice(11, 19)} ${event.network} ${event.target} ${text}`));
+
+      while (lines.childElementCount > LIVE_LINES) {
+        lines.firstChild.remove();
+      }
+    });
+  };
+
+  $("view").replaceChildren(
+    el("h2", {}, "Live"),
+    el("div", { className: "toolbar" }, channel, el("button", { onclick: follow }, "Follow")),
+    lines
+  );
+  follow();
+}
+
+async function readStream(channel, signal, onEvent) {
+  try {
+    const query = channel ? `?channel=${encodeURIComponent(channel)}` : "";
+    const response = await fetch(`/api/stream${query}`, { headers: { "x-api-key": key }, signal });
+
+    if (!response.ok) {
+      const data = await response.json().catch(() => null);
+      const error = new Error(data && data.error ? data.error : `HTTP ${response.status}`);
+
+      error.status = response.status;
+      throw error;
+    }
+
+    const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+    let buffer = "";
+
+    for (;;) {
+      const { value, done } = await reader.read();
+
+      if (done) {
+        return;
+      }
+
+      buffer += value;
+
+      const events = buffer.split("\n\n");
+
+      buffer = events.pop();
+
+      for (const event of events) {
+        const data = event
+          .split("\n")
+          .filter((line) => line.startsWith("data:"))
+          .map((line) => line.slice(5).trim())
+          .join("\n");
+
+        if (data) {
+          onEvent(JSON.parse(data));
+        }
+      }
+    }
+  } catch (error) {
+    if (error.name !== "AbortError") {
+      showError(error);
+    }
+  }
+}
+
+// Tails the lines of the logged channels (GET /api/logs) and the audit log of the API, both
+// refreshed in place so the channel filter keeps its value.
+function showLogs() {
+  const channel = el("input", { type: "text", placeholder: "All channels" });
+  const lines = el("div", { className: "log" });
+  const audit = el("div");
+  const update = async () => {
+    const filter = channel.value.trim();
+    const query = filter ? `&channel=${encodeURIComponent(filter)}` : "";
+
+    try {
+      const [{ data: logged }, { data: entries }] = await Promise.all([
+        api("GET", `logs?limit=${PAGE_SIZE * 4}${query}`),
+        api("GET", `audit?limit=${PAGE_SIZE * 4}`),
+      ]);
+
+      lines.replaceChildren(
+        ...logged
+          .reverse()
+          .map((l) => el("div", {}, `${l.datetime.slice(0, 19).replace("T", " ")} ${l.network} ${l.channel} <${l.nick}> ${l.text}`))
+      );
+      audit.replaceChildren(
+        rows(
+          ["Time", "Key", "Route", "Address", "Outcome"],
+          entries.map((e) => [e.datetime, e.key, e.route, e.address, e.outcome])
+        )
+      );
+    } catch (error) {
+      showError(error);
+    }
+  };
+
+  $("view").replaceChildren(
+    el("h2", {}, "Channel log"),
+    el("div", { className: "toolbar" }, channel, el("button", { onclick: update }, "Filter")),
+    lines,
+    el("h2", {}, "API log"),
+    audit
+  );
+  update();
+  refresh = setInterval(update, REFRESH);
+}
+
+$("login").addEventListener("submit", (event) => {
+  event.preventDefault();
+  login($("key").value.trim());
+});
+$("logout").addEventListener("click", logout);
+
+if (key) {
+  login(key);
+}
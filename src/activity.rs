@@ -0,0 +1,105 @@
+use crate::clock::Clock;
+use crate::transport::{Incoming, IncomingKind};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use utoipa::ToSchema;
+
+// Events kept for a listener of /api/stream that is not keeping up. Older events are dropped.
+const ACTIVITY_BUFFER: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    Message,
+    Join,
+    Part,
+    Quit,
+    Nick,
+    // A message running a bot command.
+    Command,
+    // A line sent by the bot.
+    Output,
+}
+
+// Something that happened on a network, as streamed by /api/stream.
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ActivityEvent {
+    #[serde(rename = "type")]
+    pub kind: ActivityKind,
+    pub network: String,
+    pub nick: Option<String>,
+    pub target: String,
+    pub text: String,
+    pub time: String,
+}
+
+// Broadcasts the activity of a network to whoever is listening. Publishing with no listener is
+// fine, the event is dropped.
+#[derive(Clone)]
+pub struct Activity {
+    network: String,
+    sender: Sender<ActivityEvent>,
+}
+
+impl Activity {
+    pub fn new(network: &str) -> Self {
+        let (sender, _) = broadcast::channel(ACTIVITY_BUFFER);
+
+        Self {
+            network: String::from(network),
+            sender,
+        }
+    }
+
+    pub fn subscribe(&self) -> Receiver<ActivityEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(
+        &self,
+        kind: ActivityKind,
+        nick: Option<String>,
+        target: &str,
+        text: &str,
+        clock: &Clock,
+    ) {
+        let _ = self.sender.send(ActivityEvent {
+            kind,
+            network: self.network.clone(),
+            nick,
+            target: String::from(target),
+            text: String::from(text),
+            time: clock.now().to_rfc3339(),
+        });
+    }
+
+    // Publishes a message or event received from the transport. Messages starting with the
+    // command prefix are published as commands.
+    pub fn incoming(&self, incoming: &Incoming, prefix: &str, clock: &Clock) {
+        let kind = match incoming.kind {
+            IncomingKind::Message
+                if incoming.text.len() > 1 && incoming.text.starts_with(prefix) =>
+            {
+                ActivityKind::Command
+            }
+            IncomingKind::Message => ActivityKind::Message,
+            IncomingKind::Join => ActivityKind::Join,
+            IncomingKind::Part => ActivityKind::Part,
+            IncomingKind::Quit => ActivityKind::Quit,
+            IncomingKind::Nick => ActivityKind::Nick,
+        };
+
+        self.publish(
+            kind,
+            incoming.nick.clone(),
+            &incoming.target,
+            &incoming.text,
+            clock,
+        );
+    }
+}
+
+// Whether target is a channel or room rather than a nick. Private messages are never streamed.
+pub fn is_channel(target: &str) -> bool {
+    target.starts_with(['#', '&', '!', '+'])
+}
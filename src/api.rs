@@ -1,68 +1,705 @@
-use crate::commands::base::Quote;
-use crate::commands::f1bet::{score_bets, Bet, ScoringSystem};
+pub mod admin;
+pub mod keys;
+pub mod openapi;
+
+use crate::activity;
+use crate::commands::base::{self, Answer, Quote, TimeZone};
+use crate::commands::f1bet::{score_bets, Bet, Driver, ScoringSystem};
+use crate::commands::karma::Karma;
+use crate::commands::log::LogLine;
 use crate::commands::next::Event;
-use crate::database::Database;
-use irc::client::prelude::Command;
-use irc::client::Client;
+use crate::commands::weather::WeatherSetting;
+use crate::database::{CsvRecord, Database, Identified};
+use crate::hooks::{self, Hook};
+use crate::network::{self, NetworkHandle};
+use crate::queue::Priority;
+use crate::status::StatusReport;
+use crate::tasks::feeds::Feed;
+use crate::tasks::next::Interest;
+use crate::tasks::train_game::TrainSchedule;
+use crate::webhook;
+use chrono::{DateTime, NaiveDate, TimeZone as _, Utc};
+use futures::stream::{select_all, unfold, StreamExt};
 use itertools::Itertools;
+use keys::AuditEntry;
+use rocket::data::{Data, ToByteUnit};
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::status::{Created, NoContent};
+use rocket::response::stream::{Event as StreamEvent, EventStream};
+use rocket::response::{self, Responder, Response};
 use rocket::serde::json::Json;
-use rocket::State;
+use rocket::{Shutdown, State};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::error::Error;
+use std::marker::PhantomData;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
+use utoipa::ToSchema;
 
-const MIN_KEY_SIZE: usize = 32;
+// The scope a route needs, given as the type parameter of its ApiKey.
+pub trait Scope: Send + Sync + 'static {
+    const NAME: &'static str;
+}
 
-async fn validate_api_key(key: &str) -> bool {
-    let keys = match tokio::fs::read_to_string("api_keys.txt").await {
-        Ok(keys) => keys,
-        Err(_) => return false,
-    };
+pub struct EventsWrite;
+pub struct QuotesWrite;
+pub struct Say;
+pub struct BetsRead;
+pub struct StreamRead;
+pub struct LogsRead;
+pub struct Admin;
 
-    keys.lines().any(|line| {
-        line.len() >= MIN_KEY_SIZE && !line.is_empty() && !line.contains(' ') && line == key
-    })
+impl Scope for EventsWrite {
+    const NAME: &'static str = "events:write";
+}
+
+impl Scope for QuotesWrite {
+    const NAME: &'static str = "quotes:write";
+}
+
+impl Scope for Say {
+    const NAME: &'static str = "say";
+}
+
+impl Scope for BetsRead {
+    const NAME: &'static str = "bets:read";
+}
+
+impl Scope for StreamRead {
+    const NAME: &'static str = "stream:read";
+}
+
+impl Scope for LogsRead {
+    const NAME: &'static str = "logs:read";
+}
+
+impl Scope for Admin {
+    const NAME: &'static str = "admin";
 }
 
 #[derive(Debug)]
 pub enum ApiKeyError {
     Invalid,
     Missing,
+    Expired,
+    Forbidden,
 }
 
-pub struct ApiKey(String);
+// A valid key sent in the x-api-key header with scope S. Every use is recorded in the audit log.
+pub struct ApiKey<S: Scope> {
+    pub key: keys::Key,
+    route: String,
+    address: String,
+    scope: PhantomData<S>,
+}
+
+impl<S: Scope> ApiKey<S> {
+    // Whether the key may act on channel, recording the refusal when it may not.
+    pub async fn channel(&self, channel: &str, state: &BotState) -> bool {
+        if self.key.allows_channel(channel) {
+            return true;
+        }
+
+        keys::audit(
+            &self.key.name,
+            &self.route,
+            &self.address,
+            &format!("denied channel {channel}"),
+            &state.db,
+        )
+        .await;
+
+        false
+    }
+}
 
 #[rocket::async_trait]
-impl<'r> FromRequest<'r> for ApiKey {
+impl<'r, S: Scope> FromRequest<'r> for ApiKey<S> {
     type Error = ApiKeyError;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        match request.headers().get_one("x-api-key") {
-            Some(key) if validate_api_key(key).await => Outcome::Success(ApiKey(key.to_string())),
-            Some(_) => Outcome::Failure((Status::Unauthorized, ApiKeyError::Invalid)),
-            None => Outcome::Failure((Status::Unauthorized, ApiKeyError::Missing)),
+        let key = match request.headers().get_one("x-api-key") {
+            Some(key) => key,
+            None => return Outcome::Failure((Status::Unauthorized, ApiKeyError::Missing)),
+        };
+        let state = match request.rocket().state::<BotState>() {
+            Some(state) => state,
+            None => return Outcome::Failure((Status::InternalServerError, ApiKeyError::Invalid)),
+        };
+        let route = format!("{} {}", request.method(), request.uri().path());
+        let address = match request.client_ip() {
+            Some(address) => address.to_string(),
+            None => String::new(),
+        };
+        let (name, outcome) = match keys::find(key, &state.db).await {
+            None => (
+                String::new(),
+                Err((Status::Unauthorized, ApiKeyError::Invalid)),
+            ),
+            Some(key) if key.expired(Utc::now()) => {
+                (key.name, Err((Status::Unauthorized, ApiKeyError::Expired)))
+            }
+            Some(key) if !key.allows(S::NAME) => {
+                (key.name, Err((Status::Forbidden, ApiKeyError::Forbidden)))
+            }
+            Some(key) => (key.name.clone(), Ok(key)),
+        };
+
+        keys::audit(
+            &name,
+            &route,
+            &address,
+            match &outcome {
+                Ok(_) => "allowed",
+                Err((_, ApiKeyError::Expired)) => "denied expired key",
+                Err((_, ApiKeyError::Forbidden)) => "denied scope",
+                Err(_) => "denied invalid key",
+            },
+            &state.db,
+        )
+        .await;
+
+        match outcome {
+            Ok(key) => Outcome::Success(ApiKey {
+                key,
+                route,
+                address,
+                scope: PhantomData,
+            }),
+            Err(failure) => Outcome::Failure(failure),
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+// Every header of a request, with lowercase names.
+pub struct Headers(HashMap<String, String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Headers {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Headers(
+            request
+                .headers()
+                .iter()
+                .map(|h| (h.name().as_str().to_lowercase(), String::from(h.value())))
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct Message {
     channel: String,
     body: String,
+    #[serde(default)]
+    network: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct F1BetScore {
     nick: String,
     points: i32,
 }
 
 pub struct BotState {
-    pub client: Arc<Mutex<Client>>,
     pub db: Arc<Mutex<Database>>,
+    pub options: Arc<HashMap<String, String>>,
+    pub networks: Vec<NetworkHandle>,
+    pub hooks: Vec<Hook>,
+}
+
+impl BotState {
+    // Finds a network by name, defaulting to the first configured network.
+    fn network(&self, name: Option<&str>) -> Option<&NetworkHandle> {
+        match name {
+            Some(name) => self
+                .networks
+                .iter()
+                .find(|n| n.name.to_lowercase() == name.to_lowercase()),
+            None => self.networks.first(),
+        }
+    }
+
+    // The network records are stored under. Clients that leave it empty, such as the static
+    // frontend, mean the data network of the first network.
+    fn data_network(&self, network: &str) -> String {
+        match network.is_empty() {
+            true => match self.networks.first() {
+                Some(handle) => handle.data.clone(),
+                None => network::default_network(),
+            },
+            false => String::from(network),
+        }
+    }
+
+    fn fill_network<T: Resource>(&self, record: &mut T) {
+        if let Some(network) = record.network_mut() {
+            *network = self.data_network(network);
+        }
+    }
+}
+
+// The body of every error of the REST routes.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ApiError {
+    pub error: String,
+}
+
+pub type ApiResult<T> = Result<T, (Status, Json<ApiError>)>;
+
+fn api_error(status: Status, error: &str) -> (Status, Json<ApiError>) {
+    (
+        status,
+        Json(ApiError {
+            error: String::from(error),
+        }),
+    )
+}
+
+// Errors raised before a route runs, such as a missing key or a malformed body, get a JSON body
+// too.
+#[catch(default)]
+pub fn catch_default(status: Status, _request: &Request) -> (Status, Json<ApiError>) {
+    api_error(status, status.reason().unwrap_or("Unknown error"))
+}
+
+// One page of a list, with the size of the whole list in the X-Total-Count header.
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+}
+
+impl<T> Page<T> {
+    // Skips offset items and keeps at most limit of the rest, every item when limit is missing.
+    pub fn new(items: Vec<T>, offset: Option<usize>, limit: Option<usize>) -> Self {
+        let total = items.len();
+        let items = items
+            .into_iter()
+            .skip(offset.unwrap_or_default())
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Self { items, total }
+    }
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for Page<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        Response::build_from(Json(self.items).respond_to(request)?)
+            .raw_header("X-Total-Count", self.total.to_string())
+            .ok()
+    }
+}
+
+// A record served by id at /api/<table>/<id>.
+pub trait Resource: CsvRecord + Identified + PartialEq + Serialize + DeserializeOwned {
+    // The channel a key must be allowed to act on to change the record. Records of no channel can
+    // only be changed by keys allowed on every channel.
+    fn channel(&self) -> &str {
+        ""
+    }
+
+    // The network of the record, for records stored per network.
+    fn network_mut(&mut self) -> Option<&mut String> {
+        None
+    }
+}
+
+impl Resource for Event {
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn network_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.network)
+    }
+}
+
+impl Resource for Quote {
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn network_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.network)
+    }
+}
+
+impl Resource for Feed {
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn network_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.network)
+    }
+}
+
+impl Resource for Bet {
+    fn network_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.network)
+    }
+}
+
+impl Resource for Driver {}
+
+impl Resource for TrainSchedule {
+    fn network_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.network)
+    }
+}
+
+impl Resource for Answer {}
+
+impl Resource for Interest {
+    fn network_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.network)
+    }
+}
+
+impl Resource for TimeZone {
+    fn network_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.network)
+    }
+}
+
+impl Resource for WeatherSetting {
+    fn network_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.network)
+    }
+}
+
+// Gives an id to the records of every table served by id that have none.
+pub fn assign_ids(db: &Database) -> Result<(), Box<dyn Error>> {
+    db.assign_ids::<Event>("events")?;
+    db.assign_ids::<Quote>("quotes")?;
+    db.assign_ids::<Feed>("feeds")?;
+    db.assign_ids::<Bet>("bets")?;
+    db.assign_ids::<Bet>("results")?;
+    db.assign_ids::<Driver>("drivers")?;
+    db.assign_ids::<TrainSchedule>("train_schedules")?;
+    db.assign_ids::<Answer>("answers")?;
+    db.assign_ids::<Interest>("interests")?;
+    db.assign_ids::<TimeZone>("time_zones")?;
+    db.assign_ids::<WeatherSetting>("weather_settings")
+}
+
+async fn find<T: Resource>(table: &str, id: u64, state: &BotState) -> ApiResult<T> {
+    match state.db.lock().await.select(table, |r: &T| r.id() == id) {
+        Ok(Some(records)) => match records.into_iter().next() {
+            Some(record) => Ok(record),
+            None => Err(api_error(
+                Status::NotFound,
+                &format!("Could not find {id}."),
+            )),
+        },
+        Ok(None) => Err(api_error(
+            Status::NotFound,
+            &format!("Could not find {id}."),
+        )),
+        Err(_) => Err(api_error(
+            Status::InternalServerError,
+            &format!("Could not read {}.", table),
+        )),
+    }
+}
+
+async fn check_channel<T: Resource, S: Scope>(
+    record: &T,
+    key: &ApiKey<S>,
+    state: &BotState,
+) -> ApiResult<()> {
+    match key.channel(record.channel(), state).await {
+        true => Ok(()),
+        false => Err(api_error(
+            Status::Forbidden,
+            &format!("The key can't act on channel {}.", record.channel()),
+        )),
+    }
+}
+
+// Stores the record with a new id and returns it as stored.
+async fn create<T: Resource, S: Scope>(
+    table: &str,
+    mut record: T,
+    key: &ApiKey<S>,
+    state: &BotState,
+) -> ApiResult<T> {
+    check_channel(&record, key, state).await?;
+    state.fill_network(&mut record);
+
+    let id = match state.db.lock().await.insert_new(table, record) {
+        Ok(id) => id,
+        Err(_) => {
+            return Err(api_error(
+                Status::InternalServerError,
+                &format!("Could not write {}.", table),
+            ))
+        }
+    };
+
+    find(table, id, state).await
+}
+
+fn created<T: Resource>(path: &str, record: T) -> Created<Json<T>> {
+    Created::new(format!("/api/{path}/{}", record.id())).body(Json(record))
+}
+
+async fn replace<T: Resource, S: Scope>(
+    table: &str,
+    id: u64,
+    mut record: T,
+    key: &ApiKey<S>,
+    state: &BotState,
+) -> ApiResult<Json<T>> {
+    let existing: T = find(table, id, state).await?;
+
+    check_channel(&existing, key, state).await?;
+    check_channel(&record, key, state).await?;
+    state.fill_network(&mut record);
+    record.set_id(id);
+
+    if state
+        .db
+        .lock()
+        .await
+        .update(table, record, |r: &&T| r.id() == id)
+        .is_err()
+    {
+        return Err(api_error(
+            Status::InternalServerError,
+            &format!("Could not write {}.", table),
+        ));
+    }
+
+    find(table, id, state).await.map(Json)
+}
+
+// Sets the fields given in changes, keeping the others and the id.
+async fn patch<T: Resource, S: Scope>(
+    table: &str,
+    id: u64,
+    changes: Value,
+    key: &ApiKey<S>,
+    state: &BotState,
+) -> ApiResult<Json<T>> {
+    let changes = match changes {
+        Value::Object(changes) => changes,
+        _ => {
+            return Err(api_error(
+                Status::BadRequest,
+                "The body must be a JSON object.",
+            ))
+        }
+    };
+    let existing: T = find(table, id, state).await?;
+    let mut fields = match serde_json::to_value(&existing) {
+        Ok(Value::Object(fields)) => fields,
+        _ => {
+            return Err(api_error(
+                Status::InternalServerError,
+                &format!("Could not read {id}."),
+            ))
+        }
+    };
+
+    for (name, value) in changes {
+        if name != "id" {
+            fields.insert(name, value);
+        }
+    }
+
+    let record: T = match serde_json::from_value(Value::Object(fields)) {
+        Ok(record) => record,
+        Err(error) => {
+            return Err(api_error(
+                Status::UnprocessableEntity,
+                &format!("Invalid fields: {error}."),
+            ))
+        }
+    };
+
+    replace(table, id, record, key, state).await
+}
+
+async fn remove<T: Resource, S: Scope>(
+    table: &str,
+    id: u64,
+    key: &ApiKey<S>,
+    state: &BotState,
+) -> ApiResult<NoContent> {
+    let existing: T = find(table, id, state).await?;
+
+    check_channel(&existing, key, state).await?;
+
+    if state
+        .db
+        .lock()
+        .await
+        .delete(table, |r: &&T| r.id() == id)
+        .is_err()
+    {
+        return Err(api_error(
+            Status::InternalServerError,
+            &format!("Could not write {}.", table),
+        ));
+    }
+
+    Ok(NoContent)
+}
+
+// The routes of a table only admin keys can use: a paginated list, then get, create, replace,
+// patch and delete by id.
+macro_rules! admin_routes {
+    (
+        $record:ident, $table:literal,
+        $path:literal, $list_path:literal, $item_path:literal,
+        $list:ident, $get:ident, $create:ident, $replace:ident, $patch:ident, $remove:ident
+    ) => {
+        #[utoipa::path(
+            tag = $table,
+            responses(
+                (status = 200, description = "One page of the list", body = [$record], headers(("X-Total-Count" = usize, description = "Size of the whole list"))),
+                (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+                (status = 403, description = "Key without the scope or channel", body = ApiError),
+                (status = 500, description = "Database error", body = ApiError)
+            ),
+            security(("api_key" = []))
+        )]
+        #[get($list_path)]
+        pub async fn $list(
+            offset: Option<usize>,
+            limit: Option<usize>,
+            _key: ApiKey<Admin>,
+            state: &State<BotState>,
+        ) -> ApiResult<Page<$record>> {
+            match state.db.lock().await.select($table, |_: &$record| true) {
+                Ok(records) => Ok(Page::new(records.unwrap_or_default(), offset, limit)),
+                Err(_) => Err(api_error(
+                    Status::InternalServerError,
+                    &format!("Could not read {}.", $table),
+                )),
+            }
+        }
+
+        #[utoipa::path(
+            tag = $table,
+            responses(
+                (status = 200, description = "The record", body = $record),
+                (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+                (status = 403, description = "Key without the scope or channel", body = ApiError),
+                (status = 404, description = "Not found", body = ApiError)
+            ),
+            security(("api_key" = []))
+        )]
+        #[get($item_path)]
+        pub async fn $get(
+            id: u64,
+            _key: ApiKey<Admin>,
+            state: &State<BotState>,
+        ) -> ApiResult<Json<$record>> {
+            find($table, id, state).await.map(Json)
+        }
+
+        #[utoipa::path(
+            tag = $table,
+            request_body = $record,
+            responses(
+                (status = 201, description = "The new record, its URL is in the Location header", body = $record),
+                (status = 400, description = "Malformed body", body = ApiError),
+                (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+                (status = 403, description = "Key without the scope or channel", body = ApiError),
+                (status = 422, description = "Invalid record", body = ApiError),
+                (status = 500, description = "Database error", body = ApiError)
+            ),
+            security(("api_key" = []))
+        )]
+        #[post($path, format = "application/json", data = "<record>")]
+        pub async fn $create(
+            record: Json<$record>,
+            key: ApiKey<Admin>,
+            state: &State<BotState>,
+        ) -> ApiResult<Created<Json<$record>>> {
+            create($table, record.into_inner(), &key, state)
+                .await
+                .map(|r| created($table, r))
+        }
+
+        #[utoipa::path(
+            tag = $table,
+            request_body = $record,
+            responses(
+                (status = 200, description = "The record", body = $record),
+                (status = 400, description = "Malformed body", body = ApiError),
+                (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+                (status = 403, description = "Key without the scope or channel", body = ApiError),
+                (status = 404, description = "Not found", body = ApiError),
+                (status = 422, description = "Invalid record", body = ApiError),
+                (status = 500, description = "Database error", body = ApiError)
+            ),
+            security(("api_key" = []))
+        )]
+        #[put($item_path, format = "application/json", data = "<record>")]
+        pub async fn $replace(
+            id: u64,
+            record: Json<$record>,
+            key: ApiKey<Admin>,
+            state: &State<BotState>,
+        ) -> ApiResult<Json<$record>> {
+            replace($table, id, record.into_inner(), &key, state).await
+        }
+
+        #[utoipa::path(
+            tag = $table,
+            request_body(content = Object, description = "The fields to set"),
+            responses(
+                (status = 200, description = "The record", body = $record),
+                (status = 400, description = "Malformed body", body = ApiError),
+                (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+                (status = 403, description = "Key without the scope or channel", body = ApiError),
+                (status = 404, description = "Not found", body = ApiError),
+                (status = 422, description = "Invalid record", body = ApiError),
+                (status = 500, description = "Database error", body = ApiError)
+            ),
+            security(("api_key" = []))
+        )]
+        #[patch($item_path, format = "application/json", data = "<changes>")]
+        pub async fn $patch(
+            id: u64,
+            changes: Json<Value>,
+            key: ApiKey<Admin>,
+            state: &State<BotState>,
+        ) -> ApiResult<Json<$record>> {
+            patch($table, id, changes.into_inner(), &key, state).await
+        }
+
+        #[utoipa::path(
+            tag = $table,
+            responses(
+                (status = 204, description = "Deleted"),
+                (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+                (status = 403, description = "Key without the scope or channel", body = ApiError),
+                (status = 404, description = "Not found", body = ApiError),
+                (status = 500, description = "Database error", body = ApiError)
+            ),
+            security(("api_key" = []))
+        )]
+        #[delete($item_path)]
+        pub async fn $remove(
+            id: u64,
+            key: ApiKey<Admin>,
+            state: &State<BotState>,
+        ) -> ApiResult<NoContent> {
+            remove::<$record, _>($table, id, &key, state).await
+        }
+    };
 }
 
 fn lookup_race(race: &str) -> String {
@@ -97,7 +734,13 @@ fn lookup_race(race: &str) -> String {
 }
 
 #[allow(clippy::too_many_arguments)]
-#[get("/events?<category>&<name>&<description>&<datetime>&<channel>&<tags>&<orderby>&<descending>")]
+#[utoipa::path(
+    tag = "events",
+    responses(
+        (status = 200, description = "One page of the list", body = [Event], headers(("X-Total-Count" = usize, description = "Size of the whole list")))
+    )
+)]
+#[get("/events?<category>&<name>&<description>&<datetime>&<channel>&<tags>&<network>&<orderby>&<descending>&<offset>&<limit>")]
 pub async fn events(
     category: Option<&str>,
     name: Option<&str>,
@@ -105,10 +748,13 @@ pub async fn events(
     datetime: Option<&str>,
     channel: Option<&str>,
     tags: Option<&str>,
+    network: Option<&str>,
     orderby: Option<&str>,
     descending: Option<bool>,
+    offset: Option<usize>,
+    limit: Option<usize>,
     state: &rocket::State<BotState>,
-) -> Json<Vec<Event>> {
+) -> Page<Event> {
     let events = state
         .db
         .lock()
@@ -133,6 +779,9 @@ pub async fn events(
                 && e.tags
                     .to_lowercase()
                     .contains(tags.unwrap_or_default().to_lowercase().as_str())
+                && e.network
+                    .to_lowercase()
+                    .contains(network.unwrap_or_default().to_lowercase().as_str())
         })
         .unwrap_or_default()
         .unwrap_or_default();
@@ -156,12 +805,139 @@ pub async fn events(
         },
     };
 
-    Json(events.into_iter().sorted_by(ordering).collect())
+    Page::new(
+        events.into_iter().sorted_by(ordering).collect(),
+        offset,
+        limit,
+    )
 }
 
+#[utoipa::path(
+    tag = "events",
+    responses(
+        (status = 200, description = "The record", body = Event),
+        (status = 404, description = "Not found", body = ApiError)
+    )
+)]
+#[get("/events/<id>")]
+pub async fn event(id: u64, state: &State<BotState>) -> ApiResult<Json<Event>> {
+    find("events", id, state).await.map(Json)
+}
+
+#[utoipa::path(
+    tag = "events",
+    request_body = Event,
+    responses(
+        (status = 201, description = "The new record, its URL is in the Location header", body = Event),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[post("/events", format = "application/json", data = "<event>")]
+pub async fn create_event(
+    event: Json<Event>,
+    key: ApiKey<EventsWrite>,
+    state: &State<BotState>,
+) -> ApiResult<Created<Json<Event>>> {
+    create("events", event.into_inner(), &key, state)
+        .await
+        .map(|r| created("events", r))
+}
+
+#[utoipa::path(
+    tag = "events",
+    request_body = Event,
+    responses(
+        (status = 200, description = "The record", body = Event),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[put("/events/<id>", format = "application/json", data = "<event>")]
+pub async fn replace_event(
+    id: u64,
+    event: Json<Event>,
+    key: ApiKey<EventsWrite>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Event>> {
+    replace("events", id, event.into_inner(), &key, state).await
+}
+
+#[utoipa::path(
+    tag = "events",
+    request_body(content = Object, description = "The fields to set"),
+    responses(
+        (status = 200, description = "The record", body = Event),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[patch("/events/<id>", format = "application/json", data = "<changes>")]
+pub async fn patch_event(
+    id: u64,
+    changes: Json<Value>,
+    key: ApiKey<EventsWrite>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Event>> {
+    patch("events", id, changes.into_inner(), &key, state).await
+}
+
+#[utoipa::path(
+    tag = "events",
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[delete("/events/<id>")]
+pub async fn remove_event(
+    id: u64,
+    key: ApiKey<EventsWrite>,
+    state: &State<BotState>,
+) -> ApiResult<NoContent> {
+    remove::<Event, _>("events", id, &key, state).await
+}
+
+#[utoipa::path(
+    tag = "events",
+    request_body = Event,
+    responses(
+        (status = 200, description = "Success or Failure", body = String),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
 #[post("/events/add", format = "application/json", data = "<event>")]
-pub async fn add_event(event: Json<Event>, _key: ApiKey, state: &State<BotState>) -> &'static str {
+pub async fn add_event(
+    event: Json<Event>,
+    key: ApiKey<EventsWrite>,
+    state: &State<BotState>,
+) -> &'static str {
+    if !key.channel(&event.channel, state).await {
+        return "Failure";
+    }
+
     let event = Event {
+        id: 0,
         category: event.category.clone(),
         name: event.name.clone(),
         description: event.description.clone(),
@@ -169,30 +945,49 @@ pub async fn add_event(event: Json<Event>, _key: ApiKey, state: &State<BotState>
         channel: event.channel.clone(),
         tags: event.tags.clone(),
         notify: event.notify,
+        network: state.data_network(&event.network),
     };
 
-    if state.db.lock().await.insert("events", event).is_err() {
+    if state.db.lock().await.insert_new("events", event).is_err() {
         return "Failure";
     }
 
     "Success"
 }
 
+#[utoipa::path(
+    tag = "events",
+    request_body = Event,
+    responses(
+        (status = 200, description = "Success or Failure", body = String),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
 #[post("/events/delete", format = "application/json", data = "<event>")]
 pub async fn delete_event(
     event: Json<Event>,
-    _key: ApiKey,
+    key: ApiKey<EventsWrite>,
     state: &State<BotState>,
 ) -> &'static str {
+    if !key.channel(&event.channel, state).await {
+        return "Failure";
+    }
+
+    let network = state.data_network(&event.network);
+
     if state
         .db
         .lock()
         .await
         .delete("events", |e: &&Event| {
-            e.category.to_lowercase() == event.category.to_lowercase()
+            key.key.allows_channel(&e.channel)
+                && e.category.to_lowercase() == event.category.to_lowercase()
                 && e.name.to_lowercase() == event.name.to_lowercase()
                 && e.description.to_lowercase() == event.description.to_lowercase()
                 && e.datetime == event.datetime
+                && e.network == network
         })
         .is_err()
     {
@@ -202,10 +997,20 @@ pub async fn delete_event(
     "Success"
 }
 
+#[utoipa::path(
+    tag = "events",
+    request_body(content = [Event], description = "The event to find, then its new fields"),
+    responses(
+        (status = 200, description = "Success or Failure", body = String),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
 #[post("/events/update", format = "application/json", data = "<input>")]
 pub async fn update_event(
     input: Json<Vec<Event>>,
-    _key: ApiKey,
+    key: ApiKey<EventsWrite>,
     state: &State<BotState>,
 ) -> &'static str {
     let new_event = match input.get(1) {
@@ -217,13 +1022,22 @@ pub async fn update_event(
         None => return "Failure",
     };
 
+    if !key.channel(&search_event.channel, state).await
+        || !key.channel(&new_event.channel, state).await
+    {
+        return "Failure";
+    }
+
+    let network = state.data_network(&search_event.network);
+
     if state
         .db
         .lock()
         .await
-        .update(
+        .update_keeping_id(
             "events",
             Event {
+                id: 0,
                 category: new_event.category.clone(),
                 name: new_event.name.clone(),
                 description: new_event.description.clone(),
@@ -231,6 +1045,7 @@ pub async fn update_event(
                 channel: new_event.channel.clone(),
                 tags: new_event.tags.clone(),
                 notify: new_event.notify,
+                network: state.data_network(&new_event.network),
             },
             |e: &&Event| {
                 e.category.to_lowercase() == search_event.category.to_lowercase()
@@ -239,6 +1054,7 @@ pub async fn update_event(
                     && e.datetime == search_event.datetime
                     && e.channel.to_lowercase() == search_event.channel.to_lowercase()
                     && e.tags.to_lowercase() == search_event.tags.to_lowercase()
+                    && e.network == network
             },
         )
         .is_err()
@@ -249,12 +1065,25 @@ pub async fn update_event(
     "Success"
 }
 
-#[get("/f1bets?<race>&<nick>")]
+#[utoipa::path(
+    tag = "f1bets",
+    responses(
+        (status = 200, description = "One page of the list", body = [Bet], headers(("X-Total-Count" = usize, description = "Size of the whole list"))),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[get("/f1bets?<race>&<nick>&<network>&<offset>&<limit>")]
 pub async fn f1_bets(
     race: Option<&str>,
     nick: Option<&str>,
+    network: Option<&str>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    _key: ApiKey<BetsRead>,
     state: &rocket::State<BotState>,
-) -> Json<Vec<Bet>> {
+) -> Page<Bet> {
     let bets = state
         .db
         .lock()
@@ -268,20 +1097,151 @@ pub async fn f1_bets(
                 .nick
                 .to_lowercase()
                 .contains(nick.unwrap_or_default().to_lowercase().as_str())
+                && b.network
+                    .to_lowercase()
+                    .contains(network.unwrap_or_default().to_lowercase().as_str())
         })
         .unwrap_or_default()
         .unwrap_or_default();
 
-    Json(bets)
+    Page::new(bets, offset, limit)
+}
+
+#[utoipa::path(
+    tag = "f1bets",
+    responses(
+        (status = 200, description = "The record", body = Bet),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[get("/f1bets/<id>")]
+pub async fn f1_bet(
+    id: u64,
+    _key: ApiKey<BetsRead>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Bet>> {
+    find("bets", id, state).await.map(Json)
+}
+
+#[utoipa::path(
+    tag = "f1bets",
+    request_body = Bet,
+    responses(
+        (status = 201, description = "The new record, its URL is in the Location header", body = Bet),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[post("/f1bets", format = "application/json", data = "<bet>")]
+pub async fn create_f1_bet(
+    bet: Json<Bet>,
+    key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<Created<Json<Bet>>> {
+    create("bets", bet.into_inner(), &key, state)
+        .await
+        .map(|r| created("f1bets", r))
+}
+
+#[utoipa::path(
+    tag = "f1bets",
+    request_body = Bet,
+    responses(
+        (status = 200, description = "The record", body = Bet),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[put("/f1bets/<id>", format = "application/json", data = "<bet>")]
+pub async fn replace_f1_bet(
+    id: u64,
+    bet: Json<Bet>,
+    key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Bet>> {
+    replace("bets", id, bet.into_inner(), &key, state).await
+}
+
+#[utoipa::path(
+    tag = "f1bets",
+    request_body(content = Object, description = "The fields to set"),
+    responses(
+        (status = 200, description = "The record", body = Bet),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[patch("/f1bets/<id>", format = "application/json", data = "<changes>")]
+pub async fn patch_f1_bet(
+    id: u64,
+    changes: Json<Value>,
+    key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Bet>> {
+    patch("bets", id, changes.into_inner(), &key, state).await
 }
 
-#[get("/f1bets/scores")]
-pub async fn score_f1_bets(state: &State<BotState>) -> Json<Vec<F1BetScore>> {
+#[utoipa::path(
+    tag = "f1bets",
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[delete("/f1bets/<id>")]
+pub async fn remove_f1_bet(
+    id: u64,
+    key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<NoContent> {
+    remove::<Bet, _>("bets", id, &key, state).await
+}
+
+#[utoipa::path(
+    tag = "f1bets",
+    responses(
+        (status = 200, description = "Points of every player", body = [F1BetScore]),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[get("/f1bets/scores?<network>")]
+pub async fn score_f1_bets(
+    network: Option<&str>,
+    _key: ApiKey<BetsRead>,
+    state: &State<BotState>,
+) -> Json<Vec<F1BetScore>> {
     let bets = state
         .db
         .lock()
         .await
-        .select("bets", |_: &Bet| true)
+        .select("bets", |b: &Bet| {
+            b.network
+                .to_lowercase()
+                .contains(network.unwrap_or_default().to_lowercase().as_str())
+        })
         .unwrap_or_default()
         .unwrap_or_default();
 
@@ -306,13 +1266,210 @@ pub async fn score_f1_bets(state: &State<BotState>) -> Json<Vec<F1BetScore>> {
     )
 }
 
-#[get("/quotes?<date>&<text>&<channel>")]
+#[utoipa::path(
+    tag = "feeds",
+    responses(
+        (status = 200, description = "One page of the list", body = [Feed], headers(("X-Total-Count" = usize, description = "Size of the whole list")))
+    )
+)]
+#[get("/feeds?<category>&<channel>&<network>&<offset>&<limit>")]
+pub async fn feeds(
+    category: Option<&str>,
+    channel: Option<&str>,
+    network: Option<&str>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: &State<BotState>,
+) -> Page<Feed> {
+    let feeds = state
+        .db
+        .lock()
+        .await
+        .select("feeds", |f: &Feed| {
+            f.category
+                .to_lowercase()
+                .contains(category.unwrap_or_default().to_lowercase().as_str())
+                && f.channel
+                    .to_lowercase()
+                    .contains(channel.unwrap_or_default().to_lowercase().as_str())
+                && f.network
+                    .to_lowercase()
+                    .contains(network.unwrap_or_default().to_lowercase().as_str())
+        })
+        .unwrap_or_default()
+        .unwrap_or_default();
+
+    Page::new(feeds, offset, limit)
+}
+
+#[utoipa::path(
+    tag = "feeds",
+    responses(
+        (status = 200, description = "The record", body = Feed),
+        (status = 404, description = "Not found", body = ApiError)
+    )
+)]
+#[get("/feeds/<id>")]
+pub async fn feed(id: u64, state: &State<BotState>) -> ApiResult<Json<Feed>> {
+    find("feeds", id, state).await.map(Json)
+}
+
+#[utoipa::path(
+    tag = "feeds",
+    request_body = Feed,
+    responses(
+        (status = 201, description = "The new record, its URL is in the Location header", body = Feed),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[post("/feeds", format = "application/json", data = "<feed>")]
+pub async fn create_feed(
+    feed: Json<Feed>,
+    key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<Created<Json<Feed>>> {
+    create("feeds", feed.into_inner(), &key, state)
+        .await
+        .map(|r| created("feeds", r))
+}
+
+#[utoipa::path(
+    tag = "feeds",
+    request_body = Feed,
+    responses(
+        (status = 200, description = "The record", body = Feed),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[put("/feeds/<id>", format = "application/json", data = "<feed>")]
+pub async fn replace_feed(
+    id: u64,
+    feed: Json<Feed>,
+    key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Feed>> {
+    replace("feeds", id, feed.into_inner(), &key, state).await
+}
+
+#[utoipa::path(
+    tag = "feeds",
+    request_body(content = Object, description = "The fields to set"),
+    responses(
+        (status = 200, description = "The record", body = Feed),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[patch("/feeds/<id>", format = "application/json", data = "<changes>")]
+pub async fn patch_feed(
+    id: u64,
+    changes: Json<Value>,
+    key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Feed>> {
+    patch("feeds", id, changes.into_inner(), &key, state).await
+}
+
+#[utoipa::path(
+    tag = "feeds",
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[delete("/feeds/<id>")]
+pub async fn remove_feed(
+    id: u64,
+    key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<NoContent> {
+    remove::<Feed, _>("feeds", id, &key, state).await
+}
+
+#[get("/p/<id>")]
+pub async fn paste(id: &str, state: &State<BotState>) -> Option<String> {
+    crate::paste::find(id, &state.options, Arc::clone(&state.db)).await
+}
+
+// Karma of the channels, highest first unless descending is false.
+#[utoipa::path(
+    tag = "karma",
+    responses(
+        (status = 200, description = "One page of the list", body = [Karma], headers(("X-Total-Count" = usize, description = "Size of the whole list")))
+    )
+)]
+#[get("/karma?<thing>&<channel>&<network>&<descending>&<offset>&<limit>")]
+pub async fn karma(
+    thing: Option<&str>,
+    channel: Option<&str>,
+    network: Option<&str>,
+    descending: Option<bool>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: &State<BotState>,
+) -> Page<Karma> {
+    let mut karma = state
+        .db
+        .lock()
+        .await
+        .select("karma", |k: &Karma| {
+            thing.is_none_or(|thing| k.thing.eq_ignore_ascii_case(thing))
+                && channel.is_none_or(|channel| k.channel.eq_ignore_ascii_case(channel))
+                && network.is_none_or(|network| k.network == network)
+        })
+        .unwrap_or_default()
+        .unwrap_or_default();
+
+    karma.sort_by_key(|k| k.score);
+
+    if descending.unwrap_or(true) {
+        karma.reverse();
+    }
+
+    Page::new(karma, offset, limit)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[utoipa::path(
+    tag = "quotes",
+    responses(
+        (status = 200, description = "One page of the list", body = [Quote], headers(("X-Total-Count" = usize, description = "Size of the whole list")))
+    )
+)]
+#[get("/quotes?<date>&<text>&<channel>&<network>&<added_by>&<said_by>&<orderby>&<descending>&<offset>&<limit>")]
 pub async fn quotes(
     date: Option<&str>,
     text: Option<&str>,
     channel: Option<&str>,
+    network: Option<&str>,
+    added_by: Option<&str>,
+    said_by: Option<&str>,
+    orderby: Option<&str>,
+    descending: Option<bool>,
+    offset: Option<usize>,
+    limit: Option<usize>,
     state: &State<BotState>,
-) -> Json<Vec<Quote>> {
+) -> Page<Quote> {
     let quotes = state
         .db
         .lock()
@@ -327,34 +1484,233 @@ pub async fn quotes(
                 && q.channel
                     .to_lowercase()
                     .contains(channel.unwrap_or_default().to_lowercase().as_str())
+                && q.network
+                    .to_lowercase()
+                    .contains(network.unwrap_or_default().to_lowercase().as_str())
+                && added_by.is_none_or(|nick| q.added_by.eq_ignore_ascii_case(nick))
+                && said_by.is_none_or(|nick| q.said_by.eq_ignore_ascii_case(nick))
         })
         .unwrap_or_default()
         .unwrap_or_default();
 
-    Json(quotes)
+    let ordering = match orderby.unwrap_or_default().to_lowercase().as_str() {
+        "votes" => match descending.unwrap_or_default() {
+            false => |a: &Quote, b: &Quote| Ord::cmp(&a.votes, &b.votes),
+            true => |a: &Quote, b: &Quote| Ord::cmp(&b.votes, &a.votes),
+        },
+        _ => match descending.unwrap_or_default() {
+            false => |a: &Quote, b: &Quote| Ord::cmp(&a.id, &b.id),
+            true => |a: &Quote, b: &Quote| Ord::cmp(&b.id, &a.id),
+        },
+    };
+
+    Page::new(
+        quotes.into_iter().sorted_by(ordering).collect(),
+        offset,
+        limit,
+    )
+}
+
+#[utoipa::path(
+    tag = "quotes",
+    responses(
+        (status = 200, description = "The record", body = Quote),
+        (status = 404, description = "Not found", body = ApiError)
+    )
+)]
+#[get("/quotes/<id>")]
+pub async fn quote(id: u64, state: &State<BotState>) -> ApiResult<Json<Quote>> {
+    find("quotes", id, state).await.map(Json)
 }
 
+#[utoipa::path(
+    tag = "quotes",
+    request_body = Quote,
+    responses(
+        (status = 201, description = "The new record, its URL is in the Location header", body = Quote),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[post("/quotes", format = "application/json", data = "<quote>")]
+pub async fn create_quote(
+    quote: Json<Quote>,
+    key: ApiKey<QuotesWrite>,
+    state: &State<BotState>,
+) -> ApiResult<Created<Json<Quote>>> {
+    // Votes are only counted through !quote +1, once per nick.
+    let quote = create(
+        "quotes",
+        Quote {
+            votes: 0,
+            ..quote.into_inner()
+        },
+        &key,
+        state,
+    )
+    .await?;
+
+    webhook::notify(
+        "quote",
+        &quote.network,
+        serde_json::to_value(&quote).unwrap_or_default(),
+        &state.options,
+        Arc::clone(&state.db),
+    );
+
+    Ok(created("quotes", quote))
+}
+
+#[utoipa::path(
+    tag = "quotes",
+    request_body = Quote,
+    responses(
+        (status = 200, description = "The record", body = Quote),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[put("/quotes/<id>", format = "application/json", data = "<quote>")]
+pub async fn replace_quote(
+    id: u64,
+    quote: Json<Quote>,
+    key: ApiKey<QuotesWrite>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Quote>> {
+    replace("quotes", id, quote.into_inner(), &key, state).await
+}
+
+#[utoipa::path(
+    tag = "quotes",
+    request_body(content = Object, description = "The fields to set"),
+    responses(
+        (status = 200, description = "The record", body = Quote),
+        (status = 400, description = "Malformed body", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 422, description = "Invalid record", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[patch("/quotes/<id>", format = "application/json", data = "<changes>")]
+pub async fn patch_quote(
+    id: u64,
+    changes: Json<Value>,
+    key: ApiKey<QuotesWrite>,
+    state: &State<BotState>,
+) -> ApiResult<Json<Quote>> {
+    patch("quotes", id, changes.into_inner(), &key, state).await
+}
+
+#[utoipa::path(
+    tag = "quotes",
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[delete("/quotes/<id>")]
+pub async fn remove_quote(
+    id: u64,
+    key: ApiKey<QuotesWrite>,
+    state: &State<BotState>,
+) -> ApiResult<NoContent> {
+    remove::<Quote, _>("quotes", id, &key, state).await?;
+
+    if let Err(error) = base::delete_votes(id, &*state.db.lock().await) {
+        eprintln!("{error}");
+    }
+
+    Ok(NoContent)
+}
+
+#[utoipa::path(
+    tag = "quotes",
+    request_body = Quote,
+    responses(
+        (status = 200, description = "Success or Failure", body = String),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
 #[post("/quotes/add", format = "application/json", data = "<quote>")]
-pub async fn add_quote(quote: Json<Quote>, _key: ApiKey, state: &State<BotState>) -> &'static str {
+pub async fn add_quote(
+    quote: Json<Quote>,
+    key: ApiKey<QuotesWrite>,
+    state: &State<BotState>,
+) -> &'static str {
+    if !key.channel(&quote.channel, state).await {
+        return "Failure";
+    }
+
     let quote = Quote {
+        id: 0,
         date: quote.date.clone(),
         text: quote.text.clone(),
         channel: quote.channel.clone(),
+        network: state.data_network(&quote.network),
+        added_by: quote.added_by.clone(),
+        said_by: quote.said_by.clone(),
+        // Votes are only counted through !quote +1, once per nick.
+        votes: 0,
     };
+    let network = quote.network.clone();
+    let mut data = serde_json::to_value(&quote).unwrap_or_default();
 
-    if state.db.lock().await.insert("quotes", quote).is_err() {
-        return "Failure";
+    match state.db.lock().await.insert_new("quotes", quote) {
+        Ok(id) => data["id"] = Value::from(id),
+        Err(_) => return "Failure",
     }
 
+    webhook::notify(
+        "quote",
+        &network,
+        data,
+        &state.options,
+        Arc::clone(&state.db),
+    );
+
     "Success"
 }
 
+#[utoipa::path(
+    tag = "quotes",
+    request_body = Quote,
+    responses(
+        (status = 200, description = "Success or Failure", body = String),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
 #[post("/quotes/delete", format = "application/json", data = "<quote>")]
 pub async fn delete_quote(
     quote: Json<Quote>,
-    _key: ApiKey,
+    key: ApiKey<QuotesWrite>,
     state: &State<BotState>,
 ) -> &'static str {
+    if !key.channel(&quote.channel, state).await {
+        return "Failure";
+    }
+
+    let network = state.data_network(&quote.network);
+
     if state
         .db
         .lock()
@@ -363,6 +1719,7 @@ pub async fn delete_quote(
             q.date.to_lowercase() == quote.date.to_lowercase()
                 && q.text.to_lowercase() == quote.text.to_lowercase()
                 && q.channel.to_lowercase() == quote.channel.to_lowercase()
+                && q.network == network
         })
         .is_err()
     {
@@ -372,10 +1729,20 @@ pub async fn delete_quote(
     "Success"
 }
 
+#[utoipa::path(
+    tag = "quotes",
+    request_body(content = [Quote], description = "The quote to find, then its new fields"),
+    responses(
+        (status = 200, description = "Success or Failure", body = String),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
 #[post("/quotes/update", format = "application/json", data = "<input>")]
 pub async fn update_quote(
     input: Json<Vec<Quote>>,
-    _key: ApiKey,
+    key: ApiKey<QuotesWrite>,
     state: &State<BotState>,
 ) -> &'static str {
     let new_quote = match input.get(1) {
@@ -387,21 +1754,35 @@ pub async fn update_quote(
         None => return "Failure",
     };
 
+    if !key.channel(&search_quote.channel, state).await
+        || !key.channel(&new_quote.channel, state).await
+    {
+        return "Failure";
+    }
+
+    let network = state.data_network(&search_quote.network);
+
     if state
         .db
         .lock()
         .await
-        .update(
+        .update_keeping_id(
             "quotes",
             Quote {
+                id: 0,
                 date: new_quote.date.clone(),
                 text: new_quote.text.clone(),
                 channel: new_quote.channel.clone(),
+                network: state.data_network(&new_quote.network),
+                added_by: new_quote.added_by.clone(),
+                said_by: new_quote.said_by.clone(),
+                votes: new_quote.votes,
             },
             |q: &&Quote| {
                 q.date.to_lowercase() == search_quote.date.to_lowercase()
                     && q.text.to_lowercase() == search_quote.text.to_lowercase()
                     && q.channel.to_lowercase() == search_quote.channel.to_lowercase()
+                    && q.network == network
             },
         )
         .is_err()
@@ -412,16 +1793,269 @@ pub async fn update_quote(
     "Success"
 }
 
+// Shows what !status shows to the owners of the bot, so it needs an admin key as well.
+#[utoipa::path(
+    tag = "status",
+    responses(
+        (status = 200, description = "Status of every network", body = [StatusReport]),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[get("/status")]
+pub async fn status(_key: ApiKey<Admin>, state: &State<BotState>) -> Json<Vec<StatusReport>> {
+    let mut reports: Vec<StatusReport> = Vec::new();
+
+    for network in &state.networks {
+        let transport = network.transport.lock().await.clone();
+
+        reports.push(
+            network
+                .status
+                .lock()
+                .await
+                .report(transport.as_deref())
+                .await,
+        );
+    }
+
+    Json(reports)
+}
+
+#[utoipa::path(
+    tag = "status",
+    responses(
+        (status = 200, description = "One page of the audit log, newest first", body = [AuditEntry], headers(("X-Total-Count" = usize, description = "Size of the whole log"))),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[get("/audit?<name>&<offset>&<limit>")]
+pub async fn audit(
+    name: Option<&str>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    _key: ApiKey<Admin>,
+    state: &State<BotState>,
+) -> ApiResult<Page<AuditEntry>> {
+    match state.db.lock().await.select("api_audit", |a: &AuditEntry| {
+        name.is_none_or(|name| a.key == name)
+    }) {
+        Ok(entries) => Ok(Page::new(
+            entries.unwrap_or_default().into_iter().rev().collect(),
+            offset,
+            limit,
+        )),
+        Err(_) => Err(api_error(
+            Status::InternalServerError,
+            "Could not read api_audit.",
+        )),
+    }
+}
+
+// Parses a bound of the logs given as a date time or a date, which stands for the start of that day
+// or, for an end bound, the end of that day.
+fn parse_bound(bound: &str, end: bool) -> ApiResult<DateTime<Utc>> {
+    let datetime = match NaiveDate::parse_from_str(bound, "%Y-%m-%d") {
+        Ok(date) => match end {
+            true => date.succ_opt(),
+            false => Some(date),
+        }
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| Utc.from_utc_datetime(&datetime)),
+        Err(_) => bound.parse().ok(),
+    };
+
+    match datetime {
+        Some(datetime) => Ok(datetime),
+        None => Err(api_error(
+            Status::BadRequest,
+            &format!("Could not parse {bound}. Example: 2025-03-16 or 2025-03-16T04:00:00Z"),
+        )),
+    }
+}
+
+// Lines of the logged channels, newest first. Keys restricted to some channels only see the lines
+// of those channels.
+#[allow(clippy::too_many_arguments)]
+#[utoipa::path(
+    tag = "logs",
+    responses(
+        (status = 200, description = "One page of the lines, newest first", body = [LogLine], headers(("X-Total-Count" = usize, description = "Number of matching lines"))),
+        (status = 400, description = "Invalid from or to", body = ApiError),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 500, description = "Database error", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[get("/logs?<channel>&<nick>&<text>&<from>&<to>&<network>&<offset>&<limit>")]
+pub async fn logs(
+    channel: Option<&str>,
+    nick: Option<&str>,
+    text: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    network: Option<&str>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    key: ApiKey<LogsRead>,
+    state: &State<BotState>,
+) -> ApiResult<Page<LogLine>> {
+    if let Some(channel) = channel {
+        if !key.channel(channel, state).await {
+            return Err(api_error(
+                Status::Forbidden,
+                &format!("The key can't act on channel {channel}."),
+            ));
+        }
+    }
+
+    let from = from.map(|from| parse_bound(from, false)).transpose()?;
+    let to = to.map(|to| parse_bound(to, true)).transpose()?;
+    let text = text.map(str::to_lowercase);
+
+    match state.db.lock().await.select("logs", |l: &LogLine| {
+        key.key.allows_channel(&l.channel)
+            && channel.is_none_or(|channel| l.channel.eq_ignore_ascii_case(channel))
+            && nick.is_none_or(|nick| l.nick.eq_ignore_ascii_case(nick))
+            && text
+                .as_ref()
+                .is_none_or(|text| l.text.to_lowercase().contains(text))
+            && from.is_none_or(|from| l.datetime >= from)
+            && to.is_none_or(|to| l.datetime < to)
+            && network.is_none_or(|network| l.network == network)
+    }) {
+        Ok(lines) => Ok(Page::new(
+            lines.unwrap_or_default().into_iter().rev().collect(),
+            offset,
+            limit,
+        )),
+        Err(_) => Err(api_error(
+            Status::InternalServerError,
+            "Could not read logs.",
+        )),
+    }
+}
+
+// Streams what happens on the channels as Server-Sent Events, each carrying an ActivityEvent as
+// JSON. Private messages are never streamed. Quits and nick changes aren't tied to a channel, so
+// they're only streamed to keys that may act on any channel when no channel is asked for.
+#[utoipa::path(
+    tag = "messages",
+    responses(
+        (status = 200, description = "Server-Sent Events until the client disconnects", content_type = "text/event-stream", body = ActivityEvent),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError),
+        (status = 404, description = "No such network", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
+#[get("/stream?<network>&<channel>")]
+pub async fn stream(
+    network: Option<&str>,
+    channel: Option<&str>,
+    key: ApiKey<StreamRead>,
+    state: &State<BotState>,
+    mut shutdown: Shutdown,
+) -> ApiResult<EventStream![]> {
+    let networks: Vec<&NetworkHandle> = match network {
+        Some(name) => match state.network(Some(name)) {
+            Some(network) => vec![network],
+            None => {
+                return Err(api_error(
+                    Status::NotFound,
+                    &format!("Could not find network {name}."),
+                ))
+            }
+        },
+        None => state.networks.iter().collect(),
+    };
+
+    if let Some(channel) = channel {
+        if !key.channel(channel, state).await {
+            return Err(api_error(
+                Status::Forbidden,
+                &format!("The key can't act on channel {channel}."),
+            ));
+        }
+    }
+
+    // Subscribe before answering, so that nothing happening from now on is missed.
+    let mut events = select_all(networks.into_iter().map(|network| {
+        unfold(network.activity.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    // Events dropped while the client was not keeping up are skipped.
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed()
+    }));
+    let channel = channel.map(String::from);
+    let key = key.key;
+
+    Ok(EventStream! {
+        loop {
+            let event = tokio::select! {
+                event = events.next() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = &mut shutdown => break,
+            };
+            let visible = match event.target.is_empty() {
+                true => channel.is_none() && key.channels.is_empty(),
+                false => {
+                    activity::is_channel(&event.target)
+                        && key.allows_channel(&event.target)
+                        && channel
+                            .as_ref()
+                            .is_none_or(|channel| channel.eq_ignore_ascii_case(&event.target))
+                }
+            };
+
+            if visible {
+                yield StreamEvent::json(&event);
+            }
+        }
+    })
+}
+
+#[utoipa::path(
+    tag = "messages",
+    request_body = Message,
+    responses(
+        (status = 200, description = "Success or Failure", body = String),
+        (status = 401, description = "Missing, invalid or expired key", body = ApiError),
+        (status = 403, description = "Key without the scope or channel", body = ApiError)
+    ),
+    security(("api_key" = []))
+)]
 #[post("/say", format = "application/json", data = "<message>")]
-pub async fn say(message: Json<Message>, _key: ApiKey, state: &State<BotState>) -> &'static str {
-    if state
-        .client
-        .lock()
-        .await
-        .send(Command::PRIVMSG(
-            message.channel.clone(),
-            message.body.clone(),
-        ))
+pub async fn say(
+    message: Json<Message>,
+    key: ApiKey<Say>,
+    state: &State<BotState>,
+) -> &'static str {
+    if !key.channel(&message.channel, state).await {
+        return "Failure";
+    }
+
+    let network = match state.network(message.network.as_deref()) {
+        Some(network) => network,
+        None => return "Failure",
+    };
+
+    if network
+        .queue
+        .send(&message.channel, &message.body, Priority::Normal)
         .is_err()
     {
         return "Failure";
@@ -429,3 +2063,165 @@ pub async fn say(message: Json<Message>, _key: ApiKey, state: &State<BotState>)
 
     "Success"
 }
+
+// Renders the JSON posted to a hook defined in config.toml into messages for its channels. Hooks
+// are authenticated by their own signature instead of an API key.
+#[utoipa::path(
+    tag = "hooks",
+    request_body(content = Object, description = "Any JSON payload, signed with the secret of the hook"),
+    responses(
+        (status = 200, description = "Sent to the channels of the hook"),
+        (status = 400, description = "Body is not JSON"),
+        (status = 401, description = "Missing or invalid signature"),
+        (status = 404, description = "No such hook"),
+        (status = 413, description = "Body too large"),
+        (status = 500, description = "No such network")
+    )
+)]
+#[post("/hooks/<name>", data = "<body>")]
+pub async fn hook(name: &str, headers: Headers, body: Data<'_>, state: &State<BotState>) -> Status {
+    let hook = match state.hooks.iter().find(|h| h.name == name) {
+        Some(hook) => hook,
+        None => return Status::NotFound,
+    };
+    let max_size = match state.options.get("hook_max_size") {
+        Some(max_size) => max_size.parse().unwrap_or(hooks::DEFAULT_MAX_SIZE),
+        None => hooks::DEFAULT_MAX_SIZE,
+    };
+    let body = match body.open(max_size.bytes()).into_bytes().await {
+        Ok(body) if body.is_complete() => body.into_inner(),
+        Ok(_) => return Status::PayloadTooLarge,
+        Err(_) => return Status::BadRequest,
+    };
+
+    if !hook.verify(
+        headers
+            .0
+            .get(&hook.signature_header.to_lowercase())
+            .map(|s| s.as_str()),
+        &body,
+    ) {
+        return Status::Unauthorized;
+    }
+
+    let payload: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(_) => return Status::BadRequest,
+    };
+    let network = match state.network(hook.network.as_deref()) {
+        Some(network) => network,
+        None => return Status::InternalServerError,
+    };
+
+    for line in hooks::render(&hook.template, &payload) {
+        for channel in &hook.channels {
+            if network
+                .queue
+                .send(channel, &line, Priority::Normal)
+                .is_err()
+            {
+                return Status::InternalServerError;
+            }
+        }
+    }
+
+    Status::Ok
+}
+
+admin_routes!(
+    Driver,
+    "drivers",
+    "/drivers",
+    "/drivers?<offset>&<limit>",
+    "/drivers/<id>",
+    drivers,
+    driver,
+    create_driver,
+    replace_driver,
+    patch_driver,
+    remove_driver
+);
+
+admin_routes!(
+    Bet,
+    "results",
+    "/results",
+    "/results?<offset>&<limit>",
+    "/results/<id>",
+    race_results,
+    race_result,
+    create_race_result,
+    replace_race_result,
+    patch_race_result,
+    remove_race_result
+);
+
+admin_routes!(
+    TrainSchedule,
+    "train_schedules",
+    "/train_schedules",
+    "/train_schedules?<offset>&<limit>",
+    "/train_schedules/<id>",
+    train_schedules,
+    train_schedule,
+    create_train_schedule,
+    replace_train_schedule,
+    patch_train_schedule,
+    remove_train_schedule
+);
+
+admin_routes!(
+    Answer,
+    "answers",
+    "/answers",
+    "/answers?<offset>&<limit>",
+    "/answers/<id>",
+    answers,
+    answer,
+    create_answer,
+    replace_answer,
+    patch_answer,
+    remove_answer
+);
+
+admin_routes!(
+    Interest,
+    "interests",
+    "/interests",
+    "/interests?<offset>&<limit>",
+    "/interests/<id>",
+    interests,
+    interest,
+    create_interest,
+    replace_interest,
+    patch_interest,
+    remove_interest
+);
+
+admin_routes!(
+    TimeZone,
+    "time_zones",
+    "/time_zones",
+    "/time_zones?<offset>&<limit>",
+    "/time_zones/<id>",
+    time_zones,
+    time_zone,
+    create_time_zone,
+    replace_time_zone,
+    patch_time_zone,
+    remove_time_zone
+);
+
+admin_routes!(
+    WeatherSetting,
+    "weather_settings",
+    "/weather_settings",
+    "/weather_settings?<offset>&<limit>",
+    "/weather_settings/<id>",
+    weather_settings,
+    weather_setting,
+    create_weather_setting,
+    replace_weather_setting,
+    patch_weather_setting,
+    remove_weather_setting
+);
@@ -0,0 +1,20 @@
+use rocket::response::content::{RawCss, RawHtml, RawJavaScript};
+
+// The admin dashboard is built into the binary, so it's served whatever the working directory and
+// doesn't need the static/ folder. It only uses the routes of the API, with the key given on the
+// login form.
+
+#[get("/admin")]
+pub fn index() -> RawHtml<&'static str> {
+    RawHtml(include_str!("../../admin/index.html"))
+}
+
+#[get("/admin/admin.js")]
+pub fn script() -> RawJavaScript<&'static str> {
+    RawJavaScript(include_str!("../../admin/admin.js"))
+}
+
+#[get("/admin/admin.css")]
+pub fn style() -> RawCss<&'static str> {
+    RawCss(include_str!("../../admin/admin.css"))
+}
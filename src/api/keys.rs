@@ -0,0 +1,288 @@
+use crate::database::{CsvRecord, Database};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use rand::distributions::Alphanumeric;
+use rand::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+pub const SCOPES: [&str; 7] = [
+    "events:write",
+    "quotes:write",
+    "say",
+    "bets:read",
+    "stream:read",
+    "logs:read",
+    "admin",
+];
+const KEY_PREFIX: &str = "gluon_";
+const KEY_LENGTH: usize = 40;
+const MIN_LEGACY_KEY_SIZE: usize = 32;
+
+// A key of the API. Only the SHA-256 of the key is stored, the key itself is shown once when
+// created.
+#[derive(PartialEq)]
+pub struct Key {
+    pub name: String,
+    pub hash: String,
+    pub scopes: Vec<String>,
+    // Channels the key may act on, any channel when empty.
+    pub channels: Vec<String>,
+    pub expires: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+}
+
+impl CsvRecord for Key {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            name: fields[0].clone(),
+            hash: fields[1].clone(),
+            scopes: fields[2].split_whitespace().map(String::from).collect(),
+            channels: fields[3].split_whitespace().map(String::from).collect(),
+            expires: fields[4].parse().ok(),
+            created: fields[5].parse().unwrap_or_default(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.hash.clone(),
+            self.scopes.join(" "),
+            self.channels.join(" "),
+            match self.expires {
+                Some(expires) => expires.to_string(),
+                None => String::new(),
+            },
+            self.created.to_string(),
+        ]
+    }
+}
+
+impl Key {
+    // The admin scope grants every other scope.
+    pub fn allows(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope || s == "admin")
+    }
+
+    pub fn allows_channel(&self, channel: &str) -> bool {
+        self.channels.is_empty()
+            || self
+                .channels
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(channel))
+    }
+
+    pub fn expired(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.expires, Some(expires) if expires <= now)
+    }
+}
+
+// Every use of a key, allowed or not.
+#[derive(PartialEq, Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub datetime: DateTime<Utc>,
+    pub key: String,
+    pub route: String,
+    pub address: String,
+    pub outcome: String,
+}
+
+impl CsvRecord for AuditEntry {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            datetime: fields[0].parse().unwrap_or_default(),
+            key: fields[1].clone(),
+            route: fields[2].clone(),
+            address: fields[3].clone(),
+            outcome: fields[4].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.datetime.to_string(),
+            self.key.clone(),
+            self.route.clone(),
+            self.address.clone(),
+            self.outcome.clone(),
+        ]
+    }
+}
+
+pub fn hash(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+// Parses an expiry given as a date (the key expires at the end of that day) or a date time.
+pub fn parse_expiry(expires: &str) -> Option<DateTime<Utc>> {
+    match NaiveDate::parse_from_str(expires, "%Y-%m-%d") {
+        Ok(date) => date
+            .succ_opt()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|datetime| Utc.from_utc_datetime(&datetime)),
+        Err(_) => expires.parse().ok(),
+    }
+}
+
+// Creates a key and returns it. This is the only time the key can be seen.
+pub async fn create(
+    name: &str,
+    scopes: &[String],
+    channels: &[String],
+    expires: Option<DateTime<Utc>>,
+    db: &Arc<Mutex<Database>>,
+) -> Result<String, String> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(String::from("Invalid key name."));
+    }
+
+    if scopes.is_empty() {
+        return Err(format!("Choose at least one scope: {}.", SCOPES.join(", ")));
+    }
+
+    if let Some(scope) = scopes.iter().find(|s| !SCOPES.contains(&s.as_str())) {
+        return Err(format!(
+            "Invalid scope {scope}. Valid scopes: {}.",
+            SCOPES.join(", ")
+        ));
+    }
+
+    let db = db.lock().await;
+
+    match db.select("api_keys", |k: &Key| k.name == name) {
+        Ok(None) => (),
+        Ok(Some(_)) => return Err(format!("Key {name} already exists.")),
+        Err(_) => return Err(String::from("Could not check the keys.")),
+    }
+
+    let key = format!(
+        "{KEY_PREFIX}{}",
+        thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(KEY_LENGTH)
+            .map(char::from)
+            .collect::<String>()
+    );
+
+    if db
+        .insert(
+            "api_keys",
+            Key {
+                name: String::from(name),
+                hash: hash(&key),
+                scopes: scopes.to_vec(),
+                channels: channels.to_vec(),
+                expires,
+                created: Utc::now(),
+            },
+        )
+        .is_err()
+    {
+        return Err(String::from("Could not store the key."));
+    }
+
+    Ok(key)
+}
+
+pub async fn revoke(name: &str, db: &Arc<Mutex<Database>>) -> Result<(), String> {
+    let db = db.lock().await;
+
+    match db.select("api_keys", |k: &Key| k.name == name) {
+        Ok(Some(_)) => (),
+        Ok(None) => return Err(format!("Could not find key {name}.")),
+        Err(_) => return Err(String::from("Could not check the keys.")),
+    }
+
+    match db.delete("api_keys", |k: &&Key| k.name == name) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(String::from("Could not revoke the key.")),
+    }
+}
+
+pub async fn list(db: &Arc<Mutex<Database>>) -> Vec<Key> {
+    db.lock()
+        .await
+        .select("api_keys", |_: &Key| true)
+        .unwrap_or_default()
+        .unwrap_or_default()
+}
+
+pub async fn find(key: &str, db: &Arc<Mutex<Database>>) -> Option<Key> {
+    let hash = hash(key);
+
+    db.lock()
+        .await
+        .select("api_keys", |k: &Key| k.hash == hash)
+        .unwrap_or_default()
+        .and_then(|keys| keys.into_iter().next())
+}
+
+pub async fn audit(
+    key: &str,
+    route: &str,
+    address: &str,
+    outcome: &str,
+    db: &Arc<Mutex<Database>>,
+) {
+    if let Err(error) = db.lock().await.append(
+        "api_audit",
+        AuditEntry {
+            datetime: Utc::now(),
+            key: String::from(key),
+            route: String::from(route),
+            address: String::from(address),
+            outcome: String::from(outcome),
+        },
+    ) {
+        eprintln!("{error}");
+    }
+}
+
+// Moves the plaintext keys of the old api_keys.txt file into the key store as admin keys, then
+// renames the file so that it isn't read again. The renamed file should be deleted once the keys
+// are known to work.
+pub async fn import_legacy(path: &str, db: &Arc<Mutex<Database>>) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+    let existing = list(db).await;
+    let mut imported = 0;
+
+    for (index, line) in contents.lines().enumerate() {
+        if line.len() < MIN_LEGACY_KEY_SIZE
+            || line.contains(' ')
+            || existing.iter().any(|k| k.hash == hash(line))
+        {
+            continue;
+        }
+
+        if let Err(error) = db.lock().await.insert(
+            "api_keys",
+            Key {
+                name: format!("legacy{}", index + 1),
+                hash: hash(line),
+                scopes: vec![String::from("admin")],
+                channels: Vec::new(),
+                expires: None,
+                created: Utc::now(),
+            },
+        ) {
+            eprintln!("{error}");
+
+            return;
+        }
+
+        imported += 1;
+    }
+
+    if let Err(error) = fs::rename(path, format!("{path}.imported")) {
+        eprintln!("Could not rename {path}: {error}");
+    }
+
+    println!("Imported {imported} API keys from {path}, which was renamed to {path}.imported.");
+}
@@ -0,0 +1,187 @@
+use super::*;
+use crate::activity::{ActivityEvent, ActivityKind};
+use crate::status::{ChannelStatus, FeedPoll, TaskState};
+use rocket::response::content::RawHtml;
+use utoipa::openapi::security::{ApiKey as ApiKeyScheme, ApiKeyValue, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+// The OpenAPI document of every route under /api, generated from the route attributes and the
+// types they take and return.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "gluon_bot API"),
+    servers((url = "/api")),
+    paths(
+        events,
+        event,
+        create_event,
+        replace_event,
+        patch_event,
+        remove_event,
+        add_event,
+        delete_event,
+        update_event,
+        quotes,
+        quote,
+        create_quote,
+        replace_quote,
+        patch_quote,
+        remove_quote,
+        add_quote,
+        delete_quote,
+        update_quote,
+        feeds,
+        feed,
+        create_feed,
+        replace_feed,
+        patch_feed,
+        remove_feed,
+        f1_bets,
+        f1_bet,
+        create_f1_bet,
+        replace_f1_bet,
+        patch_f1_bet,
+        remove_f1_bet,
+        score_f1_bets,
+        say,
+        stream,
+        logs,
+        karma,
+        status,
+        audit,
+        hook,
+        drivers,
+        driver,
+        create_driver,
+        replace_driver,
+        patch_driver,
+        remove_driver,
+        race_results,
+        race_result,
+        create_race_result,
+        replace_race_result,
+        patch_race_result,
+        remove_race_result,
+        train_schedules,
+        train_schedule,
+        create_train_schedule,
+        replace_train_schedule,
+        patch_train_schedule,
+        remove_train_schedule,
+        answers,
+        answer,
+        create_answer,
+        replace_answer,
+        patch_answer,
+        remove_answer,
+        interests,
+        interest,
+        create_interest,
+        replace_interest,
+        patch_interest,
+        remove_interest,
+        time_zones,
+        time_zone,
+        create_time_zone,
+        replace_time_zone,
+        patch_time_zone,
+        remove_time_zone,
+        weather_settings,
+        weather_setting,
+        create_weather_setting,
+        replace_weather_setting,
+        patch_weather_setting,
+        remove_weather_setting,
+    ),
+    components(schemas(
+        ActivityEvent,
+        ActivityKind,
+        ApiError,
+        Answer,
+        AuditEntry,
+        Bet,
+        ChannelStatus,
+        Driver,
+        Event,
+        F1BetScore,
+        Feed,
+        FeedPoll,
+        Interest,
+        Karma,
+        LogLine,
+        Message,
+        Quote,
+        StatusReport,
+        TaskState,
+        TimeZone,
+        TrainSchedule,
+        WeatherSetting,
+    )),
+    modifiers(&KeyHeader),
+    tags(
+        (name = "events", description = "Events announced by the bot"),
+        (name = "quotes", description = "Quotes of the channels"),
+        (name = "feeds", description = "RSS/Atom feeds sent to the channels"),
+        (name = "f1bets", description = "F1 bets and their scores"),
+        (name = "messages", description = "Messages sent and seen by the bot"),
+        (name = "status", description = "Status of the networks"),
+        (name = "logs", description = "Messages of the logged channels"),
+        (name = "karma", description = "Karma given on the channels with thing++ and thing--"),
+        (name = "hooks", description = "Inbound webhooks"),
+        (name = "drivers", description = "F1 drivers bets can be placed on"),
+        (name = "results", description = "F1 race results the bets are scored on"),
+        (name = "train_schedules", description = "Trains of the train game"),
+        (name = "answers", description = "Answers of the ask command"),
+        (name = "interests", description = "Tags of the events each user is notified of"),
+        (name = "time_zones", description = "Time zone of each user"),
+        (name = "weather_settings", description = "Location of each user for the weather command"),
+    )
+)]
+pub struct ApiDoc;
+
+// Keys are sent in the x-api-key header, see keys.rs for their scopes.
+struct KeyHeader;
+
+impl Modify for KeyHeader {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKeyScheme::Header(ApiKeyValue::with_description(
+                    "x-api-key",
+                    &format!(
+                        "A key created with --create-key. Scopes: {}.",
+                        keys::SCOPES.join(", ")
+                    ),
+                ))),
+            );
+        }
+    }
+}
+
+#[get("/openapi.json")]
+pub fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+// Swagger UI, loaded from a CDN, browsing /api/openapi.json.
+#[get("/docs")]
+pub fn docs() -> RawHtml<&'static str> {
+    RawHtml(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>gluon_bot API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="docs"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#docs", persistAuthorization: true });
+  </script>
+</body>
+</html>"##,
+    )
+}
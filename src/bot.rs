@@ -0,0 +1,646 @@
+use crate::activity::Activity;
+use crate::clock::Clock;
+use crate::commands::plugin::Plugins;
+use crate::commands::seen::{self, PendingTells};
+use crate::commands::BotCommand;
+use crate::commands::{karma, log};
+use crate::database::Database;
+use crate::network::{self, Network, NetworkHandle};
+use crate::paste;
+use crate::queue::{MessageQueue, Priority, QueueReceiver};
+use crate::status::{BotStatus, TaskState};
+use crate::tasks;
+use crate::tasks::plugins::PluginEvent;
+use crate::transport::console::ConsoleTransport;
+use crate::transport::{self, Incoming, IncomingKind, Transport};
+use crate::utils;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::Mutex;
+use tokio::task;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+// Keeps a network connected, reconnecting whenever the connection to it is lost.
+pub async fn run(
+    mut network: Network,
+    handle: NetworkHandle,
+    queue_receiver: Arc<Mutex<QueueReceiver>>,
+    db: Arc<Mutex<Database>>,
+    clock: Clock,
+    pending_tells: PendingTells,
+) {
+    let queue = handle.queue.clone();
+    let status = Arc::clone(&handle.status);
+
+    loop {
+        let transport = match handle.transport.lock().await.clone() {
+            Some(transport) => transport,
+            None => return,
+        };
+        let options = Arc::new(network.config.options.clone());
+        let owners = Arc::new(network.config.owners.clone());
+        let data = Arc::new(network.data.clone());
+        let plugins = Arc::new(Plugins::load(&options));
+
+        println!("Connected to {}.", network.name);
+        status.lock().await.set_connected(true);
+
+        // Spawn the outgoing task.
+        let options_clone = Arc::clone(&options);
+        let transport_clone = Arc::clone(&transport);
+        let queue_receiver_clone = Arc::clone(&queue_receiver);
+        let activity_clone = handle.activity.clone();
+        let clock_clone = clock.clone();
+        let status_clone = Arc::clone(&status);
+        let outgoing_token = CancellationToken::new();
+        let outgoing_token_clone = outgoing_token.clone();
+        let outgoing_task = task::spawn(async move {
+            status_clone
+                .lock()
+                .await
+                .set_task("outgoing", TaskState::Running);
+            tasks::outgoing::outgoing(
+                transport_clone,
+                queue_receiver_clone,
+                options_clone,
+                activity_clone,
+                clock_clone,
+                outgoing_token_clone.clone(),
+            )
+            .await;
+            status_clone
+                .lock()
+                .await
+                .finish_task("outgoing", outgoing_token_clone.is_cancelled());
+        });
+
+        // Spawn the next task.
+        let data_clone = Arc::clone(&data);
+        let options_clone = Arc::clone(&options);
+        let queue_clone = queue.clone();
+        let db_clone = Arc::clone(&db);
+        let clock_clone = clock.clone();
+        let next_token = CancellationToken::new();
+        let status_clone = Arc::clone(&status);
+        let next_token_clone = next_token.clone();
+        let next_task = task::spawn(async move {
+            status_clone
+                .lock()
+                .await
+                .set_task("next", TaskState::Running);
+            tasks::next::next(
+                data_clone.to_string(),
+                options_clone,
+                queue_clone,
+                db_clone,
+                clock_clone,
+                next_token_clone.clone(),
+            )
+            .await;
+            status_clone
+                .lock()
+                .await
+                .finish_task("next", next_token_clone.is_cancelled());
+        });
+
+        // Spawn the feeds task.
+        let data_clone = Arc::clone(&data);
+        let options_clone = Arc::clone(&options);
+        let queue_clone = queue.clone();
+        let db_clone = Arc::clone(&db);
+        let status_clone = Arc::clone(&status);
+        let feeds_token = CancellationToken::new();
+        let feeds_token_clone = feeds_token.clone();
+        let feeds_task = task::spawn(async move {
+            status_clone
+                .lock()
+                .await
+                .set_task("feeds", TaskState::Running);
+            tasks::feeds::feeds(
+                data_clone.to_string(),
+                options_clone,
+                queue_clone,
+                db_clone,
+                Arc::clone(&status_clone),
+                feeds_token_clone.clone(),
+            )
+            .await;
+            status_clone
+                .lock()
+                .await
+                .finish_task("feeds", feeds_token_clone.is_cancelled());
+        });
+
+        // Spawn the train_game task.
+        let data_clone = Arc::clone(&data);
+        let options_clone = Arc::clone(&options);
+        let queue_clone = queue.clone();
+        let db_clone = Arc::clone(&db);
+        let clock_clone = clock.clone();
+        let status_clone = Arc::clone(&status);
+        let train_game_token = CancellationToken::new();
+        let train_game_token_clone = train_game_token.clone();
+        let train_game_task = task::spawn(async move {
+            status_clone
+                .lock()
+                .await
+                .set_task("train_game", TaskState::Running);
+
+            let train_game = tasks::train_game::TrainGame::new(
+                data_clone.to_string(),
+                options_clone,
+                queue_clone,
+                db_clone,
+                clock_clone,
+                train_game_token_clone.clone(),
+            )
+            .await;
+
+            train_game.run().await;
+            status_clone
+                .lock()
+                .await
+                .finish_task("train_game", train_game_token_clone.is_cancelled());
+        });
+
+        // Spawn the plugins task.
+        let data_clone = Arc::clone(&data);
+        let plugins_clone = Arc::clone(&plugins);
+        let options_clone = Arc::clone(&options);
+        let queue_clone = queue.clone();
+        let clock_clone = clock.clone();
+        let (events, events_receiver) = mpsc::unbounded_channel();
+        let status_clone = Arc::clone(&status);
+        let plugins_token = CancellationToken::new();
+        let plugins_token_clone = plugins_token.clone();
+        let plugins_task = task::spawn(async move {
+            status_clone
+                .lock()
+                .await
+                .set_task("plugins", TaskState::Running);
+            tasks::plugins::plugins(
+                data_clone.to_string(),
+                plugins_clone,
+                options_clone,
+                queue_clone,
+                clock_clone,
+                events_receiver,
+                plugins_token_clone.clone(),
+            )
+            .await;
+            status_clone
+                .lock()
+                .await
+                .finish_task("plugins", plugins_token_clone.is_cancelled());
+        });
+
+        // Spawn the logs task.
+        let data_clone = Arc::clone(&data);
+        let options_clone = Arc::clone(&options);
+        let db_clone = Arc::clone(&db);
+        let clock_clone = clock.clone();
+        let status_clone = Arc::clone(&status);
+        let logs_token = CancellationToken::new();
+        let logs_token_clone = logs_token.clone();
+        let logs_task = task::spawn(async move {
+            status_clone
+                .lock()
+                .await
+                .set_task("logs", TaskState::Running);
+            tasks::logs::logs(
+                data_clone.to_string(),
+                options_clone,
+                db_clone,
+                clock_clone,
+                logs_token_clone.clone(),
+            )
+            .await;
+            status_clone
+                .lock()
+                .await
+                .finish_task("logs", logs_token_clone.is_cancelled());
+        });
+
+        // Main loop that continously gets messages from the transport.
+        let session = Session {
+            options: Arc::clone(&options),
+            owners: Arc::clone(&owners),
+            data: Arc::clone(&data),
+            plugins: Arc::clone(&plugins),
+            db: Arc::clone(&db),
+            clock: clock.clone(),
+            transport: Arc::clone(&transport),
+            queue: queue.clone(),
+            status: Arc::clone(&status),
+            events,
+            activity: handle.activity.clone(),
+            pending_tells: pending_tells.clone(),
+        };
+
+        // Spawn the external_message task, which needs the session to run commands.
+        let name_clone = network.name.clone();
+        let session_clone = session.clone();
+        let external_message_token = CancellationToken::new();
+        let status_clone = Arc::clone(&status);
+        let external_message_token_clone = external_message_token.clone();
+        let external_message_task = task::spawn(async move {
+            status_clone
+                .lock()
+                .await
+                .set_task("external_message", TaskState::Running);
+            tasks::base::external_message(
+                name_clone,
+                session_clone,
+                external_message_token_clone.clone(),
+            )
+            .await;
+            status_clone.lock().await.finish_task(
+                "external_message",
+                external_message_token_clone.is_cancelled(),
+            );
+        });
+
+        while let Some(incoming) = transport.receive().await {
+            dispatch(incoming, &session);
+        }
+
+        eprintln!("Diconnected from {}.", network.name);
+        *handle.transport.lock().await = None;
+        status.lock().await.set_connected(false);
+
+        // Cancel the outgoing task.
+        // If the task doesn't finish, terminate the bot.
+        outgoing_token.cancel();
+
+        if outgoing_task.await.is_err() {
+            eprintln!("Could not cancel outgoing task.");
+            eprintln!("Terminating bot...");
+
+            return;
+        }
+
+        eprintln!("Outgoing task finished.");
+
+        // Cancel the next task.
+        // If the task doesn't finish, terminate the bot.
+        next_token.cancel();
+
+        if next_task.await.is_err() {
+            eprintln!("Could not cancel next task.");
+            eprintln!("Terminating bot...");
+
+            return;
+        }
+
+        eprintln!("Next task finished.");
+
+        // Cancel the external_message task.
+        // If the task doesn't finish, terminate the bot.
+        external_message_token.cancel();
+
+        if external_message_task.await.is_err() {
+            eprintln!("Could not cancel external_message task.");
+            eprintln!("Terminating bot...");
+
+            return;
+        }
+
+        eprintln!("External Message task finished.");
+
+        // Cancel the feeds task.
+        // If the task doesn't finish, terminate the bot.
+        feeds_token.cancel();
+
+        if feeds_task.await.is_err() {
+            eprintln!("Could not cancel feeds task.");
+            eprintln!("Terminating bot...");
+
+            return;
+        }
+
+        eprintln!("Feeds task finished.");
+
+        // Cancel the train_game task.
+        // If the task doesn't finish, terminate the bot.
+        train_game_token.cancel();
+
+        if train_game_task.await.is_err() {
+            eprintln!("Could not cancel train_game task.");
+            eprintln!("Terminating bot...");
+
+            return;
+        }
+
+        eprintln!("Train game task finished.");
+
+        // Cancel the plugins task.
+        // If the task doesn't finish, terminate the bot.
+        plugins_token.cancel();
+
+        if plugins_task.await.is_err() {
+            eprintln!("Could not cancel plugins task.");
+            eprintln!("Terminating bot...");
+
+            return;
+        }
+
+        eprintln!("Plugins task finished.");
+
+        // Cancel the logs task.
+        // If the task doesn't finish, terminate the bot.
+        logs_token.cancel();
+
+        if logs_task.await.is_err() {
+            eprintln!("Could not cancel logs task.");
+            eprintln!("Terminating bot...");
+
+            return;
+        }
+
+        eprintln!("Logs task finished.");
+
+        // Wait 30 seconds before trying to reconnect.
+        // This should avoid an overly fast reconnect.
+        println!(
+            "Waiting 30 seconds before reconnecting to {}...",
+            network.name
+        );
+        time::sleep(Duration::from_secs(30)).await;
+        println!("Reconnecting to {}...", network.name);
+
+        // Reload the settings of this network, so that changes to the config file are picked up.
+        network = match network::load("config.toml") {
+            Ok(networks) => match networks.into_iter().find(|n| n.name == network.name) {
+                Some(network) => network,
+                None => {
+                    eprintln!("Could not find network {} in config.toml.", network.name);
+
+                    return;
+                }
+            },
+            Err(error) => {
+                eprintln!("{error}");
+
+                return;
+            }
+        };
+        *handle.transport.lock().await = match transport::connect(&network).await {
+            Ok(transport) => Some(transport),
+            Err(error) => {
+                eprintln!("{error}");
+
+                return;
+            }
+        };
+    }
+}
+
+// Everything needed to handle the messages received on a connection.
+#[derive(Clone)]
+pub struct Session {
+    pub options: Arc<HashMap<String, String>>,
+    pub owners: Arc<Vec<String>>,
+    pub data: Arc<String>,
+    pub plugins: Arc<Plugins>,
+    pub db: Arc<Mutex<Database>>,
+    pub clock: Clock,
+    pub transport: Arc<dyn Transport>,
+    pub queue: MessageQueue,
+    pub status: Arc<Mutex<BotStatus>>,
+    // Events for the events plugins.
+    pub events: UnboundedSender<PluginEvent>,
+    // Everything seen and sent, for /api/stream.
+    pub activity: Activity,
+    // Nicks with messages left by tell, shared by every network.
+    pub pending_tells: PendingTells,
+}
+
+impl Session {
+    // Runs the bot command in message as sent by nick to target, giving the output to send back.
+    // None when the message is not a bot command.
+    pub async fn command(
+        &self,
+        message: &str,
+        nick: Option<String>,
+        target: &str,
+    ) -> Option<String> {
+        let prefix = match self.options.get("prefix") {
+            Some(prefix) => prefix,
+            None => "!",
+        };
+
+        if message.len() <= 1 || !message.starts_with(prefix) {
+            return None;
+        }
+
+        let bot_command = match BotCommand::new(
+            message,
+            nick,
+            target,
+            &self.data,
+            &self.options,
+            &self.owners,
+            &self.plugins,
+        ) {
+            Ok(bot_command) => bot_command,
+            Err(_) => return None,
+        };
+        let output = match time::timeout(
+            Duration::from_secs(bot_command.timeout),
+            bot_command.handle(
+                Arc::clone(&self.db),
+                self.clock.clone(),
+                Arc::clone(&self.transport),
+                self.queue.clone(),
+                Arc::clone(&self.status),
+                self.pending_tells.clone(),
+            ),
+        )
+        .await
+        {
+            Ok(output) => output,
+            Err(_) => String::from("Timeout while running command."),
+        };
+
+        Some(paste::overflow(output, &self.options, Arc::clone(&self.db)).await)
+    }
+}
+
+// Every message and event is passed on to the events plugins and to the activity stream, and
+// recorded for the seen command by a Tokio task that also delivers the messages left with tell.
+// If the message is a bot command, spawn a Tokio task to handle the command.
+// Otherwise spawn a Tokio task to log the message if its channel is logged, to apply any karma vote
+// and to show the title of any URL in the message.
+fn dispatch(incoming: Incoming, session: &Session) -> task::JoinHandle<()> {
+    // Nobody is listening when there are no events plugins, which is fine.
+    let _ = session
+        .events
+        .send(PluginEvent::new(&incoming, &session.data, &session.clock));
+
+    let prefix = match session.options.get("prefix") {
+        Some(prefix) => prefix,
+        None => "!",
+    };
+
+    session.activity.incoming(&incoming, prefix, &session.clock);
+
+    let seen_task = {
+        let session = session.clone();
+        let incoming = incoming.clone();
+
+        task::spawn(async move {
+            seen::record(
+                &incoming,
+                &session.data,
+                &session.clock,
+                &session.db,
+                &session.queue,
+                &session.pending_tells,
+            )
+            .await;
+        })
+    };
+
+    if incoming.kind != IncomingKind::Message {
+        return seen_task;
+    }
+    let nick = incoming.nick;
+    let target = incoming.target;
+    let message = incoming.text;
+
+    if message.len() > 1 && message.starts_with(prefix) {
+        let session = session.clone();
+
+        task::spawn(async move {
+            if let Some(output) = session.command(&message, nick, &target).await {
+                if let Err(error) = session.queue.send(&target, &output, Priority::Normal) {
+                    eprintln!("{error}");
+                }
+            }
+        })
+    } else {
+        let session = session.clone();
+
+        task::spawn(async move {
+            if let Some(nick) = &nick {
+                if log::logged(&target, &session.options) {
+                    log::record(
+                        nick,
+                        &target,
+                        &message,
+                        &session.data,
+                        &session.clock,
+                        &session.db,
+                    )
+                    .await;
+                }
+
+                if let Some(answer) = karma::give(
+                    nick,
+                    &target,
+                    &message,
+                    &session.data,
+                    &session.options,
+                    &session.clock,
+                    &session.db,
+                )
+                .await
+                {
+                    if let Err(error) = session.queue.send(&target, &answer, Priority::Normal) {
+                        eprintln!("{error}");
+                    }
+                }
+            }
+
+            if let Some(url) = utils::find_url(&message) {
+                if url.to_lowercase().contains("youtube.com")
+                    || url.to_lowercase().contains("youtu.be")
+                {
+                    if let Some(video_id) = utils::extract_video_id(url) {
+                        if let Ok(Some(video_data)) = utils::youtube_data(
+                            match session.options.get("youtube_api_url") {
+                                Some(api_url) => api_url,
+                                None => utils::YOUTUBE_API_BASE,
+                            },
+                            session
+                                .options
+                                .get("youtube_api_key")
+                                .unwrap_or(&String::from("")),
+                            &video_id,
+                        )
+                        .await
+                        {
+                            if let Err(error) =
+                                session.queue.send(&target, &video_data, Priority::Normal)
+                            {
+                                eprint!("{error}");
+                            }
+                        }
+                    }
+                } else if let Ok(Some(title)) = utils::find_title(url).await {
+                    if let Err(error) = session.queue.send(&target, &title, Priority::Normal) {
+                        eprint!("{error}");
+                    }
+                }
+            }
+        })
+    }
+}
+
+// Runs each line read from stdin through the same dispatch as a network, as sent by nick on
+// channel. Commands run one at a time and whatever they send is printed as soon as they finish.
+pub async fn console(
+    network: Network,
+    db: Arc<Mutex<Database>>,
+    clock: Clock,
+    nick: &str,
+    channel: &str,
+) {
+    let max_chunk_size = match network.config.options.get("max_chunk_size") {
+        Some(max_chunk_size) => max_chunk_size.parse().unwrap_or(410),
+        None => 410,
+    };
+    let bot_nick = match &network.config.nickname {
+        Some(bot_nick) => bot_nick.clone(),
+        None => String::from("gluon_bot"),
+    };
+    let transport: Arc<dyn Transport> = Arc::new(ConsoleTransport::new(&bot_nick, nick, channel));
+    let (queue, mut queue_receiver) = MessageQueue::new();
+    let status = Arc::new(Mutex::new(BotStatus::new(&network.name)));
+
+    status.lock().await.set_connected(true);
+
+    let plugins = Arc::new(Plugins::load(&network.config.options));
+    // Events plugins don't run in the console.
+    let (events, _) = mpsc::unbounded_channel();
+    let pending_tells = PendingTells::load(&db).await;
+    let session = Session {
+        options: Arc::new(network.config.options),
+        owners: Arc::new(network.config.owners),
+        data: Arc::new(network.data),
+        plugins,
+        db,
+        clock,
+        transport: Arc::clone(&transport),
+        queue,
+        status,
+        events,
+        activity: Activity::new(&network.name),
+        pending_tells,
+    };
+
+    while let Some(incoming) = transport.receive().await {
+        if let Err(error) = dispatch(incoming, &session).await {
+            eprintln!("{error}");
+        }
+
+        while let Some(message) = queue_receiver.try_next(&bot_nick, max_chunk_size) {
+            if let Err(error) = transport.send(&message.target, &message.text).await {
+                eprintln!("{error}");
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// Time and randomness used by the games. The system clock is used while running, a pinned clock
+// lets tests and replays decide what time it is and makes every random draw repeatable.
+#[derive(Clone)]
+pub struct Clock {
+    pinned: Option<Arc<Mutex<Pinned>>>,
+    salt: Arc<String>,
+}
+
+struct Pinned {
+    now: DateTime<Utc>,
+    rng: StdRng,
+}
+
+impl Clock {
+    pub fn system(salt: &str) -> Self {
+        Self {
+            pinned: None,
+            salt: Arc::new(String::from(salt)),
+        }
+    }
+
+    pub fn pinned(now: DateTime<Utc>, salt: &str) -> Self {
+        Self::system(salt).pin(now)
+    }
+
+    // The system clock salted with the game_salt option.
+    pub fn from_options(options: &HashMap<String, String>) -> Self {
+        Self::system(match options.get("game_salt") {
+            Some(salt) => salt,
+            None => "",
+        })
+    }
+
+    // The same clock pinned to now.
+    pub fn pin(self, now: DateTime<Utc>) -> Self {
+        Self {
+            pinned: Some(Arc::new(Mutex::new(Pinned {
+                now,
+                rng: StdRng::seed_from_u64(seed(&self.salt)),
+            }))),
+            salt: self.salt,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        match &self.pinned {
+            Some(pinned) => match pinned.lock() {
+                Ok(pinned) => pinned.now,
+                Err(_) => Utc::now(),
+            },
+            None => Utc::now(),
+        }
+    }
+
+    // Moves a pinned clock to another time. The system clock can't be moved.
+    pub fn set(&self, now: DateTime<Utc>) {
+        if let Some(pinned) = &self.pinned {
+            if let Ok(mut pinned) = pinned.lock() {
+                pinned.now = now;
+            }
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.set(self.now() + duration);
+    }
+
+    // Generator giving the same numbers for a game on a given date. The seed includes the salt, so
+    // that players can't work out the numbers of a date in advance.
+    pub fn daily_rng(&self, game: &str, date: NaiveDate) -> StdRng {
+        StdRng::seed_from_u64(seed(&format!("{}:{game}:{date}", self.salt)))
+    }
+
+    // Generator for one-off random events. Generators of a pinned clock follow each other in the
+    // same order every run.
+    pub fn rng(&self) -> StdRng {
+        if let Some(pinned) = &self.pinned {
+            if let Ok(mut pinned) = pinned.lock() {
+                return StdRng::seed_from_u64(pinned.rng.gen());
+            }
+        }
+
+        StdRng::from_entropy()
+    }
+}
+
+// 64-bit FNV-1a hash, which unlike the hasher of the standard library is the same on every build.
+fn seed(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -4,16 +4,24 @@ pub mod f1bet;
 mod f1results;
 mod f1standings;
 mod first;
+pub mod karma;
+pub mod log;
 mod news;
 pub mod next;
 mod omdb;
-mod plugin;
+pub mod plugin;
 mod rates;
-mod weather;
+pub mod seen;
+pub mod weather;
 
+use crate::clock::Clock;
+use crate::commands::plugin::Plugins;
+use crate::commands::seen::PendingTells;
 use crate::database::Database;
+use crate::queue::MessageQueue;
+use crate::status::BotStatus;
 use crate::tasks::train_game;
-use irc::client::Client;
+use crate::transport::Transport;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -23,8 +31,11 @@ pub struct BotCommand<'a> {
     pub args: Vec<String>,
     pub nick: String,
     pub target: String,
+    pub network: &'a str,
     pub timeout: u64,
     pub options: &'a HashMap<String, String>,
+    pub owners: &'a [String],
+    pub plugins: &'a Plugins,
 }
 
 impl<'a> BotCommand<'a> {
@@ -32,7 +43,10 @@ impl<'a> BotCommand<'a> {
         message: &str,
         nick: Option<String>,
         target: &str,
+        network: &'a str,
         options: &'a HashMap<String, String>,
+        owners: &'a [String],
+        plugins: &'a Plugins,
     ) -> Result<Self, &'static str> {
         let split_message: Vec<&str> = message.split_ascii_whitespace().collect();
         let name = split_message[0][1..].to_lowercase();
@@ -44,7 +58,11 @@ impl<'a> BotCommand<'a> {
         let target = String::from(target);
         let timeout = match name.as_str() {
             "alarm" | "remind" | "reminder" => 86400,
-            _ => 30,
+            // Leave the plugin time to report its own timeout.
+            _ => match plugins.find(&name) {
+                Some(manifest) => manifest.timeout(options).saturating_add(1).max(30),
+                None => 30,
+            },
         };
 
         Ok(Self {
@@ -52,57 +70,154 @@ impl<'a> BotCommand<'a> {
             args,
             nick,
             target,
+            network,
             timeout,
             options,
+            owners,
+            plugins,
         })
     }
 
-    pub async fn handle(&self, db: Arc<Mutex<Database>>, client: Arc<Mutex<Client>>) -> String {
+    pub async fn handle(
+        &self,
+        db: Arc<Mutex<Database>>,
+        clock: Clock,
+        transport: Arc<dyn Transport>,
+        queue: MessageQueue,
+        status: Arc<Mutex<BotStatus>>,
+        pending_tells: PendingTells,
+    ) -> String {
         match &self.name[..] {
-            "alarm" => base::alarm(&self.args, &self.nick, &self.target, db, client).await,
+            "alarm" => {
+                base::alarm(
+                    &self.args,
+                    &self.nick,
+                    &self.target,
+                    self.network,
+                    &clock,
+                    db,
+                    queue,
+                    status,
+                )
+                .await
+            }
             "ask" => base::ask(&self.args, db).await,
-            //"bet" => f1bet::bet(&self.args, &self.nick, &self.target, self.options, db).await,
-            "board" => train_game::board(&self.nick, &self.target, &self.args, db).await,
+            //"bet" => f1bet::bet(&self.args, &self.nick, &self.target, self.network, self.options, &clock, db).await,
+            "board" => {
+                train_game::board(&self.nick, &self.target, &self.args, self.network, db).await
+            }
             "city" => city::city(&self.args, db).await,
             "date" | "time" => base::date_time().await,
-            "f1results" => f1results::f1results().await,
-            "f1standings" | "standings" | "wcc" | "wdc" => f1standings::f1standings().await,
+            "f1results" => f1results::f1results(self.options).await,
+            "f1standings" | "standings" | "wcc" | "wdc" => {
+                f1standings::f1standings(self.options).await
+            }
             "first" | "1st" => {
-                first::first(&self.nick, &self.target, self.options, db, client).await
+                first::first(
+                    &self.nick,
+                    &self.target,
+                    self.network,
+                    self.options,
+                    &clock,
+                    db,
+                    queue,
+                )
+                .await
+            }
+            "first_results" => {
+                first::first_results(&self.target, self.network, &clock, db, queue).await
             }
-            "first_results" => first::first_results(&self.target, db, client).await,
             "first_stats" | "first_points" => {
-                first::first_stats(&self.args, &self.target, db).await
+                first::first_stats(&self.args, &self.target, self.network, &clock, db).await
             }
             "hello" => base::hello(&self.nick).await,
-            "help" | "h" | "commands" => base::help().await,
+            "help" | "h" | "commands" => base::help(self.plugins).await,
             "imdb" | "omdb" => omdb::omdb(&self.args, self.options).await,
+            "grep" => log::grep(&self.args, &self.target, self.network, &clock, db).await,
             "interests" | "interested" | "i" | "notify" => {
-                next::interests(&self.args, &self.nick, db).await
+                next::interests(&self.args, &self.nick, self.network, db).await
+            }
+            "karma" => karma::karma(&self.args, &self.target, self.network, db).await,
+            "last" => log::last(&self.args, &self.target, self.network, &clock, db).await,
+            "log" => log::log(&self.args, &self.nick, self.network, db).await,
+            "news" => news::news(&self.args, &self.target, queue, self.options).await,
+            "next" | "n" => {
+                next::next(
+                    &self.args,
+                    &self.nick,
+                    &self.target,
+                    self.network,
+                    &clock,
+                    db,
+                )
+                .await
             }
-            "news" => news::news(&self.args, &self.target, client, self.options).await,
-            "next" | "n" => next::next(&self.args, &self.nick, &self.target, db).await,
             "ping" => base::ping().await,
-            //"points" | "wbc" => f1bet::points(false, self.options, db).await,
-            "quote" => base::quote(&self.args, &self.target, db).await,
+            "plugins" => plugin::plugins(&self.args, self.plugins).await,
+            //"points" | "wbc" => f1bet::points(false, self.network, self.options, db).await,
+            "quote" => {
+                base::quote(
+                    &self.args,
+                    &self.nick,
+                    &self.target,
+                    self.network,
+                    self.owners,
+                    self.options,
+                    &clock,
+                    transport,
+                    db,
+                )
+                .await
+            }
             "rates" => rates::rates(&self.args, self.options).await,
             "remind" | "reminder" => {
-                base::reminder(&self.args, &self.nick, &self.target, client).await
+                base::reminder(&self.args, &self.nick, &self.target, queue, status).await
             }
-            "timezone" | "tz" => base::time_zone(&self.args, &self.nick, db).await,
-            "tpoints" | "trainpoints" | "wtc" => train_game::points(db).await,
-            "trains" | "schedules" => train_game::schedules(db).await,
-            "weather" | "w" => weather::weather(&self.args, &self.nick, self.options, db).await,
-            _ => {
-                plugin::plugin(
-                    &self.name,
+            "seen" => seen::seen(&self.args, &self.target, self.network, &clock, db).await,
+            "status" => base::status(&self.nick, self.owners, transport, status).await,
+            "tell" => {
+                seen::tell(
                     &self.args,
                     &self.nick,
-                    &self.target,
-                    self.options,
+                    self.network,
+                    &clock,
+                    db,
+                    &pending_tells,
                 )
                 .await
             }
+            "timezone" | "tz" => base::time_zone(&self.args, &self.nick, self.network, db).await,
+            "tpoints" | "trainpoints" | "wtc" => train_game::points(self.network, db).await,
+            "trains" | "schedules" => train_game::schedules(self.network, db).await,
+            "weather" | "w" => {
+                weather::weather(&self.args, &self.nick, self.network, self.options, db).await
+            }
+            _ => match self.plugins.find(&self.name) {
+                Some(manifest) => {
+                    plugin::plugin(
+                        manifest,
+                        &self.args,
+                        &self.nick,
+                        &self.target,
+                        self.network,
+                        self.options,
+                        db,
+                        queue,
+                    )
+                    .await
+                }
+                None => {
+                    let prefix = match self.options.get("prefix") {
+                        Some(prefix) => prefix,
+                        None => "!",
+                    };
+
+                    format!(
+                        "Unknown command {}, see {prefix}help or {prefix}plugins.",
+                        self.name
+                    )
+                }
+            },
         }
     }
 }
@@ -1,105 +1,253 @@
-use crate::database::{CsvRecord, Database};
+use crate::clock::Clock;
+use crate::commands::plugin::Plugins;
+use crate::database::{CsvRecord, Database, Identified};
+use crate::network;
+use crate::queue::{MessageQueue, Priority};
+use crate::status::BotStatus;
+use crate::transport::Transport;
+use crate::webhook;
 use chrono::{DateTime, Datelike, Offset, Utc};
 use chrono_tz::Tz;
-use irc::client::prelude::Command;
-use irc::client::Client;
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::time;
+use utoipa::ToSchema;
 
-struct Answer {
-    answer: String,
+// Quotes shown at once by quote search and quote top.
+const QUOTES_SHOWN: usize = 3;
+
+#[derive(PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Answer {
+    // Stored in the last column.
+    #[serde(default)]
+    pub id: u64,
+    pub answer: String,
 }
 
 impl CsvRecord for Answer {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(1)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             answer: fields[0].clone(),
         }
     }
 
     fn to_fields(&self) -> Vec<String> {
-        vec![self.answer.clone()]
+        vec![self.answer.clone(), self.id.to_string()]
+    }
+}
+
+impl Identified for Answer {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
     }
 }
 
-#[derive(PartialEq, Serialize, Deserialize)]
+#[derive(PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Quote {
+    // Stored in the last column, after the network.
+    #[serde(default)]
+    pub id: u64,
     pub date: String,
     pub text: String,
     pub channel: String,
+    #[serde(default)]
+    pub network: String,
+    // The nick that added the quote and the nick that said it, stored after the id.
+    #[serde(default)]
+    pub added_by: String,
+    #[serde(default)]
+    pub said_by: String,
+    #[serde(default)]
+    pub votes: i64,
 }
 
 impl CsvRecord for Quote {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(4)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             date: fields[0].clone(),
             text: fields[1].clone(),
             channel: fields[2].clone(),
+            network: fields
+                .get(3)
+                .cloned()
+                .unwrap_or_else(network::default_network),
+            added_by: fields.get(5).cloned().unwrap_or_default(),
+            said_by: fields.get(6).cloned().unwrap_or_default(),
+            votes: fields
+                .get(7)
+                .and_then(|votes| votes.parse().ok())
+                .unwrap_or_default(),
         }
     }
 
     fn to_fields(&self) -> Vec<String> {
-        vec![self.date.clone(), self.text.clone(), self.channel.clone()]
+        vec![
+            self.date.clone(),
+            self.text.clone(),
+            self.channel.clone(),
+            self.network.clone(),
+            self.id.to_string(),
+            self.added_by.clone(),
+            self.said_by.clone(),
+            self.votes.to_string(),
+        ]
     }
 }
 
+impl Quote {
+    fn summary(&self) -> String {
+        match self.votes {
+            0 => format!("#{} {} {}", self.id, self.date, self.text),
+            votes => format!("#{} {} {} ({votes} votes)", self.id, self.date, self.text),
+        }
+    }
+}
+
+// A nick's vote for a quote, so that each nick votes once.
 #[derive(PartialEq)]
-struct TimeZone {
+struct QuoteVote {
+    id: u64,
     nick: String,
-    name: String,
+    network: String,
+}
+
+impl CsvRecord for QuoteVote {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            id: fields[0].parse().unwrap_or_default(),
+            nick: fields[1].clone(),
+            network: fields[2].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.nick.clone(), self.network.clone()]
+    }
+}
+
+// Deletes the votes for a deleted quote, so that a quote given its id later starts without them.
+pub fn delete_votes(id: u64, db: &Database) -> Result<(), Box<dyn Error>> {
+    db.delete("quote_votes", |v: &&QuoteVote| v.id == id)
+}
+
+impl Identified for Quote {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+}
+
+#[derive(PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TimeZone {
+    // Stored in the last column.
+    #[serde(default)]
+    pub id: u64,
+    pub nick: String,
+    // A time zone of the tz database, such as Europe/Lisbon.
+    pub name: String,
+    #[serde(default)]
+    pub network: String,
 }
 
 impl CsvRecord for TimeZone {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(3)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             nick: fields[0].clone(),
             name: fields[1].clone(),
+            network: fields
+                .get(2)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
     fn to_fields(&self) -> Vec<String> {
-        vec![self.nick.clone(), self.name.clone()]
+        vec![
+            self.nick.clone(),
+            self.name.clone(),
+            self.network.clone(),
+            self.id.to_string(),
+        ]
     }
 }
 
+impl Identified for TimeZone {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn alarm(
     args: &[String],
     nick: &str,
     target: &str,
+    network: &str,
+    clock: &Clock,
     db: Arc<Mutex<Database>>,
-    client: Arc<Mutex<Client>>,
+    queue: MessageQueue,
+    status: Arc<Mutex<BotStatus>>,
 ) -> String {
     if args.is_empty() {
         return String::from("Please provide a time in your time zone.");
     }
 
     let time_zones: Vec<TimeZone> = match db.lock().await.select("time_zones", |tz: &TimeZone| {
-        tz.nick.to_lowercase() == nick.to_lowercase()
+        tz.nick.to_lowercase() == nick.to_lowercase() && tz.network == network
     }) {
         Ok(time_zones_result) => match time_zones_result {
             Some(time_zones) => time_zones,
             None => vec![TimeZone {
+                id: 0,
                 nick: String::new(),
                 name: String::from("Europe/Berlin"),
+                network: String::from(network),
             }],
         },
         Err(_) => vec![TimeZone {
+            id: 0,
             nick: String::new(),
             name: String::from("Europe/Berlin"),
+            network: String::from(network),
         }],
     };
     let tz: Tz = match time_zones[0].name.parse() {
         Ok(tz) => tz,
         Err(_) => Tz::CET,
     };
-    let now = Utc::now();
+    let now = clock.now();
     let now = now.with_timezone(&tz);
     let utc_offset = now.offset().fix().local_minus_utc();
-    let now = Utc::now();
+    let now = clock.now();
     let day = now.day();
     let month = now.month();
     let year = now.year();
@@ -109,24 +257,27 @@ pub async fn alarm(
         Err(_) => return String::from("Please provide a time in your time zone (ex: 18:30)."),
     };
     let alarm_dt = alarm_dt - chrono::Duration::seconds(utc_offset as i64);
-    let duration = alarm_dt - Utc::now();
-
-    if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-        String::from(target),
-        format!("Alarm set to {} {}.", args[0], tz),
-    )) {
+    let duration = alarm_dt - clock.now();
+    let _pending_reminder = status.lock().await.pending_reminder();
+
+    if let Err(error) = queue.send(
+        target,
+        &format!("Alarm set to {} {}.", args[0], tz),
+        Priority::Normal,
+    ) {
         eprintln!("{error}");
     }
 
     if duration.num_seconds() > 0 {
-        if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-            String::from(target),
-            format!(
+        if let Err(error) = queue.send(
+            target,
+            &format!(
                 "Up in {} hours and {} minutes.",
                 duration.num_hours(),
                 duration.num_minutes() % 60
             ),
-        )) {
+            Priority::Normal,
+        ) {
             eprintln!("{error}");
         }
 
@@ -134,14 +285,15 @@ pub async fn alarm(
     } else {
         let corrected_duration = Duration::from_secs((duration.num_seconds() + 86400) as u64);
 
-        if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-            String::from(target),
-            format!(
+        if let Err(error) = queue.send(
+            target,
+            &format!(
                 "Up in {} hours and {} minutes.",
                 corrected_duration.as_secs() / (60 * 60),
                 (corrected_duration.as_secs() / 60) % 60
             ),
-        )) {
+            Priority::Normal,
+        ) {
             eprintln!("{error}");
         }
 
@@ -182,52 +334,207 @@ pub async fn hello(nick: &str) -> String {
     format!("Hello {}", nick)
 }
 
-pub async fn help() -> String {
-    String::from(
+pub async fn help(plugins: &Plugins) -> String {
+    let mut help = String::from(
         "Command list: \
-        alarm | ask | city | date | f1results | first | first_results | first_stats | hello | help | \
-        imdb | news | next | notify | ping | quote | rates | remind | timezone | weather",
-    )
+        alarm | ask | city | date | f1results | first | first_results | first_stats | grep | hello | \
+        help | imdb | karma | last | log | news | next | notify | ping | plugins | quote | rates | remind | \
+        seen | tell | timezone | weather",
+    );
+    let names: Vec<&str> = plugins.commands().map(|m| m.name.as_str()).collect();
+
+    if !names.is_empty() {
+        help.push_str(&format!(". Plugins: {}", names.join(" | ")));
+    }
+
+    help
 }
 
 pub async fn ping() -> String {
     String::from("pong")
 }
 
-pub async fn quote(args: &[String], target: &str, db: Arc<Mutex<Database>>) -> String {
-    if args.is_empty() {
-        let quotes: Vec<Quote> = match db
-            .lock()
-            .await
-            .select("quotes", |q: &Quote| q.channel.to_lowercase() == target)
-        {
-            Ok(quotes_result) => match quotes_result {
-                Some(quotes) => quotes,
-                None => return String::from("Could not find quotes."),
-            },
-            Err(_) => return String::from("Could not find quotes."),
-        };
+// Handles quote, quote <id>, quote search <text>, quote by <nick>, quote top, quote +1 <id> and
+// quote del <id>. Anything else is added as a quote, said by the nick in a leading <nick>, and
+// posted to the quote webhooks.
+#[allow(clippy::too_many_arguments)]
+pub async fn quote(
+    args: &[String],
+    nick: &str,
+    target: &str,
+    network: &str,
+    owners: &[String],
+    options: &HashMap<String, String>,
+    clock: &Clock,
+    transport: Arc<dyn Transport>,
+    db: Arc<Mutex<Database>>,
+) -> String {
+    let quotes: Vec<Quote> = match db.lock().await.select("quotes", |q: &Quote| {
+        q.channel.eq_ignore_ascii_case(target) && q.network == network
+    }) {
+        Ok(quotes) => quotes.unwrap_or_default(),
+        Err(_) => return String::from("Could not find quotes."),
+    };
+    let mut rng = StdRng::from_entropy();
+    let rest = args.get(1..).unwrap_or_default().join(" ");
+    let parse_id = |arg: &str| arg.trim_start_matches('#').parse::<u64>().ok();
+    let id = match args.len() {
+        2 => parse_id(&args[1]),
+        _ => None,
+    };
 
-        if quotes.is_empty() {
-            return String::from("Could not find quotes.");
+    match args.first().map(|a| a.to_lowercase()).as_deref() {
+        None => match quotes.choose(&mut rng) {
+            Some(quote) => quote.summary(),
+            None => String::from("Could not find quotes."),
+        },
+        Some("search" | "by" | "+1" | "del") if args.len() == 1 => String::from(
+            "Please use quote search <text>, quote by <nick>, quote +1 <id> or quote del <id>.",
+        ),
+        Some(arg) if args.len() == 1 && parse_id(arg).is_some() => {
+            match quotes.iter().find(|q| Some(q.id) == parse_id(arg)) {
+                Some(quote) => quote.summary(),
+                None => format!("Could not find quote #{}.", arg.trim_start_matches('#')),
+            }
+        }
+        Some("search") if !rest.is_empty() => {
+            let found: Vec<String> = quotes
+                .iter()
+                .filter(|q| q.text.to_lowercase().contains(&rest.to_lowercase()))
+                .take(QUOTES_SHOWN)
+                .map(Quote::summary)
+                .collect();
+
+            match found.is_empty() {
+                true => format!("Could not find quotes matching {rest}."),
+                false => found.join("\n"),
+            }
+        }
+        Some("by") if args.len() == 2 => {
+            let said: Vec<&Quote> = quotes
+                .iter()
+                .filter(|q| q.said_by.eq_ignore_ascii_case(&args[1]))
+                .collect();
+
+            match said.choose(&mut rng) {
+                Some(quote) => quote.summary(),
+                None => format!("Could not find quotes by {}.", args[1]),
+            }
+        }
+        Some("top") if args.len() == 1 => {
+            let mut voted: Vec<&Quote> = quotes.iter().filter(|q| q.votes > 0).collect();
+
+            if voted.is_empty() {
+                return String::from("No quotes have votes on this channel.");
+            }
+
+            voted.sort_by_key(|q| -q.votes);
+            voted
+                .iter()
+                .take(QUOTES_SHOWN)
+                .map(|q| q.summary())
+                .collect::<Vec<String>>()
+                .join("\n")
         }
+        Some("+1") if id.is_some() => match id.filter(|id| quotes.iter().any(|q| q.id == *id)) {
+            Some(id) => vote(id, nick, network, &db).await,
+            None => String::from("Please provide the id of a quote of this channel."),
+        },
+        Some("del") if id.is_some() => {
+            let quote = match id.and_then(|id| quotes.into_iter().find(|q| q.id == id)) {
+                Some(quote) => quote,
+                None => return String::from("Please provide the id of a quote of this channel."),
+            };
+
+            if !owners.iter().any(|o| o.eq_ignore_ascii_case(nick))
+                && !transport.is_op(target, nick).await
+            {
+                return String::from("Only channel operators can delete quotes.");
+            }
+
+            let db = db.lock().await;
+
+            match db
+                .delete("quotes", |q: &&Quote| q.id == quote.id)
+                .and_then(|_| delete_votes(quote.id, &db))
+            {
+                Ok(()) => format!("Quote #{} deleted.", quote.id),
+                Err(_) => String::from("Could not delete the quote."),
+            }
+        }
+        Some(_) => {
+            let text = args.join(" ");
+            // Quotes pasted from a client start with the nick that said them, as in <nick> text.
+            let said_by = match text.strip_prefix('<').and_then(|t| t.split_once('>')) {
+                Some((said_by, _)) => said_by.trim_start_matches(['@', '%', '+', '~', '&']),
+                None => "",
+            };
+
+            let quote = Quote {
+                id: 0,
+                date: clock.now().format("%d-%m-%Y").to_string(),
+                text: text.clone(),
+                channel: String::from(target),
+                network: String::from(network),
+                added_by: String::from(nick),
+                said_by: String::from(said_by),
+                votes: 0,
+            };
+            let mut data = serde_json::to_value(&quote).unwrap_or_default();
+            let inserted = db.lock().await.insert_new("quotes", quote);
+
+            match inserted {
+                Ok(id) => {
+                    data["id"] = Value::from(id);
+                    webhook::notify("quote", network, data, options, Arc::clone(&db));
+
+                    format!("Quote #{id} added.")
+                }
+                Err(_) => String::from("Problem adding quote."),
+            }
+        }
+    }
+}
 
-        let mut rng = StdRng::from_entropy();
-        let index = rng.gen_range(0..quotes.len());
+// Adds the vote of nick to a quote, once per nick.
+async fn vote(id: u64, nick: &str, network: &str, db: &Arc<Mutex<Database>>) -> String {
+    let db = db.lock().await;
+    // The quote is read again under the lock, so concurrent votes all count.
+    let mut quote = match db.select("quotes", |q: &Quote| q.id == id) {
+        Ok(quotes) => match quotes.unwrap_or_default().pop() {
+            Some(quote) => quote,
+            None => return format!("Could not find quote #{id}."),
+        },
+        Err(_) => return String::from("Could not read the quotes."),
+    };
+    let voted =
+        |v: &QuoteVote| v.id == id && v.nick.eq_ignore_ascii_case(nick) && v.network == network;
 
-        format!("{} {}", quotes[index].date, quotes[index].text)
-    } else {
-        match db.lock().await.insert(
-            "quotes",
-            Quote {
-                date: Utc::now().format("%d-%m-%Y").to_string(),
-                text: args.join(" "),
-                channel: String::from(target),
-            },
-        ) {
-            Ok(_) => String::from("Quote added successfully."),
-            Err(_) => String::from("Problem adding quote."),
+    match db.select("quote_votes", voted) {
+        Ok(Some(votes)) if !votes.is_empty() => {
+            return format!("You already voted for quote #{id}.");
         }
+        Ok(_) => {}
+        Err(_) => return String::from("Could not read the votes."),
+    }
+
+    quote.votes += 1;
+
+    let votes = quote.votes;
+
+    match db
+        .insert(
+            "quote_votes",
+            QuoteVote {
+                id,
+                nick: nick.to_lowercase(),
+                network: String::from(network),
+            },
+        )
+        .and_then(|_| db.update_keeping_id("quotes", quote, |q: &&Quote| q.id == id))
+    {
+        Ok(_) => format!("Quote #{id} has {votes} votes."),
+        Err(_) => String::from("Could not store the vote."),
     }
 }
 
@@ -235,7 +542,8 @@ pub async fn reminder(
     args: &[String],
     nick: &str,
     target: &str,
-    client: Arc<Mutex<Client>>,
+    queue: MessageQueue,
+    status: Arc<Mutex<BotStatus>>,
 ) -> String {
     if args.is_empty() {
         return String::from("Please provide a duration in minutes.");
@@ -246,13 +554,16 @@ pub async fn reminder(
         Err(_) => return String::from("Please provide a duration in integer minutes."),
     };
 
-    if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-        String::from(target),
-        format!("Reminder set for {} minute(s) from now.", minutes),
-    )) {
+    if let Err(error) = queue.send(
+        target,
+        &format!("Reminder set for {} minute(s) from now.", minutes),
+        Priority::Normal,
+    ) {
         eprintln!("{error}");
     }
 
+    let _pending_reminder = status.lock().await.pending_reminder();
+
     time::sleep(Duration::from_secs(minutes * 60)).await;
 
     if args.len() > 1 {
@@ -262,27 +573,56 @@ pub async fn reminder(
     }
 }
 
-pub async fn time_zone(args: &[String], nick: &str, db: Arc<Mutex<Database>>) -> String {
+pub async fn status(
+    nick: &str,
+    owners: &[String],
+    transport: Arc<dyn Transport>,
+    status: Arc<Mutex<BotStatus>>,
+) -> String {
+    if !owners
+        .iter()
+        .any(|o| o.to_lowercase() == nick.to_lowercase())
+    {
+        return String::from("Only the bot owners can check its status.");
+    }
+
+    let report = status.lock().await.report(Some(transport.as_ref())).await;
+
+    report.summary()
+}
+
+pub async fn time_zone(
+    args: &[String],
+    nick: &str,
+    network: &str,
+    db: Arc<Mutex<Database>>,
+) -> String {
     let time_zones: Vec<TimeZone> = match db.lock().await.select("time_zones", |tz: &TimeZone| {
-        tz.nick.to_lowercase() == nick.to_lowercase()
+        tz.nick.to_lowercase() == nick.to_lowercase() && tz.network == network
     }) {
         Ok(time_zones_result) => match time_zones_result {
             Some(time_zones) => time_zones,
             None => vec![TimeZone {
+                id: 0,
                 nick: String::new(),
                 name: String::from("Europe/Berlin"),
+                network: String::from(network),
             }],
         },
         Err(_) => vec![TimeZone {
+            id: 0,
             nick: String::new(),
             name: String::from("Europe/Berlin"),
+            network: String::from(network),
         }],
     };
     let tz: Tz = match time_zones
         .first()
         .unwrap_or(&TimeZone {
+            id: 0,
             nick: String::from(nick),
             name: String::from("Europe/Berlin"),
+            network: String::from(network),
         })
         .name
         .parse()
@@ -294,13 +634,15 @@ pub async fn time_zone(args: &[String], nick: &str, db: Arc<Mutex<Database>>) ->
     if args.is_empty() {
         format!("Your current time zone: {}", tz)
     } else {
-        match db.lock().await.update(
+        match db.lock().await.update_keeping_id(
             "time_zones",
             TimeZone {
+                id: 0,
                 nick: String::from(nick),
                 name: args.concat(),
+                network: String::from(network),
             },
-            |tz: &&TimeZone| tz.nick.to_lowercase() == nick.to_lowercase(),
+            |tz: &&TimeZone| tz.nick.to_lowercase() == nick.to_lowercase() && tz.network == network,
         ) {
             Ok(_) => String::from("Your time zone was successfully updated."),
             Err(_) => String::from("Problem updating your time zone."),
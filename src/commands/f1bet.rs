@@ -1,26 +1,51 @@
-use crate::database::{CsvRecord, Database};
+use crate::clock::Clock;
+use crate::database::{CsvRecord, Database, Identified};
+use crate::network;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-
-struct Driver {
-    number: u32,
-    code: String,
+use utoipa::ToSchema;
+
+#[derive(PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct Driver {
+    // Stored in the last column.
+    #[serde(default)]
+    pub id: u64,
+    pub number: u32,
+    pub code: String,
 }
 
 impl CsvRecord for Driver {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(2)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             number: fields[0].parse().unwrap_or(0),
             code: fields[1].clone(),
         }
     }
 
     fn to_fields(&self) -> Vec<String> {
-        vec![self.number.to_string(), self.code.clone()]
+        vec![
+            self.number.to_string(),
+            self.code.clone(),
+            self.id.to_string(),
+        ]
+    }
+}
+
+impl Identified for Driver {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
     }
 }
 
@@ -32,6 +57,7 @@ struct Event {
     channel: String,
     tags: String,
     notify: bool,
+    network: String,
 }
 
 impl CsvRecord for Event {
@@ -47,6 +73,10 @@ impl CsvRecord for Event {
             channel: fields[4].clone(),
             tags: fields[5].clone(),
             notify: fields[6].parse().unwrap_or_default(),
+            network: fields
+                .get(7)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -59,6 +89,7 @@ impl CsvRecord for Event {
             self.channel.clone(),
             self.tags.clone(),
             self.notify.to_string(),
+            self.network.clone(),
         ]
     }
 }
@@ -98,8 +129,11 @@ impl ScoringSystem {
     }
 }
 
-#[derive(PartialEq, Deserialize, Serialize)]
+#[derive(PartialEq, Deserialize, Serialize, ToSchema)]
 pub struct Bet {
+    // Stored in the last column, after the network.
+    #[serde(default)]
+    pub id: u64,
     pub race: String,
     pub nick: String,
     pub p1: String,
@@ -107,11 +141,17 @@ pub struct Bet {
     pub p3: String,
     pub p4: String,
     pub p5: String,
+    #[serde(default)]
+    pub network: String,
 }
 
 impl CsvRecord for Bet {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(8)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             race: fields[0].clone(),
             nick: fields[1].clone(),
             p1: fields[2].clone(),
@@ -119,6 +159,10 @@ impl CsvRecord for Bet {
             p3: fields[4].clone(),
             p4: fields[5].clone(),
             p5: fields[6].clone(),
+            network: fields
+                .get(7)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -131,10 +175,22 @@ impl CsvRecord for Bet {
             self.p3.clone(),
             self.p4.clone(),
             self.p5.clone(),
+            self.network.clone(),
+            self.id.to_string(),
         ]
     }
 }
 
+impl Identified for Bet {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+}
+
 async fn valid_drivers(drivers: &[String], db: Arc<Mutex<Database>>) -> bool {
     let valid_drivers: Vec<Driver> = match db.lock().await.select("drivers", |d: &Driver| {
         d.code.to_lowercase() == drivers[0].to_lowercase()
@@ -153,10 +209,16 @@ async fn valid_drivers(drivers: &[String], db: Arc<Mutex<Database>>) -> bool {
     valid_drivers.len() == 5
 }
 
-async fn next_race(target: &str, db: Arc<Mutex<Database>>) -> Option<Event> {
+async fn next_race(
+    target: &str,
+    network: &str,
+    clock: &Clock,
+    db: Arc<Mutex<Database>>,
+) -> Option<Event> {
     match db.lock().await.select("events", |e: &Event| {
-        e.datetime > Utc::now()
+        e.datetime > clock.now()
             && e.channel.to_lowercase() == target.to_lowercase()
+            && e.network == network
             && e.category.to_lowercase().contains("formula 1")
             && e.description.eq_ignore_ascii_case("race")
     }) {
@@ -351,17 +413,19 @@ pub async fn bet(
     args: &[String],
     nick: &str,
     target: &str,
+    network: &str,
     options: &HashMap<String, String>,
+    clock: &Clock,
     db: Arc<Mutex<Database>>,
 ) -> String {
-    let next_race = match next_race(target, Arc::clone(&db)).await {
+    let next_race = match next_race(target, network, clock, Arc::clone(&db)).await {
         Some(next_race) => next_race,
         None => return String::from("Could not find next race."),
     };
 
     if args.len() <= 1 {
         let bets: Vec<Bet> = match db.lock().await.select("bets", |b: &Bet| {
-            b.nick.to_lowercase() == nick.to_lowercase()
+            b.nick.to_lowercase() == nick.to_lowercase() && b.network == network
         }) {
             Ok(bets_result) => match bets_result {
                 Some(bets) => bets,
@@ -410,10 +474,14 @@ pub async fn bet(
                 Some(bets_log) => return bets_log,
                 None => return String::from("Could not find any bets."),
             },
-            "last_points" | "lastpoints" => return points(true, options, db).await,
-            "points" | "wbc" => return points(false, options, db).await,
+            "last_points" | "lastpoints" => return points(true, network, options, db).await,
+            "points" | "wbc" => return points(false, network, options, db).await,
             _ => {
-                let bets: Vec<Bet> = match db.lock().await.select("bets", |_: &Bet| true) {
+                let bets: Vec<Bet> = match db
+                    .lock()
+                    .await
+                    .select("bets", |b: &Bet| b.network == network)
+                {
                     Ok(bets_result) => match bets_result {
                         Some(bets) => bets,
                         None => return String::from("Could not find any bets."),
@@ -435,9 +503,10 @@ pub async fn bet(
         return String::from("Invalid drivers.");
     }
 
-    match db.lock().await.update(
+    match db.lock().await.update_keeping_id(
         "bets",
         Bet {
+            id: 0,
             race: next_race.name.clone(),
             nick: nick.to_lowercase(),
             p1: args[0].to_lowercase(),
@@ -445,13 +514,15 @@ pub async fn bet(
             p3: args[2].to_lowercase(),
             p4: args[3].to_lowercase(),
             p5: args[4].to_lowercase(),
+            network: String::from(network),
         },
         |b: &&Bet| {
             b.race.to_lowercase() == next_race.name.to_lowercase()
                 && b.nick.to_lowercase() == nick.to_lowercase()
+                && b.network == network
         },
     ) {
-        Ok(()) => format!(
+        Ok(_) => format!(
             "Your bet for the {} was successfully updated.",
             next_race.name
         ),
@@ -461,10 +532,15 @@ pub async fn bet(
 
 pub async fn points(
     last: bool,
+    network: &str,
     options: &HashMap<String, String>,
     db: Arc<Mutex<Database>>,
 ) -> String {
-    let bets: Vec<Bet> = match db.lock().await.select("bets", |_| true) {
+    let bets: Vec<Bet> = match db
+        .lock()
+        .await
+        .select("bets", |b: &Bet| b.network == network)
+    {
         Ok(bets_result) => match bets_result {
             Some(bets) => bets,
             None => return String::from("Could not find any bets."),
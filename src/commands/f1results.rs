@@ -2,11 +2,14 @@
 use crate::utils;
 use reqwest::{header::USER_AGENT, Client};
 use scraper::{Html, Selector};
+use std::collections::HashMap;
 use tokio::time::Duration;
 
+const F1_RESULTS_URL: &str = "https://www.formula1.com/en/results/latest.html";
+
 // const EVENT: &str = "1143/australia"; // Hardcoded for now. Need to find a way to do it programmatically.
 
-pub async fn f1results() -> String {
+pub async fn f1results(options: &HashMap<String, String>) -> String {
     // if args.len() != 1 {
     //     return String::from("Please provide a session. Ex: FP1, FP2, FP3, Qualifying, Race");
     // }
@@ -21,7 +24,10 @@ pub async fn f1results() -> String {
     //     _ => return String::from("Session must be one of: FP1, FP2, FP3, Qualifying, Race"),
     // };
     //let base_url = format!("https://www.formula1.com/en/results.html/{year}/races");
-    let url = "https://www.formula1.com/en/results/latest.html";
+    let url = match options.get("f1_results_url") {
+        Some(url) => url.as_str(),
+        None => F1_RESULTS_URL,
+    };
     let client = match Client::builder().timeout(Duration::from_secs(10)).build() {
         Ok(client) => client,
         Err(_) => return String::from("Could not fetch data."),
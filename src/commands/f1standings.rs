@@ -155,10 +155,16 @@ pub fn team_alias(name: &str) -> String {
     }
 }
 
-pub async fn f1standings() -> String {
-    let wcc_task = task::spawn(async {
+pub async fn f1standings(options: &HashMap<String, String>) -> String {
+    let api_url = match options.get("ergast_api_url") {
+        Some(api_url) => api_url.clone(),
+        None => String::from(ERGAST_API_URL),
+    };
+    let api_url_clone = api_url.clone();
+
+    let wcc_task = task::spawn(async move {
         let wcc: wcc_models::Wcc =
-            match reqwest::get(format!("{}/constructorStandings.json", ERGAST_API_URL)).await {
+            match reqwest::get(format!("{}/constructorStandings.json", api_url)).await {
                 Ok(response) => match response.json().await {
                     Ok(wcc) => wcc,
                     Err(_) => return String::from("Could not decode WCC data."),
@@ -181,9 +187,9 @@ pub async fn f1standings() -> String {
             .join(" | ")
     });
 
-    let wdc_task = task::spawn(async {
+    let wdc_task = task::spawn(async move {
         let wdc: wdc_models::Wdc =
-            match reqwest::get(format!("{}/driverStandings.json", ERGAST_API_URL)).await {
+            match reqwest::get(format!("{}/driverStandings.json", api_url_clone)).await {
                 Ok(response) => match response.json().await {
                     Ok(wcc) => wcc,
                     Err(_) => return String::from("Could not decode WDC data."),
@@ -1,9 +1,13 @@
+use crate::clock::Clock;
 use crate::database::{CsvRecord, Database};
+use crate::network;
+use crate::queue::{MessageQueue, Priority};
+use crate::webhook;
 use chrono::{DateTime, Datelike, Days, Duration, Timelike, Utc, Weekday};
 use chrono_tz::Tz;
-use irc::{client::Client, proto::Command};
 use rand::prelude::*;
 use regex::Regex;
+use serde_json::json;
 use std::{cmp, collections::HashMap, ops::Range, str::FromStr, sync::Arc};
 use tokio::sync::Mutex;
 
@@ -39,6 +43,7 @@ struct FirstResult {
     target: String,
     datetime: DateTime<Utc>,
     tz: Tz,
+    network: String,
 }
 
 impl CsvRecord for FirstResult {
@@ -51,6 +56,10 @@ impl CsvRecord for FirstResult {
                 Err(_) => Utc::now(),
             },
             tz: Tz::from_str(&fields[3].clone()).unwrap_or(Tz::Europe__Berlin),
+            network: fields
+                .get(4)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -60,6 +69,7 @@ impl CsvRecord for FirstResult {
             self.target.clone(),
             self.datetime.to_string(),
             self.tz.to_string(),
+            self.network.clone(),
         ]
     }
 }
@@ -67,6 +77,7 @@ impl CsvRecord for FirstResult {
 struct TimeZone {
     nick: String,
     name: String,
+    network: String,
 }
 
 impl CsvRecord for TimeZone {
@@ -74,30 +85,32 @@ impl CsvRecord for TimeZone {
         Self {
             nick: fields[0].clone(),
             name: fields[1].clone(),
+            network: fields
+                .get(2)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
     fn to_fields(&self) -> Vec<String> {
-        vec![self.nick.clone(), self.name.clone()]
+        vec![self.nick.clone(), self.name.clone(), self.network.clone()]
     }
 }
 
-fn open_time(options: &HashMap<String, String>, utc_now: DateTime<Utc>, next: bool) -> (u32, u32) {
+fn open_time(options: &HashMap<String, String>, clock: &Clock, next: bool) -> (u32, u32) {
+    let date = if next {
+        (clock.now() + Duration::days(1)).date_naive()
+    } else {
+        clock.now().date_naive()
+    };
+    let mut rng = clock.daily_rng("first", date);
     let open_hour = match options.get("first_open_hour") {
         Some(open_hour) => match open_hour.parse() {
             Ok(open_hour) => match open_hour {
                 0..=23 => open_hour,
                 _ => DEFAULT_OPEN_HOUR,
             },
-            Err(_) => {
-                let mut rng = if next {
-                    StdRng::seed_from_u64((utc_now + Duration::days(1)).day() as u64)
-                } else {
-                    StdRng::seed_from_u64(utc_now.day() as u64)
-                };
-
-                rng.gen_range(RAND_OPEN_HOUR)
-            }
+            Err(_) => rng.gen_range(RAND_OPEN_HOUR),
         },
         None => 5,
     };
@@ -107,15 +120,7 @@ fn open_time(options: &HashMap<String, String>, utc_now: DateTime<Utc>, next: bo
                 0..=59 => open_min,
                 _ => DEFAULT_OPEN_MIN,
             },
-            Err(_) => {
-                let mut rng = if next {
-                    StdRng::seed_from_u64((utc_now + Duration::days(1)).day() as u64)
-                } else {
-                    StdRng::seed_from_u64(utc_now.day() as u64)
-                };
-
-                rng.gen_range(RAND_OPEN_MIN)
-            }
+            Err(_) => rng.gen_range(RAND_OPEN_MIN),
         },
         None => 30,
     };
@@ -127,7 +132,7 @@ async fn show_results(
     first_results: &mut [FirstResult],
     nick: Option<&str>,
     target: &str,
-    client: Arc<Mutex<Client>>,
+    queue: MessageQueue,
 ) {
     first_results.sort_by(|a, b| {
         a.datetime
@@ -140,10 +145,8 @@ async fn show_results(
         let re = match Regex::new(r"[^A-Za-z0-9]+") {
             Ok(re) => re,
             Err(_) => {
-                if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-                    String::from(target),
-                    String::from("Could not print results."),
-                )) {
+                if let Err(error) = queue.send(target, "Could not print results.", Priority::Normal)
+                {
                     eprintln!("{error}");
                 }
 
@@ -155,25 +158,27 @@ async fn show_results(
             None => {
                 let nick = re.replace_all(&result.nick, "").to_uppercase();
 
-                if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-                    String::from(target),
-                    format!(
+                if let Err(error) = queue.send(
+                    target,
+                    &format!(
                         "{}. {} | {} ({})",
                         position + 1,
                         &nick[..cmp::min(nick.len(), 3)],
                         result.datetime.with_timezone(&result.tz).time(),
                         result.datetime.with_timezone(&result.tz).timezone()
                     ),
-                )) {
+                    Priority::Normal,
+                ) {
                     eprintln!("{error}");
                 }
             }
             Some(nick) => {
                 if nick.to_lowercase() == result.nick.to_lowercase() {
-                    if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-                        String::from(target),
-                        format!("You are currently P{}.", position + 1),
-                    )) {
+                    if let Err(error) = queue.send(
+                        target,
+                        &format!("You are currently P{}.", position + 1),
+                        Priority::Normal,
+                    ) {
                         eprintln!("{error}");
                     }
 
@@ -187,11 +192,13 @@ async fn show_results(
 pub async fn first(
     nick: &str,
     target: &str,
+    network: &str,
     options: &HashMap<String, String>,
+    clock: &Clock,
     db: Arc<Mutex<Database>>,
-    client: Arc<Mutex<Client>>,
+    queue: MessageQueue,
 ) -> String {
-    let utc_now = Utc::now();
+    let utc_now = clock.now();
     let close_hour = match options.get("first_close_hour") {
         Some(close_hour) => match close_hour.parse() {
             Ok(close_hour) => match close_hour {
@@ -223,7 +230,7 @@ pub async fn first(
     }
 
     let time_zones: Vec<TimeZone> = match db.lock().await.select("time_zones", |tz: &TimeZone| {
-        tz.nick.to_lowercase() == nick.to_lowercase()
+        tz.nick.to_lowercase() == nick.to_lowercase() && tz.network == network
     }) {
         Ok(time_zones_result) => match time_zones_result {
             Some(time_zones) => time_zones,
@@ -236,6 +243,7 @@ pub async fn first(
         .unwrap_or(&TimeZone {
             nick: String::from(nick),
             name: String::from("Europe/Berlin"),
+            network: String::from(network),
         })
         .name
         .parse()
@@ -246,10 +254,10 @@ pub async fn first(
         }
     };
     let tz_now = utc_now.with_timezone(&tz);
-    let open_time_today = open_time(options, utc_now, false);
+    let open_time_today = open_time(options, clock, false);
     let open_hour = open_time_today.0;
     let open_min = open_time_today.1;
-    let open_time_next = open_time(options, utc_now, true);
+    let open_time_next = open_time(options, clock, true);
     let open_hour_next = open_time_next.0;
     let open_min_next = open_time_next.1;
 
@@ -264,6 +272,7 @@ pub async fn first(
         fr.nick.to_lowercase() == nick.to_lowercase()
             && fr.target.to_lowercase() == target.to_lowercase()
             && fr.datetime.date_naive() == utc_now.date_naive()
+            && fr.network == network
     }) {
         Ok(result) => {
             if result.is_some() && !result.unwrap().is_empty() {
@@ -286,6 +295,7 @@ pub async fn first(
                 target: String::from(target),
                 datetime: utc_now,
                 tz,
+                network: String::from(network),
             },
         )
         .is_err()
@@ -297,6 +307,7 @@ pub async fn first(
         match db.lock().await.select("first_results", |fr: &FirstResult| {
             utc_now.date_naive() == fr.datetime.date_naive()
                 && fr.target.to_lowercase() == target.to_lowercase()
+                && fr.network == network
         }) {
             Ok(first_results) => match first_results {
                 Some(first_results) => first_results,
@@ -305,7 +316,18 @@ pub async fn first(
             Err(_) => return String::from("Could not get results."),
         };
 
-    show_results(&mut first_results, Some(nick), target, client).await;
+    // Only the winner of the day finds nobody else on the list.
+    if first_results.len() == 1 {
+        webhook::notify(
+            "first",
+            network,
+            json!({"nick": nick, "target": target, "time": utc_now.to_rfc3339(), "time_zone": tz.to_string()}),
+            options,
+            Arc::clone(&db),
+        );
+    }
+
+    show_results(&mut first_results, Some(nick), target, queue).await;
 
     format!(
         "STATUS open (today opened at {:0>2}H{:0>2} {} | tomorrow opens at {:0>2}H{:0>2} {})",
@@ -313,21 +335,33 @@ pub async fn first(
     )
 }
 
-pub async fn first_stats(args: &[String], target: &str, db: Arc<Mutex<Database>>) -> String {
+pub async fn first_stats(
+    args: &[String],
+    target: &str,
+    network: &str,
+    clock: &Clock,
+    db: Arc<Mutex<Database>>,
+) -> String {
     match args.concat().to_lowercase().as_str() {
-        "week" => stats(Period::Week, target, db).await,
-        "weekly" => stats(Period::Weekly, target, db).await,
-        "month" => stats(Period::Month, target, db).await,
-        "monthly" => stats(Period::Monthly, target, db).await,
-        "year" => stats(Period::Year, target, db).await,
-        "yearly" => stats(Period::Yearly, target, db).await,
-        "alltime" => stats(Period::AllTime, target, db).await,
-        _ => stats(Period::Unknown, target, db).await,
+        "week" => stats(Period::Week, target, network, clock, db).await,
+        "weekly" => stats(Period::Weekly, target, network, clock, db).await,
+        "month" => stats(Period::Month, target, network, clock, db).await,
+        "monthly" => stats(Period::Monthly, target, network, clock, db).await,
+        "year" => stats(Period::Year, target, network, clock, db).await,
+        "yearly" => stats(Period::Yearly, target, network, clock, db).await,
+        "alltime" => stats(Period::AllTime, target, network, clock, db).await,
+        _ => stats(Period::Unknown, target, network, clock, db).await,
     }
 }
 
-pub async fn stats(period: Period, target: &str, db: Arc<Mutex<Database>>) -> String {
-    let now = Utc::now();
+pub async fn stats(
+    period: Period,
+    target: &str,
+    network: &str,
+    clock: &Clock,
+    db: Arc<Mutex<Database>>,
+) -> String {
+    let now = clock.now();
     let day_number = match period {
         Period::Week => {
             let week_day = now.weekday();
@@ -373,6 +407,7 @@ pub async fn stats(period: Period, target: &str, db: Arc<Mutex<Database>>) -> St
         match db.lock().await.select("first_results", |fr: &FirstResult| {
             fr.target.to_lowercase() == target.to_lowercase()
                 && fr.datetime.date_naive() > start_date
+                && fr.network == network
         }) {
             Ok(first_results) => match first_results {
                 Some(first_results) => first_results,
@@ -451,13 +486,16 @@ pub async fn stats(period: Period, target: &str, db: Arc<Mutex<Database>>) -> St
 
 pub async fn first_results(
     target: &str,
+    network: &str,
+    clock: &Clock,
     db: Arc<Mutex<Database>>,
-    client: Arc<Mutex<Client>>,
+    queue: MessageQueue,
 ) -> String {
     let mut first_results: Vec<FirstResult> =
         match db.lock().await.select("first_results", |fr: &FirstResult| {
-            Utc::now().date_naive() == fr.datetime.date_naive()
+            clock.now().date_naive() == fr.datetime.date_naive()
                 && fr.target.to_lowercase() == target.to_lowercase()
+                && fr.network == network
         }) {
             Ok(first_results) => match first_results {
                 Some(first_results) => first_results,
@@ -466,7 +504,7 @@ pub async fn first_results(
             Err(_) => return String::from("Could not get results."),
         };
 
-    show_results(&mut first_results, None, target, client).await;
+    show_results(&mut first_results, None, target, queue).await;
 
     String::new()
 }
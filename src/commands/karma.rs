@@ -0,0 +1,237 @@
+use crate::activity;
+use crate::clock::Clock;
+use crate::database::{CsvRecord, Database};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+const RANKING_SIZE: usize = 5;
+
+// The karma of a thing on a channel, with the reason of the last vote that gave one.
+#[derive(PartialEq, Serialize, ToSchema)]
+pub struct Karma {
+    pub thing: String,
+    pub score: i64,
+    pub reason: String,
+    pub channel: String,
+    pub network: String,
+}
+
+impl CsvRecord for Karma {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            thing: fields[0].clone(),
+            score: fields[1].parse().unwrap_or_default(),
+            reason: fields[2].clone(),
+            channel: fields[3].clone(),
+            network: fields[4].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.thing.clone(),
+            self.score.to_string(),
+            self.reason.clone(),
+            self.channel.clone(),
+            self.network.clone(),
+        ]
+    }
+}
+
+// When a nick last gave karma on a channel, for the cooldown.
+#[derive(PartialEq)]
+struct Giver {
+    nick: String,
+    datetime: DateTime<Utc>,
+    channel: String,
+    network: String,
+}
+
+impl CsvRecord for Giver {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            nick: fields[0].clone(),
+            datetime: fields[1].parse().unwrap_or_default(),
+            channel: fields[2].clone(),
+            network: fields[3].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.nick.clone(),
+            self.datetime.to_string(),
+            self.channel.clone(),
+            self.network.clone(),
+        ]
+    }
+}
+
+// Finds a vote in a message starting with thing++ or thing--, followed by an optional reason.
+// Gives the thing, in lowercase, the change of its score and the reason.
+pub fn parse(message: &str) -> Option<(String, i64, String)> {
+    let mut words = message.trim().splitn(2, char::is_whitespace);
+    let word = words.next()?.trim_end_matches([':', ',']);
+    let reason = words.next().unwrap_or_default().trim();
+    let (thing, change) = match (word.strip_suffix("++"), word.strip_suffix("--")) {
+        (Some(thing), _) => (thing, 1),
+        (_, Some(thing)) => (thing, -1),
+        _ => return None,
+    };
+
+    if thing.is_empty() || thing.ends_with(['+', '-']) {
+        return None;
+    }
+
+    Some((thing.to_lowercase(), change, String::from(reason)))
+}
+
+// Applies the vote in a message sent by nick on a channel, giving the answer to send back. None
+// when the message has no vote. Nicks can't vote for themselves and must wait karma_cooldown
+// seconds (default 60) between votes on a channel.
+pub async fn give(
+    nick: &str,
+    channel: &str,
+    message: &str,
+    network: &str,
+    options: &HashMap<String, String>,
+    clock: &Clock,
+    db: &Arc<Mutex<Database>>,
+) -> Option<String> {
+    let (thing, change, reason) = parse(message)?;
+
+    if !activity::is_channel(channel) {
+        return None;
+    }
+
+    if thing.eq_ignore_ascii_case(nick) {
+        return Some(String::from("You can't give karma to yourself."));
+    }
+
+    let cooldown = match options.get("karma_cooldown") {
+        Some(cooldown) => cooldown.parse().unwrap_or(60),
+        None => 60,
+    };
+    let channel = channel.to_lowercase();
+    let db = db.lock().await;
+    let given = |g: &Giver| {
+        g.nick.eq_ignore_ascii_case(nick) && g.channel == channel && g.network == network
+    };
+
+    match db.select("karma_givers", given) {
+        Ok(givers) => {
+            if givers
+                .unwrap_or_default()
+                .iter()
+                .any(|g| clock.now() < g.datetime + Duration::seconds(cooldown))
+            {
+                return Some(String::from("Please wait before giving karma again."));
+            }
+        }
+        Err(_) => return Some(String::from("Could not read the karma.")),
+    }
+
+    let current = match db.select("karma", |k: &Karma| {
+        k.thing == thing && k.channel == channel && k.network == network
+    }) {
+        Ok(karma) => karma.and_then(|k| k.into_iter().next()),
+        Err(_) => return Some(String::from("Could not read the karma.")),
+    };
+    let score = current.as_ref().map_or(0, |k| k.score) + change;
+    let karma = Karma {
+        thing: thing.clone(),
+        score,
+        reason: match reason.is_empty() {
+            true => current.map(|k| k.reason).unwrap_or_default(),
+            false => reason,
+        },
+        channel: channel.clone(),
+        network: String::from(network),
+    };
+
+    if db
+        .update("karma", karma, |k: &&Karma| {
+            k.thing == thing && k.channel == channel && k.network == network
+        })
+        .is_err()
+        || db
+            .update(
+                "karma_givers",
+                Giver {
+                    nick: nick.to_lowercase(),
+                    datetime: clock.now(),
+                    channel: channel.clone(),
+                    network: String::from(network),
+                },
+                |g: &&Giver| given(g),
+            )
+            .is_err()
+    {
+        return Some(String::from("Could not store the karma."));
+    }
+
+    Some(format!("{thing} has {score} karma."))
+}
+
+pub async fn karma(
+    args: &[String],
+    target: &str,
+    network: &str,
+    db: Arc<Mutex<Database>>,
+) -> String {
+    let arg = match args.first() {
+        Some(arg) => arg.to_lowercase(),
+        None => return String::from("Please provide a thing, top or bottom."),
+    };
+    let channel = target.to_lowercase();
+    let scores = match db.lock().await.select("karma", |k: &Karma| {
+        k.channel == channel && k.network == network
+    }) {
+        Ok(scores) => scores.unwrap_or_default(),
+        Err(_) => return String::from("Could not read the karma."),
+    };
+
+    match arg.as_str() {
+        "top" | "bottom" => {
+            let mut scores = scores;
+
+            scores.sort_by_key(|k| k.score);
+
+            if arg == "top" {
+                scores.reverse();
+            }
+
+            if scores.is_empty() {
+                return String::from("Nothing has karma on this channel.");
+            }
+
+            format!(
+                "{} karma: {}",
+                match arg.as_str() {
+                    "top" => "Top",
+                    _ => "Bottom",
+                },
+                scores
+                    .iter()
+                    .take(RANKING_SIZE)
+                    .map(|k| format!("{} ({})", k.thing, k.score))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+        }
+        thing => match scores.into_iter().find(|k| k.thing == thing) {
+            Some(karma) if karma.reason.is_empty() => {
+                format!("{} has {} karma.", karma.thing, karma.score)
+            }
+            Some(karma) => format!(
+                "{} has {} karma, last for: {}",
+                karma.thing, karma.score, karma.reason
+            ),
+            None => format!("{thing} has no karma."),
+        },
+    }
+}
@@ -0,0 +1,305 @@
+use crate::clock::Clock;
+use crate::database::{CsvRecord, Database};
+use crate::utils::format_ago;
+use chrono::{DateTime, Duration, Utc};
+use regex::RegexBuilder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+const DEFAULT_LAST: usize = 3;
+const MAX_LAST: usize = 10;
+const MAX_MATCHES: usize = 3;
+// Keeps patterns given to !grep from compiling into huge regexes.
+const MAX_PATTERN_SIZE: usize = 100_000;
+
+// A message sent on a logged channel.
+#[derive(PartialEq, Serialize, ToSchema)]
+pub struct LogLine {
+    pub datetime: DateTime<Utc>,
+    pub channel: String,
+    pub nick: String,
+    pub text: String,
+    pub network: String,
+}
+
+impl CsvRecord for LogLine {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            datetime: fields[0].parse().unwrap_or_default(),
+            channel: fields[1].clone(),
+            nick: fields[2].clone(),
+            text: fields[3].clone(),
+            network: fields[4].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.datetime.to_string(),
+            self.channel.clone(),
+            self.nick.clone(),
+            self.text.clone(),
+            self.network.clone(),
+        ]
+    }
+}
+
+// A nick whose messages are never logged.
+#[derive(PartialEq)]
+struct OptOut {
+    nick: String,
+    network: String,
+}
+
+impl CsvRecord for OptOut {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            nick: fields[0].clone(),
+            network: fields[1].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![self.nick.clone(), self.network.clone()]
+    }
+}
+
+// Whether the messages of channel are logged. Only the channels in the log_channels option are.
+pub fn logged(channel: &str, options: &HashMap<String, String>) -> bool {
+    match options.get("log_channels") {
+        Some(channels) => channels
+            .split_whitespace()
+            .any(|c| c.eq_ignore_ascii_case(channel)),
+        None => false,
+    }
+}
+
+// Whether nick opted out of logging with log off.
+pub fn opted_out(nick: &str, network: &str, db: &Database) -> Result<bool, Box<dyn Error>> {
+    let opt_outs = db.select("log_opt_outs", |o: &OptOut| {
+        o.nick.eq_ignore_ascii_case(nick) && o.network == network
+    })?;
+
+    Ok(!opt_outs.unwrap_or_default().is_empty())
+}
+
+// Stores a message sent on a logged channel, unless its nick opted out.
+pub async fn record(
+    nick: &str,
+    channel: &str,
+    text: &str,
+    network: &str,
+    clock: &Clock,
+    db: &Arc<Mutex<Database>>,
+) {
+    let db = db.lock().await;
+
+    match opted_out(nick, network, &db) {
+        Ok(true) => return,
+        Ok(false) => (),
+        Err(error) => {
+            eprintln!("{error}");
+
+            return;
+        }
+    }
+
+    if let Err(error) = db.append(
+        "logs",
+        LogLine {
+            datetime: clock.now(),
+            channel: channel.to_lowercase(),
+            nick: String::from(nick),
+            text: String::from(text),
+            network: String::from(network),
+        },
+    ) {
+        eprintln!("{error}");
+    }
+}
+
+// Deletes the lines of network older than the log_retention option, in days. 0 keeps every line.
+pub async fn prune(
+    network: &str,
+    options: &HashMap<String, String>,
+    clock: &Clock,
+    db: &Arc<Mutex<Database>>,
+) {
+    let retention: i64 = match options.get("log_retention") {
+        Some(retention) => retention.parse().unwrap_or(30),
+        None => 30,
+    };
+
+    if retention <= 0 {
+        return;
+    }
+
+    let oldest = clock.now() - Duration::days(retention);
+
+    if let Err(error) = db.lock().await.delete("logs", |l: &&LogLine| {
+        l.network == network && l.datetime < oldest
+    }) {
+        eprintln!("{error}");
+    }
+}
+
+// Lines of network matching filter, oldest first.
+async fn lines<P>(
+    network: &str,
+    db: &Arc<Mutex<Database>>,
+    filter: P,
+) -> Result<Vec<LogLine>, String>
+where
+    P: Fn(&LogLine) -> bool,
+{
+    match db
+        .lock()
+        .await
+        .select("logs", |l: &LogLine| l.network == network && filter(l))
+    {
+        Ok(lines) => Ok(lines.unwrap_or_default()),
+        Err(_) => Err(String::from("Could not read the logs.")),
+    }
+}
+
+pub async fn last(
+    args: &[String],
+    target: &str,
+    network: &str,
+    clock: &Clock,
+    db: Arc<Mutex<Database>>,
+) -> String {
+    let nick = match args.first() {
+        Some(nick) => nick,
+        None => return String::from("Please provide a nick."),
+    };
+    let count = match args.get(1) {
+        Some(count) => count.parse().unwrap_or(DEFAULT_LAST).clamp(1, MAX_LAST),
+        None => DEFAULT_LAST,
+    };
+    let lines = match lines(network, &db, |l| {
+        l.nick.eq_ignore_ascii_case(nick) && l.channel.eq_ignore_ascii_case(target)
+    })
+    .await
+    {
+        Ok(lines) => lines,
+        Err(error) => return error,
+    };
+
+    if lines.is_empty() {
+        return format!("Could not find {nick} in the logs of {target}.");
+    }
+
+    lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|l| {
+            format!(
+                "[{}] <{}> {}",
+                format_ago(l.datetime, clock.now()),
+                l.nick,
+                l.text
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub async fn grep(
+    args: &[String],
+    target: &str,
+    network: &str,
+    clock: &Clock,
+    db: Arc<Mutex<Database>>,
+) -> String {
+    if args.is_empty() {
+        return String::from("Please provide a pattern.");
+    }
+
+    let pattern = match RegexBuilder::new(&args.join(" "))
+        .case_insensitive(true)
+        .size_limit(MAX_PATTERN_SIZE)
+        .build()
+    {
+        Ok(pattern) => pattern,
+        Err(_) => return String::from("Invalid pattern."),
+    };
+    let lines = match lines(network, &db, |l| {
+        l.channel.eq_ignore_ascii_case(target) && pattern.is_match(&l.text)
+    })
+    .await
+    {
+        Ok(lines) => lines,
+        Err(error) => return error,
+    };
+
+    if lines.is_empty() {
+        return format!("Could not find {} in the logs of {target}.", args.join(" "));
+    }
+
+    lines[lines.len().saturating_sub(MAX_MATCHES)..]
+        .iter()
+        .map(|l| {
+            format!(
+                "[{}] <{}> {}",
+                format_ago(l.datetime, clock.now()),
+                l.nick,
+                l.text
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Opts nick out of the logs, deleting the lines already logged, or back in.
+pub async fn log(args: &[String], nick: &str, network: &str, db: Arc<Mutex<Database>>) -> String {
+    let db = db.lock().await;
+    let opted_out = match db.select("log_opt_outs", |o: &OptOut| {
+        o.nick.eq_ignore_ascii_case(nick) && o.network == network
+    }) {
+        Ok(opt_outs) => !opt_outs.unwrap_or_default().is_empty(),
+        Err(_) => return String::from("Could not read the opt-outs."),
+    };
+
+    match args.first().map(|a| a.to_lowercase()).as_deref() {
+        Some("off") => {
+            if !opted_out
+                && db
+                    .insert(
+                        "log_opt_outs",
+                        OptOut {
+                            nick: nick.to_lowercase(),
+                            network: String::from(network),
+                        },
+                    )
+                    .is_err()
+            {
+                return String::from("Could not opt out of the logs.");
+            }
+
+            match db.delete("logs", |l: &&LogLine| {
+                l.nick.eq_ignore_ascii_case(nick) && l.network == network
+            }) {
+                Ok(()) => String::from(
+                    "Your messages won't be logged anymore and those already logged were deleted.",
+                ),
+                Err(_) => String::from("Could not delete your logged messages."),
+            }
+        }
+        Some("on") => match db.delete("log_opt_outs", |o: &&OptOut| {
+            o.nick.eq_ignore_ascii_case(nick) && o.network == network
+        }) {
+            Ok(()) => String::from("Your messages are logged again on logged channels."),
+            Err(_) => String::from("Could not opt back in to the logs."),
+        },
+        _ => match opted_out {
+            true => String::from("Your messages are not logged. Use log on to opt back in."),
+            false => {
+                String::from("Your messages are logged on logged channels. Use log off to opt out.")
+            }
+        },
+    }
+}
@@ -1,12 +1,9 @@
+use crate::queue::{MessageQueue, Priority};
 use chrono::{Duration, Utc};
-use irc::client::prelude::Command;
-use irc::client::Client;
 use newsapi::api::NewsAPIClient;
 use newsapi::constants::{Country, SortMethod};
 use newsapi::payload::article::Articles;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 
 fn country(name: &str) -> Option<Country> {
     match name.to_lowercase().as_str() {
@@ -27,7 +24,7 @@ fn country(name: &str) -> Option<Country> {
 pub async fn news(
     search: &[String],
     target: &str,
-    client: Arc<Mutex<Client>>,
+    queue: MessageQueue,
     options: &HashMap<String, String>,
 ) -> String {
     if search.is_empty() {
@@ -73,16 +70,8 @@ pub async fn news(
     };
 
     for article in articles.articles.iter().take(amount) {
-        if client
-            .lock()
-            .await
-            .send(Command::PRIVMSG(
-                String::from(target),
-                article.title.clone(),
-            ))
-            .is_err()
-        {
-            eprintln!("error");
+        if let Err(error) = queue.send(target, &article.title, Priority::Normal) {
+            eprintln!("{error}");
         }
     }
 
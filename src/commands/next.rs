@@ -1,13 +1,19 @@
-use crate::database::{CsvRecord, Database};
+use crate::clock::Clock;
+use crate::database::{CsvRecord, Database, Identified};
+use crate::network;
 use crate::tasks::next::Interest;
 use chrono::{DateTime, Utc};
 use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use utoipa::ToSchema;
 
-#[derive(PartialEq, Serialize, Deserialize)]
+#[derive(PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Event {
+    // Stored in the last column, after the network.
+    #[serde(default)]
+    pub id: u64,
     pub category: String,
     pub name: String,
     pub description: String,
@@ -15,11 +21,17 @@ pub struct Event {
     pub channel: String,
     pub tags: String,
     pub notify: bool,
+    #[serde(default)]
+    pub network: String,
 }
 
 impl CsvRecord for Event {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(8)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             category: fields[0].clone(),
             name: fields[1].clone(),
             description: fields[2].clone(),
@@ -30,6 +42,10 @@ impl CsvRecord for Event {
             channel: fields[4].clone(),
             tags: fields[5].clone(),
             notify: fields[6].parse().unwrap_or_default(),
+            network: fields
+                .get(7)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -42,13 +58,26 @@ impl CsvRecord for Event {
             self.channel.clone(),
             self.tags.clone(),
             self.notify.to_string(),
+            self.network.clone(),
+            self.id.to_string(),
         ]
     }
 }
 
+impl Identified for Event {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+}
+
 struct TimeZone {
     nick: String,
     name: String,
+    network: String,
 }
 
 impl CsvRecord for TimeZone {
@@ -56,19 +85,31 @@ impl CsvRecord for TimeZone {
         Self {
             nick: fields[0].clone(),
             name: fields[1].clone(),
+            network: fields
+                .get(2)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
     fn to_fields(&self) -> Vec<String> {
-        vec![self.nick.clone(), self.name.clone()]
+        vec![self.nick.clone(), self.name.clone(), self.network.clone()]
     }
 }
 
-pub async fn next(args: &[String], nick: &str, target: &str, db: Arc<Mutex<Database>>) -> String {
+pub async fn next(
+    args: &[String],
+    nick: &str,
+    target: &str,
+    network: &str,
+    clock: &Clock,
+    db: Arc<Mutex<Database>>,
+) -> String {
     let search = &args.join(" ").to_lowercase();
     let mut events: Vec<Event> = match db.lock().await.select("events", |e: &Event| {
-        e.datetime > Utc::now()
+        e.datetime > clock.now()
             && e.channel.to_lowercase() == target.to_lowercase()
+            && e.network == network
             && (e.category.to_lowercase().contains(search)
                 || e.description.to_lowercase().contains(search)
                 || e.tags.to_lowercase().contains(search))
@@ -81,18 +122,20 @@ pub async fn next(args: &[String], nick: &str, target: &str, db: Arc<Mutex<Datab
     };
 
     let time_zones: Vec<TimeZone> = match db.lock().await.select("time_zones", |tz: &TimeZone| {
-        tz.nick.to_lowercase() == nick.to_lowercase()
+        tz.nick.to_lowercase() == nick.to_lowercase() && tz.network == network
     }) {
         Ok(time_zones_result) => match time_zones_result {
             Some(time_zones) => time_zones,
             None => vec![TimeZone {
                 nick: String::new(),
                 name: String::from("Europe/Berlin"),
+                network: String::from(network),
             }],
         },
         Err(_) => vec![TimeZone {
             nick: String::new(),
             name: String::from("Europe/Berlin"),
+            network: String::from(network),
         }],
     };
 
@@ -104,7 +147,7 @@ pub async fn next(args: &[String], nick: &str, target: &str, db: Arc<Mutex<Datab
 
         events.sort_by(|a, b| a.datetime.cmp(&b.datetime));
 
-        let duration = events[0].datetime.signed_duration_since(Utc::now());
+        let duration = events[0].datetime.signed_duration_since(clock.now());
 
         format!(
             "{} | {} {} {} | {} day(s), {} hour(s), {} minute(s)",
@@ -124,10 +167,15 @@ pub async fn next(args: &[String], nick: &str, target: &str, db: Arc<Mutex<Datab
     }
 }
 
-pub async fn interests(args: &[String], nick: &str, db: Arc<Mutex<Database>>) -> String {
+pub async fn interests(
+    args: &[String],
+    nick: &str,
+    network: &str,
+    db: Arc<Mutex<Database>>,
+) -> String {
     if args.is_empty() {
         match db.lock().await.select("interests", |i: &Interest| {
-            i.nick.to_lowercase() == nick.to_lowercase()
+            i.nick.to_lowercase() == nick.to_lowercase() && i.network == network
         }) {
             Ok(Some(interests)) => return interests[0].tags.clone(),
             _ => return String::from("Could not get interests."),
@@ -135,15 +183,17 @@ pub async fn interests(args: &[String], nick: &str, db: Arc<Mutex<Database>>) ->
     }
 
     let interest = Interest {
+        id: 0,
         nick: nick.to_string(),
         tags: args.join(" "),
+        network: network.to_string(),
     };
 
     match db
         .lock()
         .await
-        .update("interests", interest, |i: &&Interest| {
-            i.nick.to_lowercase() == nick.to_lowercase()
+        .update_keeping_id("interests", interest, |i: &&Interest| {
+            i.nick.to_lowercase() == nick.to_lowercase() && i.network == network
         }) {
         Ok(_) => String::from("Your interests were updated."),
         Err(_) => String::from("Could not update your interests."),
@@ -76,8 +76,12 @@ pub async fn omdb(args: &[String], options: &HashMap<String, String>) -> String
         return String::from("Please provide a movie or series title.");
     }
 
+    let api_url = match options.get("omdb_api_url") {
+        Some(api_url) => api_url.as_str(),
+        None => OMDB_API_URL,
+    };
     let omdb: OmDb = match reqwest::get(format!(
-        "{OMDB_API_URL}/?apikey={}&t={}",
+        "{api_url}/?apikey={}&t={}",
         match options.get("omdb_api_key") {
             Some(value) => value,
             None => "",
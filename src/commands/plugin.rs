@@ -1,35 +1,573 @@
-use std::{collections::HashMap, process::Command};
-use tokio::task;
+mod wasm;
 
+use crate::database::Database;
+use crate::queue::{MessageQueue, Priority};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tokio::time::{self, Duration};
+
+const DEFAULT_TIMEOUT: u64 = 10;
+const DEFAULT_MAX_OUTPUT: u64 = 65536;
+// Plugins run with an empty environment except for PATH, so that interpreters can still be found.
+const PLUGIN_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+// Written to the stdin of the plugin as a single line of JSON.
+#[derive(Serialize)]
+struct PluginRequest<'a> {
+    command: &'a str,
+    args: &'a [String],
+    nick: &'a str,
+    target: &'a str,
+    network: &'a str,
+}
+
+// Printed by plugins using the JSON protocol instead of plain text.
+#[derive(Deserialize)]
+struct PluginResponse {
+    #[serde(default)]
+    messages: Vec<PluginMessage>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PluginMessage {
+    target: Option<String>,
+    text: String,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginKind {
+    // Run once for each command.
+    #[default]
+    Command,
+    // Kept running while connected, reading events on stdin and writing actions on stdout.
+    Events,
+    // Run once for each command by the WebAssembly runtime, from <name>.wasm.
+    Wasm,
+}
+
+// Description of a plugin, read from <name>.toml next to its executable.
+#[derive(Clone, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default, rename = "type")]
+    pub kind: PluginKind,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub usage: String,
+    // Channels the plugin can be used on. Any channel when empty.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    // Overrides the plugin_timeout option.
+    pub timeout: Option<u64>,
+    // Seconds between the timer events sent to an events plugin. No timer events when unset.
+    pub interval: Option<u64>,
+    // Hosts a WebAssembly plugin may fetch from.
+    #[serde(default)]
+    pub http_allow: Vec<String>,
+    // Override the wasm_fuel and wasm_max_memory options for a WebAssembly plugin.
+    pub fuel: Option<u64>,
+    pub max_memory: Option<usize>,
+    // Compiled once when a WebAssembly plugin is loaded.
+    #[serde(skip)]
+    module: Option<wasm::Module>,
+}
+
+impl Manifest {
+    fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            kind: PluginKind::Command,
+            aliases: Vec::new(),
+            description: String::new(),
+            usage: String::new(),
+            channels: Vec::new(),
+            timeout: None,
+            interval: None,
+            http_allow: Vec::new(),
+            fuel: None,
+            max_memory: None,
+            module: None,
+        }
+    }
+
+    // Name of the file holding the plugin in plugins_path.
+    fn file_name(&self) -> String {
+        match self.kind {
+            PluginKind::Wasm => format!("{}.wasm", self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    pub fn allowed(&self, target: &str) -> bool {
+        self.channels.is_empty()
+            || self
+                .channels
+                .iter()
+                .any(|channel| channel.eq_ignore_ascii_case(target))
+    }
+
+    pub fn timeout(&self, options: &HashMap<String, String>) -> u64 {
+        match self.timeout {
+            Some(timeout) => timeout,
+            None => match options.get("plugin_timeout") {
+                Some(timeout) => timeout.parse().unwrap_or(DEFAULT_TIMEOUT),
+                None => DEFAULT_TIMEOUT,
+            },
+        }
+    }
+}
+
+// The plugins found in plugins_path when the bot connects.
+#[derive(Default)]
+pub struct Plugins {
+    manifests: Vec<Manifest>,
+}
+
+impl Plugins {
+    // Every <name>.toml manifest is loaded, and executables without a manifest are added under
+    // their file name, so that plugins written before manifests existed keep working. The same
+    // goes for <name>.wasm files, added as WebAssembly plugins.
+    pub fn load(options: &HashMap<String, String>) -> Self {
+        let path = plugins_path(options);
+        let entries = match fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(_) => return Self::default(),
+        };
+        let mut manifests: Vec<Manifest> = Vec::new();
+        let mut executables: Vec<String> = Vec::new();
+        let mut modules: Vec<Manifest> = Vec::new();
+
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+
+            if file_name.starts_with('.') || !entry.path().is_file() {
+                continue;
+            }
+
+            if let Some(name) = file_name.strip_suffix(".wasm") {
+                let mut manifest = Manifest::new(name);
+
+                manifest.kind = PluginKind::Wasm;
+                modules.push(manifest);
+
+                continue;
+            }
+
+            if !file_name.ends_with(".toml") {
+                executables.push(file_name);
+
+                continue;
+            }
+
+            match fs::read_to_string(entry.path()) {
+                Ok(contents) => match toml::from_str::<Manifest>(&contents) {
+                    Ok(manifest) => manifests.push(manifest),
+                    Err(error) => eprintln!("Invalid plugin manifest ({file_name}): {error}"),
+                },
+                Err(error) => eprintln!("Could not read plugin manifest ({file_name}): {error}"),
+            }
+        }
+
+        for executable in executables {
+            if !manifests.iter().any(|m| m.name == executable) {
+                manifests.push(Manifest::new(&executable));
+            }
+        }
+
+        for module in modules {
+            if !manifests.iter().any(|m| m.name == module.name) {
+                manifests.push(module);
+            }
+        }
+
+        manifests.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut plugins = Self::default();
+
+        for mut manifest in manifests {
+            if !valid_name(&manifest.name) {
+                eprintln!("Invalid plugin name: {}", manifest.name);
+
+                continue;
+            }
+
+            let file = format!("{path}/{}", manifest.file_name());
+
+            if !Path::new(&file).is_file() {
+                eprintln!("Could not find the executable of plugin {}.", manifest.name);
+
+                continue;
+            }
+
+            if manifest.kind == PluginKind::Wasm {
+                match wasm::compile(&file) {
+                    Ok(module) => manifest.module = Some(module),
+                    Err(error) => {
+                        eprintln!("Could not load plugin {}: {error}", manifest.name);
+
+                        continue;
+                    }
+                }
+            }
+
+            if plugins.find(&manifest.name).is_some() {
+                eprintln!("Duplicate plugin name: {}", manifest.name);
+
+                continue;
+            }
+
+            manifest.aliases.retain(|alias| {
+                let taken =
+                    plugins.find(alias).is_some() || alias.eq_ignore_ascii_case(&manifest.name);
+
+                if taken {
+                    eprintln!("Duplicate plugin alias: {alias}");
+                }
+
+                !taken
+            });
+            plugins.manifests.push(manifest);
+        }
+
+        plugins
+    }
+
+    // The plugin named command, or having command as an alias.
+    pub fn find(&self, command: &str) -> Option<&Manifest> {
+        self.manifests.iter().find(|m| {
+            m.name.eq_ignore_ascii_case(command)
+                || m.aliases.iter().any(|a| a.eq_ignore_ascii_case(command))
+        })
+    }
+
+    // The plugins run as commands, leaving out events plugins.
+    pub fn commands(&self) -> impl Iterator<Item = &Manifest> {
+        self.manifests
+            .iter()
+            .filter(|m| m.kind != PluginKind::Events)
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &Manifest> {
+        self.manifests
+            .iter()
+            .filter(|m| m.kind == PluginKind::Events)
+    }
+}
+
+// Lists the installed plugins, or describes one of them.
+pub async fn plugins(args: &[String], plugins: &Plugins) -> String {
+    if args.is_empty() {
+        if plugins.manifests.is_empty() {
+            return String::from("No plugins installed.");
+        }
+
+        return format!(
+            "Plugins: {}",
+            plugins
+                .manifests
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(" | ")
+        );
+    }
+
+    let manifest = match plugins.find(&args[0]) {
+        Some(manifest) => manifest,
+        None => return format!("Could not find plugin {}.", args[0]),
+    };
+    let mut description = manifest.name.clone();
+
+    if !manifest.aliases.is_empty() {
+        description.push_str(&format!(" ({})", manifest.aliases.join(", ")));
+    }
+
+    if !manifest.description.is_empty() {
+        description.push_str(&format!(": {}", manifest.description));
+    }
+
+    if !manifest.usage.is_empty() {
+        description.push_str(&format!(" Usage: {}", manifest.usage));
+    }
+
+    description
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn plugin(
-    name: &str,
+    manifest: &Manifest,
     args: &[String],
     nick: &str,
     target: &str,
+    network: &str,
     options: &HashMap<String, String>,
+    db: Arc<Mutex<Database>>,
+    queue: MessageQueue,
 ) -> String {
-    let path = match options.get("plugins_path") {
+    let name = manifest.name.as_str();
+
+    if manifest.kind == PluginKind::Events {
+        return String::new();
+    }
+
+    if !manifest.allowed(target) {
+        return String::from("This command is not available here.");
+    }
+
+    let mut argv: Vec<String> = args.to_vec();
+
+    argv.insert(0, String::from(nick));
+    argv.insert(1, String::from(target));
+
+    let request = serde_json::to_string(&PluginRequest {
+        command: name,
+        args,
+        nick,
+        target,
+        network,
+    })
+    .unwrap_or_default();
+    let output = match manifest.kind {
+        PluginKind::Wasm => {
+            wasm::run(
+                manifest,
+                &argv,
+                &request,
+                target,
+                network,
+                options,
+                db,
+                queue.clone(),
+            )
+            .await
+        }
+        _ => process(manifest, &argv, &request, options).await,
+    };
+    let (stdout, success) = match output {
+        Ok(output) => output,
+        Err(reply) => return reply,
+    };
+
+    if stdout.trim_start().starts_with('{') {
+        if let Ok(response) = serde_json::from_str::<PluginResponse>(stdout.trim()) {
+            return respond(name, response, target, queue);
+        }
+    }
+
+    if !success && stdout.trim().is_empty() {
+        return format!("Plugin {name} failed.");
+    }
+
+    stdout.replace('\n', "\r\n")
+}
+
+// Runs an executable plugin, giving its output and whether it succeeded. Gives the reply instead
+// when the plugin could not run or timed out.
+async fn process(
+    manifest: &Manifest,
+    argv: &[String],
+    request: &str,
+    options: &HashMap<String, String>,
+) -> Result<(String, bool), String> {
+    let name = manifest.name.as_str();
+    let timeout = manifest.timeout(options);
+    let max_output = max_output(options);
+    let mut command = match command(manifest, argv, options) {
+        Some(command) => command,
+        None => return Err(String::new()),
+    };
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            if error.kind() != ErrorKind::NotFound {
+                eprintln!("Could not run plugin {name}: {error}");
+            }
+
+            return Err(String::new());
+        }
+    };
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let run = async {
+        // Plugins are free to ignore stdin, so a failed write is not an error.
+        if let Some(mut stdin) = stdin {
+            let _ = stdin.write_all(format!("{request}\n").as_bytes()).await;
+        }
+
+        let (stdout, stderr) = tokio::join!(
+            read_capped(stdout, max_output),
+            read_capped(stderr, max_output)
+        );
+
+        (stdout, stderr, child.wait().await)
+    };
+    // The plugin is killed when the child is dropped, which also happens on timeout.
+    let ((stdout, truncated), (stderr, _), status) =
+        match time::timeout(Duration::from_secs(timeout), run).await {
+            Ok(output) => output,
+            Err(_) => return Err(format!("Plugin {name} timed out.")),
+        };
+    let success = match status {
+        Ok(status) => {
+            if !status.success() {
+                eprintln!("Plugin {name} exited with {status}.");
+            }
+
+            status.success()
+        }
+        Err(_) => false,
+    };
+
+    if !stderr.trim().is_empty() {
+        eprintln!("Plugin {name}: {}", stderr.trim());
+    }
+
+    if truncated {
+        eprintln!("Output of plugin {name} was cut at {max_output} bytes.");
+    }
+
+    Ok((stdout, success))
+}
+
+// The command running a plugin in a clean environment, with piped stdin, stdout and stderr. None
+// when the plugin can't be found.
+pub fn command(
+    manifest: &Manifest,
+    argv: &[String],
+    options: &HashMap<String, String>,
+) -> Option<Command> {
+    // The name ends up in the path of the executable, so it must not lead out of plugins_path.
+    if !valid_name(&manifest.name) {
+        return None;
+    }
+
+    // The path is made absolute, since a relative one is ambiguous once the working directory of
+    // the plugin is changed.
+    let program = match fs::canonicalize(format!("{}/{}", plugins_path(options), manifest.name)) {
+        Ok(program) => program,
+        Err(_) => return None,
+    };
+    let mut command = Command::new(program);
+
+    command
+        .args(argv)
+        .env_clear()
+        .env("PATH", PLUGIN_PATH)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    if let Some(working_dir) = options.get("plugins_working_dir") {
+        command.current_dir(working_dir);
+    }
+
+    Some(command)
+}
+
+pub fn max_output(options: &HashMap<String, String>) -> u64 {
+    match options.get("plugin_max_output") {
+        Some(max_output) => max_output.parse().unwrap_or(DEFAULT_MAX_OUTPUT),
+        None => DEFAULT_MAX_OUTPUT,
+    }
+}
+
+// Carries out a line printed by an events plugin. Every message needs a target, since the output
+// of the plugin isn't the reply to anything.
+pub fn act(name: &str, line: &str, queue: &MessageQueue) {
+    if line.trim().is_empty() {
+        return;
+    }
+
+    let response: PluginResponse = match serde_json::from_str(line) {
+        Ok(response) => response,
+        Err(error) => {
+            eprintln!("Invalid output of plugin {name} ({line}): {error}");
+
+            return;
+        }
+    };
+
+    for message in response.messages {
+        match message.target {
+            Some(target) => {
+                if let Err(error) = queue.send(&target, &message.text, Priority::Normal) {
+                    eprintln!("{error}");
+                }
+            }
+            None => eprintln!(
+                "Plugin {name} sent a message without target: {}",
+                message.text
+            ),
+        }
+    }
+
+    if let Some(error) = response.error {
+        eprintln!("Plugin {name} error: {error}");
+    }
+}
+
+fn plugins_path(options: &HashMap<String, String>) -> String {
+    match options.get("plugins_path") {
         Some(path) => path.to_owned(),
         None => "plugins".to_owned(),
-    };
-    let name = name.to_owned();
-    let mut args: Vec<String> = args.to_vec();
+    }
+}
 
-    args.insert(0, String::from(nick));
-    args.insert(1, String::from(target));
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0'])
+}
+
+// Messages to the target of the command are returned as the reply, any other message is queued.
+fn respond(name: &str, response: PluginResponse, target: &str, queue: MessageQueue) -> String {
+    let mut reply: Vec<String> = Vec::new();
 
-    match task::spawn_blocking(move || {
-        match Command::new(format!("{}/{}", path, name))
-            .args(args)
-            .output()
-        {
-            Ok(output) => String::from_utf8_lossy(&output.stdout).replace('\n', "\r\n"),
-            Err(_) => String::new(),
+    for message in response.messages {
+        match message.target {
+            Some(message_target) if !message_target.eq_ignore_ascii_case(target) => {
+                if let Err(error) = queue.send(&message_target, &message.text, Priority::Normal) {
+                    eprintln!("{error}");
+                }
+            }
+            _ => reply.push(message.text),
+        }
+    }
+
+    if let Some(error) = response.error {
+        reply.push(format!("Plugin {name} error: {error}"));
+    }
+
+    reply.join("\r\n")
+}
+
+// Reads at most max bytes and tells whether there was more. The rest is left unread, so a plugin
+// that keeps writing gets a broken pipe once the reader is dropped.
+async fn read_capped<R: AsyncRead + Unpin>(reader: Option<R>, max: u64) -> (String, bool) {
+    let mut buffer = Vec::new();
+
+    if let Some(reader) = reader {
+        if let Err(error) = reader.take(max + 1).read_to_end(&mut buffer).await {
+            eprintln!("{error}");
         }
-    })
-    .await
-    {
-        Ok(output) => output,
-        Err(_) => String::new(),
     }
+
+    let truncated = buffer.len() as u64 > max;
+
+    buffer.truncate(max as usize);
+
+    (String::from_utf8_lossy(&buffer).into_owned(), truncated)
 }
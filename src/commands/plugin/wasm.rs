@@ -0,0 +1,469 @@
+use super::Manifest;
+use crate::database::{CsvRecord, Database};
+use crate::queue::{MessageQueue, Priority};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::Mutex;
+use tokio::task;
+use url::Url;
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Memory, Store, StoreLimits, Trap};
+use wasmtime::{Result as WasmResult, StoreLimitsBuilder};
+use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{I32Exit, WasiCtxBuilder};
+
+pub use wasmtime::Module;
+
+const DEFAULT_FUEL: u64 = 1_000_000_000;
+// In MiB.
+const DEFAULT_MAX_MEMORY: usize = 64;
+const HTTP_TIMEOUT: u64 = 10;
+// Module of the functions the bot offers to plugins, besides WASI.
+const HOST_MODULE: &str = "gluon";
+
+static ENGINE: OnceLock<Engine> = OnceLock::new();
+
+// Values a plugin keeps between runs, through kv_get, kv_set and kv_delete.
+#[derive(PartialEq)]
+struct PluginValue {
+    plugin: String,
+    key: String,
+    value: String,
+    network: String,
+}
+
+impl CsvRecord for PluginValue {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            plugin: fields[0].clone(),
+            key: fields[1].clone(),
+            value: fields[2].clone(),
+            network: fields[3].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.plugin.clone(),
+            self.key.clone(),
+            self.value.clone(),
+            self.network.clone(),
+        ]
+    }
+}
+
+// Everything a running plugin can reach.
+struct Host {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+    plugin: String,
+    network: String,
+    http_allow: Vec<String>,
+    // Channels or nicks send may reach.
+    targets: Vec<String>,
+    max_output: usize,
+    replies: Vec<String>,
+    // Value of the last kv_get or http_get, copied to the plugin by result.
+    result: Vec<u8>,
+    db: Arc<Mutex<Database>>,
+    queue: MessageQueue,
+    handle: Handle,
+}
+
+// The engine shared by every plugin. Its epoch goes up every second, which is how plugins running
+// for too long are stopped.
+fn engine() -> &'static Engine {
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+
+        config.consume_fuel(true).epoch_interruption(true);
+
+        let engine = Engine::new(&config).expect("Invalid WebAssembly configuration.");
+        let ticker = engine.clone();
+
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+            ticker.increment_epoch();
+        });
+
+        engine
+    })
+}
+
+pub fn compile(path: &str) -> Result<Module, String> {
+    match Module::from_file(engine(), path) {
+        Ok(module) => Ok(module),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+// Runs the _start function of a WebAssembly plugin with the same arguments and stdin as an
+// executable plugin. Whatever the plugin replies comes before what it writes to stdout.
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    manifest: &Manifest,
+    argv: &[String],
+    request: &str,
+    target: &str,
+    network: &str,
+    options: &HashMap<String, String>,
+    db: Arc<Mutex<Database>>,
+    queue: MessageQueue,
+) -> Result<(String, bool), String> {
+    let name = manifest.name.clone();
+    let module = match &manifest.module {
+        Some(module) => module.clone(),
+        None => return Err(String::new()),
+    };
+    let timeout = manifest.timeout(options);
+    let max_output = super::max_output(options) as usize;
+    let fuel = match manifest.fuel {
+        Some(fuel) => fuel,
+        None => match options.get("wasm_fuel") {
+            Some(fuel) => fuel.parse().unwrap_or(DEFAULT_FUEL),
+            None => DEFAULT_FUEL,
+        },
+    };
+    let max_memory: usize = match manifest.max_memory {
+        Some(max_memory) => max_memory,
+        None => match options.get("wasm_max_memory") {
+            Some(max_memory) => max_memory.parse().unwrap_or(DEFAULT_MAX_MEMORY),
+            None => DEFAULT_MAX_MEMORY,
+        },
+    };
+    // The plugin may only send to the channels of its manifest, or where it was run from.
+    let targets = match manifest.channels.is_empty() {
+        true => vec![String::from(target)],
+        false => manifest.channels.clone(),
+    };
+    let stdout = MemoryOutputPipe::new(max_output);
+    let mut args = vec![name.clone()];
+
+    args.extend_from_slice(argv);
+
+    let host = Host {
+        wasi: WasiCtxBuilder::new()
+            .args(&args)
+            .stdin(MemoryInputPipe::new(format!("{request}\n")))
+            .stdout(stdout.clone())
+            .build_p1(),
+        limits: StoreLimitsBuilder::new()
+            .memory_size(max_memory.saturating_mul(1024 * 1024))
+            .instances(1)
+            .build(),
+        plugin: name.clone(),
+        network: String::from(network),
+        http_allow: manifest.http_allow.clone(),
+        targets,
+        max_output,
+        replies: Vec::new(),
+        result: Vec::new(),
+        db,
+        queue,
+        handle: Handle::current(),
+    };
+    // Host functions block on the runtime, so the plugin runs on a thread of its own.
+    let (result, replies) =
+        match task::spawn_blocking(move || call(module, host, fuel, timeout)).await {
+            Ok(output) => output,
+            Err(error) => {
+                eprintln!("Plugin {name}: {error}");
+
+                return Err(format!("Plugin {name} failed."));
+            }
+        };
+    let success = match result {
+        Ok(()) => true,
+        Err(error) => match error.downcast_ref::<I32Exit>() {
+            Some(exit) => exit.0 == 0,
+            None => match error.downcast_ref::<Trap>() {
+                Some(Trap::Interrupt) => return Err(format!("Plugin {name} timed out.")),
+                Some(Trap::OutOfFuel) => return Err(format!("Plugin {name} ran out of fuel.")),
+                _ => {
+                    eprintln!("Plugin {name}: {error:?}");
+
+                    false
+                }
+            },
+        },
+    };
+    let mut output: String = replies.iter().map(|reply| format!("{reply}\n")).collect();
+
+    output.push_str(&String::from_utf8_lossy(&stdout.contents()));
+
+    Ok((output, success))
+}
+
+fn call(module: Module, host: Host, fuel: u64, timeout: u64) -> (WasmResult<()>, Vec<String>) {
+    let mut store = Store::new(engine(), host);
+    let result = start(&mut store, &module, fuel, timeout);
+
+    (result, std::mem::take(&mut store.data_mut().replies))
+}
+
+fn start(store: &mut Store<Host>, module: &Module, fuel: u64, timeout: u64) -> WasmResult<()> {
+    let mut linker: Linker<Host> = Linker::new(engine());
+
+    preview1::add_to_linker_sync(&mut linker, |host| &mut host.wasi)?;
+    host_functions(&mut linker)?;
+    store.limiter(|host| &mut host.limits);
+    store.set_fuel(fuel)?;
+    store.set_epoch_deadline(timeout);
+
+    let instance = linker.instantiate(&mut *store, module)?;
+    let start = instance.get_typed_func::<(), ()>(&mut *store, "_start")?;
+
+    start.call(&mut *store, ())
+}
+
+// The host API. Strings are passed as a pointer and a length into the memory of the plugin.
+// Functions returning an i32 give -1 on failure, and kv_get and http_get give the length of the
+// value, which the plugin then copies into its memory with result.
+fn host_functions(linker: &mut Linker<Host>) -> WasmResult<()> {
+    linker.func_wrap(
+        HOST_MODULE,
+        "reply",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> WasmResult<()> {
+            let text = read(&mut caller, ptr, len)?;
+
+            caller.data_mut().replies.push(text);
+
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "send",
+        |mut caller: Caller<'_, Host>,
+         target_ptr: i32,
+         target_len: i32,
+         text_ptr: i32,
+         text_len: i32|
+         -> WasmResult<i32> {
+            let target = read(&mut caller, target_ptr, target_len)?;
+            let text = read(&mut caller, text_ptr, text_len)?;
+            let host = caller.data();
+
+            if !sendable(&target, &text, &host.targets) {
+                eprintln!("Plugin {} may not send to {target:?}.", host.plugin);
+
+                return Ok(-1);
+            }
+
+            match host.queue.send(&target, &text, Priority::Normal) {
+                Ok(()) => Ok(0),
+                Err(error) => {
+                    eprintln!("{error}");
+
+                    Ok(-1)
+                }
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "kv_get",
+        |mut caller: Caller<'_, Host>, key_ptr: i32, key_len: i32| -> WasmResult<i32> {
+            let key = read(&mut caller, key_ptr, key_len)?;
+            let host = caller.data_mut();
+            let values =
+                host.handle
+                    .block_on(host.db.lock())
+                    .select("plugin_store", |v: &PluginValue| {
+                        v.plugin == host.plugin && v.network == host.network && v.key == key
+                    });
+
+            match values {
+                Ok(Some(values)) => {
+                    host.result = values[0].value.clone().into_bytes();
+
+                    Ok(host.result.len() as i32)
+                }
+                Ok(None) => Ok(-1),
+                Err(_) => {
+                    eprintln!("Could not get plugin value.");
+
+                    Ok(-1)
+                }
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "kv_set",
+        |mut caller: Caller<'_, Host>,
+         key_ptr: i32,
+         key_len: i32,
+         value_ptr: i32,
+         value_len: i32|
+         -> WasmResult<i32> {
+            let key = read(&mut caller, key_ptr, key_len)?;
+            let value = read(&mut caller, value_ptr, value_len)?;
+            let host = caller.data();
+            let plugin_value = PluginValue {
+                plugin: host.plugin.clone(),
+                key: key.clone(),
+                value,
+                network: host.network.clone(),
+            };
+            let updated = host.handle.block_on(host.db.lock()).update(
+                "plugin_store",
+                plugin_value,
+                |v: &&PluginValue| {
+                    v.plugin == host.plugin && v.network == host.network && v.key == key
+                },
+            );
+
+            match updated {
+                Ok(()) => Ok(0),
+                Err(_) => {
+                    eprintln!("Could not set plugin value.");
+
+                    Ok(-1)
+                }
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "kv_delete",
+        |mut caller: Caller<'_, Host>, key_ptr: i32, key_len: i32| -> WasmResult<i32> {
+            let key = read(&mut caller, key_ptr, key_len)?;
+            let host = caller.data();
+            let deleted =
+                host.handle
+                    .block_on(host.db.lock())
+                    .delete("plugin_store", |v: &&PluginValue| {
+                        v.plugin == host.plugin && v.network == host.network && v.key == key
+                    });
+
+            match deleted {
+                Ok(()) => Ok(0),
+                Err(_) => {
+                    eprintln!("Could not delete plugin value.");
+
+                    Ok(-1)
+                }
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "http_get",
+        |mut caller: Caller<'_, Host>, url_ptr: i32, url_len: i32| -> WasmResult<i32> {
+            let url = read(&mut caller, url_ptr, url_len)?;
+            let host = caller.data_mut();
+
+            if !allowed(&url, &host.http_allow) {
+                eprintln!("Plugin {} may not fetch {url}.", host.plugin);
+
+                return Ok(-1);
+            }
+
+            match host
+                .handle
+                .block_on(fetch(&url, &host.http_allow, host.max_output))
+            {
+                Ok(body) => {
+                    host.result = body;
+
+                    Ok(host.result.len() as i32)
+                }
+                Err(error) => {
+                    eprintln!("{error}");
+
+                    Ok(-1)
+                }
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "result",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> WasmResult<i32> {
+            let result = caller.data().result.clone();
+            let len = result.len().min(len.max(0) as usize);
+
+            memory(&mut caller)?.write(&mut caller, ptr as u32 as usize, &result[..len])?;
+
+            Ok(len as i32)
+        },
+    )?;
+
+    Ok(())
+}
+
+fn memory(caller: &mut Caller<'_, Host>) -> WasmResult<Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => Err(wasmtime::Error::msg(
+            "The plugin does not export its memory.",
+        )),
+    }
+}
+
+fn read(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> WasmResult<String> {
+    let len = len as u32 as usize;
+
+    if len > caller.data().max_output {
+        return Err(wasmtime::Error::msg("The string is too long."));
+    }
+
+    let mut buffer = vec![0; len];
+
+    memory(caller)?.read(&*caller, ptr as u32 as usize, &mut buffer)?;
+
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+// Targets and texts with raw line breaks or null characters could smuggle IRC commands, and targets
+// hold no whitespace at all.
+fn sendable(target: &str, text: &str, targets: &[String]) -> bool {
+    !target.contains(|c: char| c.is_whitespace() || c == '\0')
+        && !text.contains(['\r', '\0'])
+        && targets.iter().any(|t| t.eq_ignore_ascii_case(target))
+}
+
+fn allowed(url: &str, http_allow: &[String]) -> bool {
+    match Url::parse(url) {
+        Ok(url) => {
+            matches!(url.scheme(), "http" | "https")
+                && url
+                    .host_str()
+                    .is_some_and(|host| http_allow.iter().any(|a| a.eq_ignore_ascii_case(host)))
+        }
+        Err(_) => false,
+    }
+}
+
+// Redirects are only followed to allowed hosts as well. The body is read until max bytes, the rest
+// is never downloaded.
+async fn fetch(url: &str, http_allow: &[String], max: usize) -> Result<Vec<u8>, reqwest::Error> {
+    let http_allow = http_allow.to_vec();
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(HTTP_TIMEOUT))
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if allowed(attempt.url().as_str(), &http_allow) {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        }))
+        .build()?;
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    let mut body = Vec::new();
+
+    while body.len() < max {
+        match response.chunk().await? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+
+    body.truncate(max);
+
+    Ok(body)
+}
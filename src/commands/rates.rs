@@ -109,8 +109,12 @@ pub async fn rates(args: &[String], options: &HashMap<String, String>) -> String
         0 => "EUR",
         _ => &args[0],
     };
+    let api_url = match options.get("currency_api_url") {
+        Some(api_url) => api_url.as_str(),
+        None => CURRENCY_API_URL,
+    };
     let currencies: Currency = match reqwest::get(format!(
-        "{CURRENCY_API_URL}/?apikey={}&base_currency={}",
+        "{api_url}/?apikey={}&base_currency={}",
         match options.get("currency_api_key") {
             Some(currency_api_key) => currency_api_key,
             None => "",
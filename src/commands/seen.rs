@@ -0,0 +1,353 @@
+use crate::activity;
+use crate::clock::Clock;
+use crate::commands::log;
+use crate::database::{CsvRecord, Database};
+use crate::queue::{MessageQueue, Priority};
+use crate::transport::{Incoming, IncomingKind};
+use crate::utils::format_ago;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, PoisonError};
+use tokio::sync::Mutex;
+
+// Messages that may wait for a nick. Further tells are refused until they are delivered.
+const MAX_TELLS: usize = 10;
+
+// The last thing a nick was seen doing. The text is the message, the reason of a part or quit or
+// the new nick of a nick change.
+#[derive(PartialEq)]
+struct Sighting {
+    nick: String,
+    datetime: DateTime<Utc>,
+    kind: String,
+    channel: String,
+    text: String,
+    network: String,
+}
+
+impl CsvRecord for Sighting {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            nick: fields[0].clone(),
+            datetime: fields[1].parse().unwrap_or_default(),
+            kind: fields[2].clone(),
+            channel: fields[3].clone(),
+            text: fields[4].clone(),
+            network: fields[5].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.nick.clone(),
+            self.datetime.to_string(),
+            self.kind.clone(),
+            self.channel.clone(),
+            self.text.clone(),
+            self.network.clone(),
+        ]
+    }
+}
+
+// A message left with !tell, delivered the next time its recipient speaks or joins.
+#[derive(PartialEq)]
+struct Tell {
+    from: String,
+    to: String,
+    text: String,
+    datetime: DateTime<Utc>,
+    network: String,
+}
+
+impl CsvRecord for Tell {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            from: fields[0].clone(),
+            to: fields[1].clone(),
+            text: fields[2].clone(),
+            datetime: fields[3].parse().unwrap_or_default(),
+            network: fields[4].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.from.clone(),
+            self.to.clone(),
+            self.text.clone(),
+            self.datetime.to_string(),
+            self.network.clone(),
+        ]
+    }
+}
+
+// The nicks with messages waiting, so that only their messages and joins read the tells. Shared by
+// every network like the database, and loaded from it on startup.
+#[derive(Clone, Default)]
+pub struct PendingTells {
+    nicks: Arc<std::sync::Mutex<HashSet<(String, String)>>>,
+}
+
+impl PendingTells {
+    pub async fn load(db: &Arc<Mutex<Database>>) -> Self {
+        let nicks = match db.lock().await.select("tells", |_: &Tell| true) {
+            Ok(tells) => tells
+                .unwrap_or_default()
+                .into_iter()
+                .map(|t| (t.network, t.to.to_ascii_lowercase()))
+                .collect(),
+            Err(error) => {
+                eprintln!("{error}");
+
+                HashSet::new()
+            }
+        };
+
+        Self {
+            nicks: Arc::new(std::sync::Mutex::new(nicks)),
+        }
+    }
+
+    fn add(&self, nick: &str, network: &str) {
+        self.nicks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((String::from(network), nick.to_ascii_lowercase()));
+    }
+
+    // Whether nick has messages waiting, which the caller then has to deliver.
+    fn take(&self, nick: &str, network: &str) -> bool {
+        self.nicks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&(String::from(network), nick.to_ascii_lowercase()))
+    }
+}
+
+// Records what the nick of a message or event was last seen doing and delivers the messages left
+// for it when it speaks or joins a channel. Private messages are left out, and so are the messages
+// and reasons of nicks that opted out of logging. Sightings are appended, compact drops the older
+// ones.
+pub async fn record(
+    incoming: &Incoming,
+    network: &str,
+    clock: &Clock,
+    db: &Arc<Mutex<Database>>,
+    queue: &MessageQueue,
+    pending: &PendingTells,
+) {
+    let nick = match &incoming.nick {
+        Some(nick) => nick,
+        None => return,
+    };
+
+    // Quits and nick changes have no channel.
+    if !incoming.target.is_empty() && !activity::is_channel(&incoming.target) {
+        return;
+    }
+
+    let kind = match incoming.kind {
+        IncomingKind::Message => "message",
+        IncomingKind::Join => "join",
+        IncomingKind::Part => "part",
+        IncomingKind::Quit => "quit",
+        IncomingKind::Nick => "nick",
+    };
+    let db = db.lock().await;
+    let text = match incoming.kind {
+        _ if incoming.text.is_empty() => String::new(),
+        IncomingKind::Nick => incoming.text.clone(),
+        _ => match log::opted_out(nick, network, &db) {
+            Ok(false) => incoming.text.clone(),
+            Ok(true) => String::new(),
+            Err(error) => {
+                eprintln!("{error}");
+
+                String::new()
+            }
+        },
+    };
+
+    if let Err(error) = db.append(
+        "seen",
+        Sighting {
+            nick: nick.clone(),
+            datetime: clock.now(),
+            kind: String::from(kind),
+            channel: incoming.target.clone(),
+            text,
+            network: String::from(network),
+        },
+    ) {
+        eprintln!("{error}");
+    }
+
+    if !matches!(incoming.kind, IncomingKind::Message | IncomingKind::Join)
+        || !pending.take(nick, network)
+    {
+        return;
+    }
+
+    let tells = match db.select("tells", |t: &Tell| {
+        t.to.eq_ignore_ascii_case(nick) && t.network == network
+    }) {
+        Ok(tells) => tells.unwrap_or_default(),
+        Err(error) => {
+            eprintln!("{error}");
+            pending.add(nick, network);
+
+            return;
+        }
+    };
+
+    if tells.is_empty() {
+        return;
+    }
+
+    // Deleted before sending, so that a message is never delivered twice.
+    if let Err(error) = db.delete("tells", |t: &&Tell| {
+        t.to.eq_ignore_ascii_case(nick) && t.network == network
+    }) {
+        eprintln!("{error}");
+        pending.add(nick, network);
+
+        return;
+    }
+
+    for tell in tells {
+        if let Err(error) = queue.send(
+            &incoming.target,
+            &format!(
+                "{nick}: {} left you a message {}: {}",
+                tell.from,
+                format_ago(tell.datetime, clock.now()),
+                tell.text
+            ),
+            Priority::Normal,
+        ) {
+            eprintln!("{error}");
+        }
+    }
+}
+
+// Keeps only the last sighting of every nick, since record only ever appends them.
+pub async fn compact(db: &Arc<Mutex<Database>>) {
+    let db = db.lock().await;
+    let sightings: Vec<Sighting> = match db.select("seen", |_: &Sighting| true) {
+        Ok(sightings) => sightings.unwrap_or_default(),
+        Err(error) => {
+            eprintln!("{error}");
+
+            return;
+        }
+    };
+    let last: HashMap<(&str, String), usize> = sightings
+        .iter()
+        .enumerate()
+        .map(|(i, s)| ((s.network.as_str(), s.nick.to_ascii_lowercase()), i))
+        .collect();
+
+    if last.len() == sightings.len() {
+        return;
+    }
+
+    let kept: Vec<&Sighting> = sightings
+        .iter()
+        .enumerate()
+        .filter(|(i, s)| last.get(&(s.network.as_str(), s.nick.to_ascii_lowercase())) == Some(i))
+        .map(|(_, s)| s)
+        .collect();
+
+    if let Err(error) = db.write("seen", &kept) {
+        eprintln!("{error}");
+    }
+}
+
+pub async fn seen(
+    args: &[String],
+    target: &str,
+    network: &str,
+    clock: &Clock,
+    db: Arc<Mutex<Database>>,
+) -> String {
+    let nick = match args.first() {
+        Some(nick) => nick,
+        None => return String::from("Please provide a nick."),
+    };
+    let sighting = match db.lock().await.select("seen", |s: &Sighting| {
+        s.nick.eq_ignore_ascii_case(nick) && s.network == network
+    }) {
+        Ok(sightings) => match sightings.and_then(|s| s.into_iter().last()) {
+            Some(sighting) => sighting,
+            None => return format!("I haven't seen {nick}."),
+        },
+        Err(_) => return String::from("Could not read when nicks were last seen."),
+    };
+    // What was said is only repeated on the channel it was said on.
+    let doing = match sighting.kind.as_str() {
+        "message" if sighting.channel.eq_ignore_ascii_case(target) && !sighting.text.is_empty() => {
+            format!("in {}, saying: {}", sighting.channel, sighting.text)
+        }
+        "message" => format!("in {}", sighting.channel),
+        "join" => format!("joining {}", sighting.channel),
+        "part" => format!("leaving {}", sighting.channel),
+        "quit" if sighting.text.is_empty() => String::from("quitting"),
+        "quit" => format!("quitting ({})", sighting.text),
+        _ => format!("changing nick to {}", sighting.text),
+    };
+
+    format!(
+        "{} was last seen {} {doing}",
+        sighting.nick,
+        format_ago(sighting.datetime, clock.now())
+    )
+}
+
+pub async fn tell(
+    args: &[String],
+    nick: &str,
+    network: &str,
+    clock: &Clock,
+    db: Arc<Mutex<Database>>,
+    pending: &PendingTells,
+) -> String {
+    if args.len() < 2 {
+        return String::from("Please provide a nick and a message.");
+    }
+
+    let to = args[0].trim_end_matches([':', ',']);
+
+    if to.eq_ignore_ascii_case(nick) {
+        return String::from("You can tell yourself that.");
+    }
+
+    let db = db.lock().await;
+    let waiting = match db.select("tells", |t: &Tell| {
+        t.to.eq_ignore_ascii_case(to) && t.network == network
+    }) {
+        Ok(tells) => tells.unwrap_or_default().len(),
+        Err(_) => return String::from("Could not read the messages."),
+    };
+
+    if waiting >= MAX_TELLS {
+        return format!("{to} already has {MAX_TELLS} messages waiting.");
+    }
+
+    match db.insert(
+        "tells",
+        Tell {
+            from: String::from(nick),
+            to: String::from(to),
+            text: args[1..].join(" "),
+            datetime: clock.now(),
+            network: String::from(network),
+        },
+    ) {
+        Ok(()) => {
+            pending.add(to, network);
+
+            format!("I'll tell {to} the next time they speak or join.")
+        }
+        Err(_) => String::from("Could not store the message."),
+    }
+}
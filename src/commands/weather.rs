@@ -1,35 +1,115 @@
-use crate::database::{CsvRecord, Database};
+use crate::database::{CsvRecord, Database, Identified};
+use crate::network;
 use crate::utils;
 use chrono::Utc;
 use chrono_tz::Tz;
 use futures::join;
-use openweather_sdk::{Language, OpenWeather, Units};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use url::form_urlencoded;
+use utoipa::ToSchema;
 
-#[derive(PartialEq)]
-struct WeatherSetting {
-    nick: String,
-    location: String,
+const OWM_API_URL: &str = "https://api.openweathermap.org";
+
+// The parts of the OpenWeatherMap geocoding, One Call and forecast responses that are shown.
+#[derive(Deserialize)]
+struct Location {
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Deserialize)]
+struct Condition {
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct Current {
+    temp: f64,
+    humidity: u64,
+    pressure: u64,
+    wind_speed: f64,
+    wind_deg: u64,
+    wind_gust: Option<f64>,
+    weather: Vec<Condition>,
+}
+
+#[derive(Deserialize)]
+struct OneCall {
+    current: Option<Current>,
+}
+
+#[derive(Deserialize)]
+struct ForecastMain {
+    temp: f64,
+}
+
+#[derive(Deserialize)]
+struct ForecastItem {
+    dt_txt: String,
+    main: ForecastMain,
+    weather: Vec<Condition>,
+}
+
+#[derive(Deserialize)]
+struct Forecast {
+    list: Vec<ForecastItem>,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WeatherSetting {
+    // Stored in the last column.
+    #[serde(default)]
+    pub id: u64,
+    pub nick: String,
+    pub location: String,
+    #[serde(default)]
+    pub network: String,
 }
 
 impl CsvRecord for WeatherSetting {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(3)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             nick: fields[0].clone(),
             location: fields[1].clone(),
+            network: fields
+                .get(2)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
     fn to_fields(&self) -> Vec<String> {
-        vec![self.nick.clone(), self.location.clone()]
+        vec![
+            self.nick.clone(),
+            self.location.clone(),
+            self.network.clone(),
+            self.id.to_string(),
+        ]
+    }
+}
+
+impl Identified for WeatherSetting {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
     }
 }
 
 struct TimeZone {
     nick: String,
     name: String,
+    network: String,
 }
 
 impl CsvRecord for TimeZone {
@@ -37,34 +117,40 @@ impl CsvRecord for TimeZone {
         Self {
             nick: fields[0].clone(),
             name: fields[1].clone(),
+            network: fields
+                .get(2)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
     fn to_fields(&self) -> Vec<String> {
-        vec![self.nick.clone(), self.name.clone()]
+        vec![self.nick.clone(), self.name.clone(), self.network.clone()]
     }
 }
 
 pub async fn weather(
     args: &[String],
     nick: &str,
+    network: &str,
     options: &HashMap<String, String>,
     db: Arc<Mutex<Database>>,
 ) -> String {
     let location = match args.len() {
         ..=0 => {
-            let weather_settings: Vec<WeatherSetting> = match db
-                .lock()
-                .await
-                .select("weather_settings", |ws: &WeatherSetting| {
-                    ws.nick.to_lowercase() == nick.to_lowercase()
-                }) {
-                Ok(weather_settings_result) => match weather_settings_result {
-                    Some(weather_settings) => weather_settings,
-                    None => return String::from("Please provide a location."),
-                },
-                Err(_) => return String::from("Please provide a location."),
-            };
+            let weather_settings: Vec<WeatherSetting> =
+                match db
+                    .lock()
+                    .await
+                    .select("weather_settings", |ws: &WeatherSetting| {
+                        ws.nick.to_lowercase() == nick.to_lowercase() && ws.network == network
+                    }) {
+                    Ok(weather_settings_result) => match weather_settings_result {
+                        Some(weather_settings) => weather_settings,
+                        None => return String::from("Please provide a location."),
+                    },
+                    Err(_) => return String::from("Please provide a location."),
+                };
 
             if !weather_settings.is_empty() {
                 weather_settings[0].location.clone()
@@ -74,15 +160,17 @@ pub async fn weather(
         }
         _ => {
             let entity = WeatherSetting {
+                id: 0,
                 nick: String::from(nick),
                 location: args.join(" "),
+                network: String::from(network),
             };
 
             if db
                 .lock()
                 .await
-                .update("weather_settings", entity, |ws: &&WeatherSetting| {
-                    ws.nick.to_lowercase() == nick.to_lowercase()
+                .update_keeping_id("weather_settings", entity, |ws: &&WeatherSetting| {
+                    ws.nick.to_lowercase() == nick.to_lowercase() && ws.network == network
                 })
                 .is_err()
             {
@@ -93,26 +181,28 @@ pub async fn weather(
         }
     };
 
-    let openweather = OpenWeather::new(
-        match options.get("owm_api_key") {
-            Some(key) => key.to_owned(),
-            None => return String::from("Could not find OWM API key."),
-        },
-        match options.get("owm_api_units") {
-            Some(units) => match units.to_lowercase().as_str() {
-                "f" | "fahrenheit" | "imperial" => Units::Imperial,
-                "c" | "celsius" | "metric" => Units::Metric,
-                _ => Units::Standard,
-            },
-            None => Units::Standard,
+    let api_url = match options.get("owm_api_url") {
+        Some(api_url) => api_url.as_str(),
+        None => OWM_API_URL,
+    };
+    let api_key = match options.get("owm_api_key") {
+        Some(key) => key,
+        None => return String::from("Could not find OWM API key."),
+    };
+    let units = match options.get("owm_api_units") {
+        Some(units) => match units.to_lowercase().as_str() {
+            "f" | "fahrenheit" | "imperial" => "imperial",
+            "c" | "celsius" | "metric" => "metric",
+            _ => "standard",
         },
-        Language::English,
-    );
+        None => "standard",
+    };
 
-    let geo = match openweather
-        .geocoding
-        .get_geocoding(&location, None, None, 1)
-        .await
+    let geo = match get::<Vec<Location>>(format!(
+        "{api_url}/geo/1.0/direct?q={}&limit=1&appid={api_key}",
+        form_urlencoded::byte_serialize(location.as_bytes()).collect::<String>()
+    ))
+    .await
     {
         Ok(geo) => {
             if !geo.is_empty() {
@@ -129,23 +219,30 @@ pub async fn weather(
     };
 
     let time_zones: Vec<TimeZone> = match db.lock().await.select("time_zones", |tz: &TimeZone| {
-        tz.nick.to_lowercase() == nick.to_lowercase()
+        tz.nick.to_lowercase() == nick.to_lowercase() && tz.network == network
     }) {
         Ok(timezones_result) => match timezones_result {
             Some(time_zones) => time_zones,
             None => vec![TimeZone {
                 nick: nick.to_owned(),
                 name: String::from("Europe/Berlin"),
+                network: String::from(network),
             }],
         },
         Err(_) => vec![TimeZone {
             nick: nick.to_owned(),
             name: String::from("Europe/Berlin"),
+            network: String::from(network),
         }],
     };
 
     let current_task = async {
-        match openweather.one_call.call(geo[0].lat, geo[0].lon).await {
+        match get::<OneCall>(format!(
+            "{api_url}/data/3.0/onecall?lat={}&lon={}&units={units}&lang=en&appid={api_key}",
+            geo[0].lat, geo[0].lon
+        ))
+        .await
+        {
             Ok(weather) => match weather.current {
                 Some(current) => format!(
                     "{}: {} {:.1}C | Humidity: {}% | Pressure: {}hPa | Wind: {:.1}m/s @ {} {:.1}m/s\r\n",
@@ -169,7 +266,12 @@ pub async fn weather(
     };
 
     let forecast_task = async {
-        match openweather.forecast.call(geo[0].lat, geo[0].lon, 6).await {
+        match get::<Forecast>(format!(
+            "{api_url}/data/2.5/forecast?lat={}&lon={}&cnt=6&units={units}&lang=en&appid={api_key}",
+            geo[0].lat, geo[0].lon
+        ))
+        .await
+        {
             Ok(forecast) => forecast
                 .list
                 .iter()
@@ -215,3 +317,7 @@ pub async fn weather(
 
     format!("{}\r\n{}", current, forecast)
 }
+
+async fn get<T: DeserializeOwned>(url: String) -> Result<T, reqwest::Error> {
+    reqwest::get(url).await?.error_for_status()?.json().await
+}
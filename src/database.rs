@@ -1,7 +1,7 @@
 use std::cmp::PartialEq;
 use std::error::Error;
 use std::fmt;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::ErrorKind;
 
 #[derive(Debug)]
@@ -20,6 +20,12 @@ pub trait CsvRecord {
     fn to_fields(&self) -> Vec<String>;
 }
 
+// A record with a stable id, 0 until the database assigns one.
+pub trait Identified {
+    fn id(&self) -> u64;
+    fn set_id(&mut self, id: u64);
+}
+
 pub struct Database {
     path: String,
     extension: String,
@@ -50,6 +56,7 @@ impl Database {
         let mut entities: Vec<T> = Vec::new();
         let mut rdr = match csv::ReaderBuilder::new()
             .has_headers(false)
+            .flexible(true)
             .from_path(format!("{}{}.{}", self.path, from, self.extension))
         {
             Ok(rdr) => rdr,
@@ -99,6 +106,23 @@ impl Database {
         Ok(())
     }
 
+    // Adds the entity at the end of the table without reading or rewriting it, for large tables
+    // that only grow between prunes, such as the logs.
+    pub fn append<T: CsvRecord>(&self, into: &str, entity: T) -> Result<(), Box<dyn Error>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(format!("{}{}.{}", self.path, into, self.extension))?;
+        let mut wtr = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(file);
+
+        wtr.write_record(entity.to_fields())?;
+        wtr.flush()?;
+
+        Ok(())
+    }
+
     pub fn update<T, P>(&self, from: &str, entity: T, where_filter: P) -> Result<(), Box<dyn Error>>
     where
         T: CsvRecord + PartialEq,
@@ -121,6 +145,73 @@ impl Database {
         Ok(())
     }
 
+    // The id following the highest id of the table.
+    pub fn next_id<T>(&self, from: &str) -> Result<u64, Box<dyn Error>>
+    where
+        T: CsvRecord + Identified,
+    {
+        let entities: Vec<T> = self.select(from, |_| true)?.unwrap_or_default();
+
+        Ok(entities.iter().map(|e| e.id()).max().unwrap_or_default() + 1)
+    }
+
+    // Inserts the entity with the next id of the table and returns that id.
+    pub fn insert_new<T>(&self, into: &str, mut entity: T) -> Result<u64, Box<dyn Error>>
+    where
+        T: CsvRecord + Identified,
+    {
+        let id = self.next_id::<T>(into)?;
+
+        entity.set_id(id);
+        self.insert(into, entity)?;
+
+        Ok(id)
+    }
+
+    // Like update, but the entity keeps the id of the first record it replaces, or gets the next
+    // id of the table when it replaces none. Returns the id.
+    pub fn update_keeping_id<T, P>(
+        &self,
+        from: &str,
+        mut entity: T,
+        mut where_filter: P,
+    ) -> Result<u64, Box<dyn Error>>
+    where
+        T: CsvRecord + Identified + PartialEq,
+        P: FnMut(&&T) -> bool,
+    {
+        let entities: Vec<T> = self.select(from, |_| true)?.unwrap_or_default();
+        let id = match entities.iter().find(|e| where_filter(e)) {
+            Some(existing) if existing.id() != 0 => existing.id(),
+            _ => entities.iter().map(|e| e.id()).max().unwrap_or_default() + 1,
+        };
+
+        entity.set_id(id);
+        self.update(from, entity, where_filter)?;
+
+        Ok(id)
+    }
+
+    // Gives an id to every record of the table that has none, such as those written before the
+    // table had ids.
+    pub fn assign_ids<T>(&self, table: &str) -> Result<(), Box<dyn Error>>
+    where
+        T: CsvRecord + Identified,
+    {
+        let mut entities: Vec<T> = self.select(table, |_| true)?.unwrap_or_default();
+        let next = entities.iter().map(|e| e.id()).max().unwrap_or_default() + 1;
+
+        if entities.iter().all(|e| e.id() != 0) {
+            return Ok(());
+        }
+
+        for (id, entity) in (next..).zip(entities.iter_mut().filter(|e| e.id() == 0)) {
+            entity.set_id(id);
+        }
+
+        self.write(table, &entities.iter().collect())
+    }
+
     #[allow(dead_code)]
     pub fn delete<T, P>(&self, from: &str, where_filter: P) -> Result<(), Box<dyn Error>>
     where
@@ -0,0 +1,158 @@
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+
+pub const DEFAULT_MAX_SIZE: u64 = 1048576;
+
+fn default_signature_header() -> String {
+    String::from("X-Hub-Signature-256")
+}
+
+// An inbound webhook from a [[hooks]] table of config.toml, served at /api/hooks/<name>.
+#[derive(Clone, Deserialize)]
+pub struct Hook {
+    pub name: String,
+    // Key of the HMAC-SHA256 of the body the sender puts in signature_header.
+    pub secret: String,
+    #[serde(default = "default_signature_header")]
+    pub signature_header: String,
+    // The first network when missing.
+    #[serde(default)]
+    pub network: Option<String>,
+    pub channels: Vec<String>,
+    pub template: String,
+}
+
+impl Hook {
+    // Checks the hex signature of the body, with or without a sha256= prefix.
+    pub fn verify(&self, signature: Option<&str>, body: &[u8]) -> bool {
+        let signature = match signature {
+            Some(signature) => signature.trim(),
+            None => return false,
+        };
+        let signature = match hex::decode(signature.strip_prefix("sha256=").unwrap_or(signature)) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        let mut mac = match Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()) {
+            Ok(mac) => mac,
+            Err(_) => return false,
+        };
+
+        mac.update(body);
+        mac.verify_slice(&signature).is_ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct HooksFile {
+    #[serde(default)]
+    hooks: Vec<Hook>,
+}
+
+// Loads the [[hooks]] tables of the config file, if any.
+pub fn load(path: &str) -> Result<Vec<Hook>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Err(format!("Could not read configuration file ({path}).")),
+    };
+    let hooks = match toml::from_str::<HooksFile>(&contents) {
+        Ok(hooks_file) => hooks_file.hooks,
+        Err(error) => {
+            return Err(format!(
+                "Invalid configuration file ({path}). Cause: {error}."
+            ))
+        }
+    };
+
+    for (index, hook) in hooks.iter().enumerate() {
+        let cause = if hook.name.is_empty() || hook.name.contains('/') {
+            format!("invalid hook name {}", hook.name)
+        } else if hooks[..index].iter().any(|h| h.name == hook.name) {
+            format!("duplicate hook {}", hook.name)
+        } else if hook.secret.is_empty() {
+            format!("hook {} has no secret", hook.name)
+        } else {
+            continue;
+        };
+
+        return Err(format!(
+            "Invalid configuration file ({path}). Cause: {cause}."
+        ));
+    }
+
+    Ok(hooks)
+}
+
+// Fills in every {path} of the template with the value found at that path of the payload and
+// returns each non-empty line as a message. Paths are dot separated keys or array indexes, where
+// * joins the rest of the path for every item of an array and # counts them. Use {{ and }} for
+// literal braces.
+pub fn render(template: &str, payload: &Value) -> Vec<String> {
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let path: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let path: Vec<&str> = path.trim().split('.').collect();
+
+                output.push_str(&resolve(payload, &path));
+            }
+            c => output.push(c),
+        }
+    }
+
+    output
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn resolve(value: &Value, path: &[&str]) -> String {
+    match path.split_first() {
+        None => match value {
+            Value::Null => String::new(),
+            // Values can't break a message in two.
+            Value::String(text) => text.replace(['\r', '\n'], " "),
+            value => value.to_string(),
+        },
+        Some((&"*", rest)) => match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| resolve(item, rest))
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<String>>()
+                .join(", "),
+            _ => String::new(),
+        },
+        Some((&"#", [])) => match value {
+            Value::Array(items) => items.len().to_string(),
+            Value::Object(fields) => fields.len().to_string(),
+            _ => String::new(),
+        },
+        Some((key, rest)) => {
+            let next = match key.parse::<usize>() {
+                Ok(index) => value.get(index),
+                Err(_) => value.get(*key),
+            };
+
+            match next {
+                Some(next) => resolve(next, rest),
+                None => String::new(),
+            }
+        }
+    }
+}
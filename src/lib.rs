@@ -0,0 +1,18 @@
+#[macro_use]
+extern crate rocket;
+
+pub mod activity;
+pub mod api;
+pub mod bot;
+pub mod clock;
+pub mod commands;
+pub mod database;
+pub mod hooks;
+pub mod network;
+pub mod paste;
+pub mod queue;
+pub mod status;
+pub mod tasks;
+pub mod transport;
+pub mod utils;
+pub mod webhook;
@@ -1,23 +1,22 @@
-mod api;
-mod commands;
-mod database;
-mod tasks;
-mod utils;
-
-use commands::BotCommand;
-use database::Database;
 use futures::prelude::*;
-use irc::client::prelude::*;
+use gluon_bot::activity::Activity;
+use gluon_bot::api::{self, keys};
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::seen::PendingTells;
+use gluon_bot::database::Database;
+use gluon_bot::network::{self, NetworkHandle};
+use gluon_bot::queue::MessageQueue;
+use gluon_bot::status::BotStatus;
+use gluon_bot::{bot, hooks, transport};
 use rocket::form::validate::Len;
 use rocket::fs::FileServer;
 use rocket::fs::NamedFile;
+use std::env;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::task;
-use tokio::time;
 use tokio_util::sync::CancellationToken;
 
 #[macro_use]
@@ -30,289 +29,310 @@ async fn all(_path: PathBuf) -> Option<NamedFile> {
 
 #[tokio::main]
 async fn main() {
-    loop {
-        // Configure an IRC cient with settings from a config file.
-        let config = match Config::load("config.toml") {
-            Ok(config) => config,
-            Err(error) => match error {
-                irc::error::Error::Io(_) => {
-                    eprintln!("Could not read configuration file (config.toml).");
+    // Load the settings of every IRC network from a config file.
+    let networks = match network::load("config.toml") {
+        Ok(networks) => networks,
+        Err(error) => {
+            eprintln!("{error}");
 
-                    return;
-                }
-                _ => {
-                    eprintln!("Unknown error parsing configuration file.");
+            return;
+        }
+    };
+    // Inbound webhooks served by the API.
+    let hooks = match hooks::load("config.toml") {
+        Ok(hooks) => hooks,
+        Err(error) => {
+            eprintln!("{error}");
+
+            return;
+        }
+    };
+    // Options shared by all networks are taken from the first one.
+    let options = Arc::new(networks[0].config.options.clone());
+    let db = Arc::new(Mutex::new(Database::new(
+        match options.get("database_path") {
+            Some(path) => path,
+            None => "data/",
+        },
+        None,
+    )));
+    let args: Vec<String> = env::args().collect();
+
+    // Manage the API keys without connecting to any network.
+    if let Some(name) = arg(&args, "--create-key") {
+        let list = |name| {
+            arg(&args, name)
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<String>>()
+        };
+        let expires = match arg(&args, "--expires") {
+            Some(expires) => match keys::parse_expiry(expires) {
+                Some(expires) => Some(expires),
+                None => {
+                    eprintln!("Could not parse expiry {expires}. Example: 2026-12-31");
 
                     return;
                 }
             },
+            None => None,
         };
-        let client = Arc::new(Mutex::new(
-            match Client::from_config(config.clone()).await {
-                Ok(client) => client,
-                Err(error) => match error {
-                    irc::error::Error::InvalidConfig { path, cause } => {
-                        eprintln!("Invalid configuration file ({path}). Cause: {cause}.");
 
-                        return;
-                    }
-                    _ => {
-                        eprintln!("Unknown error parsing configuration file.");
+        match keys::create(name, &list("--scopes"), &list("--channels"), expires, &db).await {
+            Ok(key) => println!("{key}"),
+            Err(error) => eprintln!("{error}"),
+        }
 
-                        return;
-                    }
-                },
-            },
-        ));
-        let options = Arc::new(config.options);
-        let prefix = match options.get("prefix") {
-            Some(prefix) => prefix,
-            None => "!",
-        };
-        let db = Arc::new(Mutex::new(Database::new(
-            match options.get("database_path") {
-                Some(path) => path,
-                None => "data/",
-            },
-            None,
-        )));
+        return;
+    }
 
-        // Connect the IRC client to the IRC server.
-        let mut stream = match client.lock().await.stream() {
-            Ok(stream) => stream,
-            Err(error) => {
-                eprintln!("{error}");
+    if let Some(name) = arg(&args, "--revoke-key") {
+        match keys::revoke(name, &db).await {
+            Ok(()) => println!("Key {name} revoked."),
+            Err(error) => eprintln!("{error}"),
+        }
 
-                return;
-            }
-        };
+        return;
+    }
 
-        if let Err(error) = client.lock().await.identify() {
-            eprintln!("{error}");
+    if args.iter().any(|a| a == "--list-keys") {
+        for key in keys::list(&db).await {
+            println!(
+                "{} | scopes: {} | channels: {} | expires: {} | created: {}",
+                key.name,
+                key.scopes.join(" "),
+                match key.channels.is_empty() {
+                    true => String::from("any"),
+                    false => key.channels.join(" "),
+                },
+                match key.expires {
+                    Some(expires) => expires.to_string(),
+                    None => String::from("never"),
+                },
+                key.created
+            );
         }
 
-        println!("Connected to the IRC server.");
+        return;
+    }
 
-        // Spawn the API task.
-        // TODO: Find a way to cancel this task during disconnects.
-        let client_clone = Arc::clone(&client);
-        let db_clone = Arc::clone(&db);
-        let api_token = CancellationToken::new();
-        task::spawn(async move {
-            let my_state = api::BotState {
-                client: client_clone,
-                db: db_clone,
-            };
+    // Run commands typed on the console instead of connecting to any network.
+    if args.iter().any(|a| a == "--console") {
+        let name = match arg(&args, "--network") {
+            Some(name) => String::from(name),
+            None => networks[0].name.clone(),
+        };
+        let network = match networks.into_iter().find(|n| n.name == name) {
+            Some(network) => network,
+            None => {
+                eprintln!("Could not find network {name} in config.toml.");
 
-            if rocket::build()
-                .mount(
-                    "/api",
-                    routes![
-                        api::add_event,
-                        api::add_quote,
-                        api::delete_event,
-                        api::delete_quote,
-                        api::events,
-                        api::f1_bets,
-                        api::quotes,
-                        api::say,
-                        api::score_f1_bets,
-                        api::update_event,
-                        api::update_quote,
-                    ],
-                )
-                .mount("/", FileServer::from("static/").rank(1))
-                .mount("/", routes![all])
-                .manage(my_state)
-                .launch()
-                .await
-                .is_err()
-            {
-                api_token.cancel();
-                eprintln!("Problem with the API task.");
+                return;
             }
-        });
-
-        // Spawn the next task.
-        let client_clone = Arc::clone(&client);
-        let db_clone = Arc::clone(&db);
-        let next_token = CancellationToken::new();
-        let next_token_clone = next_token.clone();
-        let next_task = task::spawn(async move {
-            tasks::next::next(client_clone, db_clone, next_token_clone).await;
-        });
-
-        // Spawn the external_message task.
-        let client_clone = Arc::clone(&client);
-        let external_message_token = CancellationToken::new();
-        let external_message_token_clone = external_message_token.clone();
-        let external_message_task = task::spawn(async move {
-            tasks::base::external_message(client_clone, external_message_token_clone).await;
-        });
-
-        // Spawn the feeds task.
-        let options_clone = Arc::clone(&options);
-        let client_clone = Arc::clone(&client);
-        let db_clone = Arc::clone(&db);
-        let feeds_token = CancellationToken::new();
-        let feeds_token_clone = feeds_token.clone();
-        let feeds_task = task::spawn(async move {
-            tasks::feeds::feeds(options_clone, client_clone, db_clone, feeds_token_clone).await
-        });
-
-        let client_clone = Arc::clone(&client);
-        let db_clone = Arc::clone(&db);
-        let train_game_token = CancellationToken::new();
-        let train_game_token_clone = feeds_token.clone();
-        let train_game_task = task::spawn(async move {
-            let train_game =
-                tasks::train_game::TrainGame::new(client_clone, db_clone, train_game_token_clone)
-                    .await;
-
-            train_game.run().await;
-        });
-
-        // Main loop that continously gets IRC messages from an asynchronous stream.
-        // Match any PRIVMSG received from the asynchronous stream of messages.
-        // If the message is a bot command, spawn a Tokio task to handle the command.
-        while let Ok(Some(message)) = stream.next().await.transpose() {
-            let sender = client.lock().await.sender();
-            let nick = match message.prefix {
-                Some(Prefix::Nickname(nick, _, _)) => Some(nick),
-                Some(Prefix::ServerName(_)) => None,
-                None => None,
-            };
-
-            if let Command::PRIVMSG(target, message) = message.command {
-                if message.len() > 1 && message.starts_with(prefix) {
-                    let options = Arc::clone(&options);
-                    let db = Arc::clone(&db);
-                    let client = Arc::clone(&client);
-                    task::spawn(async move {
-                        if let Ok(bot_command) = BotCommand::new(&message, nick, &target, &options)
-                        {
-                            let output = match time::timeout(
-                                Duration::from_secs(bot_command.timeout),
-                                bot_command.handle(db, client),
-                            )
-                            .await
-                            {
-                                Ok(output) => output,
-                                Err(_) => String::from("Timeout while running command."),
-                            };
-
-                            let max_chunk_size = options
-                                .get("max_chunk_size")
-                                .unwrap_or(&String::from("410"))
-                                .parse()
-                                .unwrap_or(410);
-
-                            if output.len() <= max_chunk_size {
-                                if let Err(error) = sender.send_privmsg(&target, output) {
-                                    eprintln!("{error}");
-                                }
-                            } else {
-                                let output_chunks = utils::split_message(&output, max_chunk_size);
-
-                                for chunk in output_chunks {
-                                    if let Err(error) = sender.send_privmsg(&target, chunk) {
-                                        eprintln!("{error}");
-                                    }
+        };
+        // Pin the clock with --now to replay commands at a given time.
+        let clock = Clock::from_options(&network.config.options);
+        let clock = match arg(&args, "--now") {
+            Some(now) => match now.parse() {
+                Ok(now) => clock.pin(now),
+                Err(_) => {
+                    eprintln!("Could not parse time {now}. Example: 2023-04-30 11:00:00 UTC");
 
-                                    time::sleep(Duration::from_secs(2)).await;
-                                }
-                            }
-                        }
-                    });
-                } else {
-                    let options = Arc::clone(&options);
-                    task::spawn(async move {
-                        if let Some(url) = utils::find_url(&message) {
-                            if url.to_lowercase().contains("youtube.com")
-                                || url.to_lowercase().contains("youtu.be")
-                            {
-                                if let Some(video_id) = utils::extract_video_id(url) {
-                                    if let Ok(Some(video_data)) = utils::youtube_data(
-                                        options.get("youtube_api_key").unwrap_or(&String::from("")),
-                                        &video_id,
-                                    )
-                                    .await
-                                    {
-                                        if let Err(error) = sender.send_privmsg(&target, video_data)
-                                        {
-                                            eprint!("{error}");
-                                        }
-                                    }
-                                }
-                            } else if let Ok(Some(title)) = utils::find_title(url).await {
-                                if let Err(error) = sender.send_privmsg(&target, title) {
-                                    eprint!("{error}");
-                                }
-                            }
-                        }
-                    });
+                    return;
                 }
-            }
-        }
+            },
+            None => clock,
+        };
 
-        eprintln!("Diconnected from the IRC server.");
+        bot::console(
+            network,
+            db,
+            clock,
+            arg(&args, "--nick").unwrap_or("console"),
+            arg(&args, "--channel").unwrap_or("#console"),
+        )
+        .await;
 
-        // Cancel the next task.
-        // If the task doesn't finish, terminate the bot.
-        next_token.cancel();
+        return;
+    }
 
-        if next_task.await.is_err() {
-            eprintln!("Could not cancel next task.");
-            eprintln!("Terminating bot...");
+    // Keys of the old plaintext file are moved to the key store.
+    keys::import_legacy("api_keys.txt", &db).await;
 
-            return;
-        }
+    // Records written before the tables had ids get one.
+    if let Err(error) = api::assign_ids(&*db.lock().await) {
+        eprintln!("Could not assign ids: {error}");
 
-        eprintln!("Next task finished.");
+        return;
+    }
 
-        // Cancel the external_message task.
-        // If the task doesn't finish, terminate the bot.
-        external_message_token.cancel();
+    let mut handles: Vec<NetworkHandle> = Vec::new();
+    let mut runs = Vec::new();
+    let pending_tells = PendingTells::load(&db).await;
 
-        if external_message_task.await.is_err() {
-            eprintln!("Could not cancel external_message task.");
-            eprintln!("Terminating bot...");
+    for network in networks {
+        // Connect to each network with its own transport.
+        let transport = match transport::connect(&network).await {
+            Ok(transport) => transport,
+            Err(error) => {
+                eprintln!("{error}");
 
-            return;
-        }
+                return;
+            }
+        };
+        let (queue, queue_receiver) = MessageQueue::new();
+        let handle = NetworkHandle {
+            name: network.name.clone(),
+            data: network.data.clone(),
+            transport: Arc::new(Mutex::new(Some(transport))),
+            queue,
+            status: Arc::new(Mutex::new(BotStatus::new(&network.name))),
+            activity: Activity::new(&network.name),
+        };
 
-        eprintln!("External Message task finished.");
+        handles.push(handle.clone());
+        let clock = Clock::from_options(&network.config.options);
 
-        // Cancel the feeds task.
-        // If the task doesn't finish, terminate the bot.
-        feeds_token.cancel();
+        runs.push(bot::run(
+            network,
+            handle,
+            Arc::new(Mutex::new(queue_receiver)),
+            Arc::clone(&db),
+            clock,
+            pending_tells.clone(),
+        ));
+    }
 
-        if feeds_task.await.is_err() {
-            eprintln!("Could not cancel feeds task.");
-            eprintln!("Terminating bot...");
+    // Spawn the API task.
+    let db_clone = Arc::clone(&db);
+    let options_clone = Arc::clone(&options);
+    let api_token = CancellationToken::new();
+    task::spawn(async move {
+        let my_state = api::BotState {
+            db: db_clone,
+            options: options_clone,
+            networks: handles,
+            hooks,
+        };
 
-            return;
+        if rocket::build()
+            .mount(
+                "/api",
+                routes![
+                    api::add_event,
+                    api::add_quote,
+                    api::answer,
+                    api::answers,
+                    api::audit,
+                    api::create_answer,
+                    api::create_driver,
+                    api::create_event,
+                    api::create_f1_bet,
+                    api::create_feed,
+                    api::create_interest,
+                    api::create_quote,
+                    api::create_race_result,
+                    api::create_time_zone,
+                    api::create_train_schedule,
+                    api::create_weather_setting,
+                    api::delete_event,
+                    api::delete_quote,
+                    api::driver,
+                    api::drivers,
+                    api::event,
+                    api::events,
+                    api::f1_bet,
+                    api::f1_bets,
+                    api::feed,
+                    api::feeds,
+                    api::hook,
+                    api::interest,
+                    api::interests,
+                    api::karma,
+                    api::logs,
+                    api::patch_answer,
+                    api::patch_driver,
+                    api::patch_event,
+                    api::patch_f1_bet,
+                    api::patch_feed,
+                    api::patch_interest,
+                    api::patch_quote,
+                    api::patch_race_result,
+                    api::patch_time_zone,
+                    api::patch_train_schedule,
+                    api::patch_weather_setting,
+                    api::quote,
+                    api::quotes,
+                    api::race_result,
+                    api::race_results,
+                    api::remove_answer,
+                    api::remove_driver,
+                    api::remove_event,
+                    api::remove_f1_bet,
+                    api::remove_feed,
+                    api::remove_interest,
+                    api::remove_quote,
+                    api::remove_race_result,
+                    api::remove_time_zone,
+                    api::remove_train_schedule,
+                    api::remove_weather_setting,
+                    api::replace_answer,
+                    api::replace_driver,
+                    api::replace_event,
+                    api::replace_f1_bet,
+                    api::replace_feed,
+                    api::replace_interest,
+                    api::replace_quote,
+                    api::replace_race_result,
+                    api::replace_time_zone,
+                    api::replace_train_schedule,
+                    api::replace_weather_setting,
+                    api::say,
+                    api::score_f1_bets,
+                    api::status,
+                    api::stream,
+                    api::time_zone,
+                    api::time_zones,
+                    api::train_schedule,
+                    api::train_schedules,
+                    api::update_event,
+                    api::update_quote,
+                    api::weather_setting,
+                    api::weather_settings,
+                    api::openapi::docs,
+                    api::openapi::openapi,
+                ],
+            )
+            .register("/api", catchers![api::catch_default])
+            .mount("/", FileServer::from("static/").rank(1))
+            .mount(
+                "/",
+                routes![
+                    all,
+                    api::admin::index,
+                    api::admin::script,
+                    api::admin::style,
+                    api::paste
+                ],
+            )
+            .manage(my_state)
+            .launch()
+            .await
+            .is_err()
+        {
+            api_token.cancel();
+            eprintln!("Problem with the API task.");
         }
+    });
 
-        eprintln!("Feeds task finished.");
-
-        // Cancel the train_game task.
-        // If the task doesn't finish, terminate the bot.
-        train_game_token.cancel();
-
-        if train_game_task.await.is_err() {
-            eprintln!("Could not cancel train_game task.");
-            eprintln!("Terminating bot...");
-
-            return;
-        }
+    // Run every network until the bot terminates.
+    future::join_all(runs).await;
+}
 
-        eprintln!("Train game task finished.");
+// Value of a command line argument given as --name value.
+fn arg<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let index = args.iter().position(|a| a == name)?;
 
-        // Wait 30 seconds before trying to reconnect.
-        // This should avoid an overly fast reconnect.
-        println!("Waiting 30 seconds before reconnecting to the IRC server...");
-        time::sleep(Duration::from_secs(30)).await;
-        println!("Reconnecting to the IRC server...");
-    }
+    args.get(index + 1).map(|a| a.as_str())
 }
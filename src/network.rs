@@ -0,0 +1,129 @@
+use crate::activity::Activity;
+use crate::queue::MessageQueue;
+use crate::status::BotStatus;
+use crate::transport::Transport;
+use irc::client::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub const DEFAULT_NETWORK: &str = "default";
+
+pub fn default_network() -> String {
+    String::from(DEFAULT_NETWORK)
+}
+
+#[derive(Deserialize)]
+struct NetworkConfig {
+    name: String,
+    #[serde(flatten)]
+    config: Config,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    options: HashMap<String, String>,
+    #[serde(default)]
+    networks: Vec<NetworkConfig>,
+}
+
+pub struct Network {
+    pub name: String,
+    // Identifier stored with every record of this network. Networks configured with the same
+    // data_network option share their records.
+    pub data: String,
+    pub config: Config,
+}
+
+impl Network {
+    fn new(name: String, config: Config) -> Self {
+        let data = match config.options.get("data_network") {
+            Some(data) => data.clone(),
+            None => name.clone(),
+        };
+
+        Self { name, data, config }
+    }
+}
+
+// Handles to a network that stay valid across reconnects, used by the API.
+#[derive(Clone)]
+pub struct NetworkHandle {
+    pub name: String,
+    // The network the records of this network are stored under, see Network.
+    pub data: String,
+    // Set while the network is connected.
+    pub transport: Arc<Mutex<Option<Arc<dyn Transport>>>>,
+    pub queue: MessageQueue,
+    pub status: Arc<Mutex<BotStatus>>,
+    pub activity: Activity,
+}
+
+// Loads every network from the config file. A config file without a list of networks is a plain
+// IRC client config for a single network, named after its network option.
+pub fn load(path: &str) -> Result<Vec<Network>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Err(format!("Could not read configuration file ({path}).")),
+    };
+    let config_file: ConfigFile = match toml::from_str(&contents) {
+        Ok(config_file) => config_file,
+        Err(error) => {
+            return Err(format!(
+                "Invalid configuration file ({path}). Cause: {error}."
+            ))
+        }
+    };
+
+    if config_file.networks.is_empty() {
+        let config = match Config::load(path) {
+            Ok(config) => config,
+            Err(error) => match error {
+                irc::error::Error::Io(_) => {
+                    return Err(format!("Could not read configuration file ({path})."))
+                }
+                _ => return Err(String::from("Unknown error parsing configuration file.")),
+            },
+        };
+        let name = match config.options.get("network") {
+            Some(name) => name.clone(),
+            None => default_network(),
+        };
+
+        return Ok(vec![Network::new(name, config)]);
+    }
+
+    let mut networks: Vec<Network> = Vec::new();
+
+    for mut network_config in config_file.networks {
+        if networks.iter().any(|n| n.name == network_config.name) {
+            return Err(format!(
+                "Invalid configuration file ({path}). Cause: duplicate network {}.",
+                network_config.name
+            ));
+        }
+
+        // Options shared by all networks can be overridden by each network.
+        let mut options = config_file.options.clone();
+
+        options.extend(network_config.config.options);
+        network_config.config.options = options;
+        networks.push(Network::new(network_config.name, network_config.config));
+    }
+
+    Ok(networks)
+}
+
+pub async fn connect(config: &Config) -> Result<Client, String> {
+    match Client::from_config(config.clone()).await {
+        Ok(client) => Ok(client),
+        Err(error) => match error {
+            irc::error::Error::InvalidConfig { path, cause } => Err(format!(
+                "Invalid configuration file ({path}). Cause: {cause}."
+            )),
+            _ => Err(String::from("Unknown error parsing configuration file.")),
+        },
+    }
+}
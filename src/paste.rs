@@ -0,0 +1,133 @@
+use crate::database::{CsvRecord, Database};
+use crate::utils;
+use chrono::{DateTime, Duration, Utc};
+use rand::distributions::Alphanumeric;
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const ID_LENGTH: usize = 8;
+
+#[derive(PartialEq)]
+struct Paste {
+    id: String,
+    created: DateTime<Utc>,
+    text: String,
+}
+
+impl CsvRecord for Paste {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            id: fields[0].clone(),
+            created: fields[1].parse().unwrap_or_default(),
+            text: fields[2].clone(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![self.id.clone(), self.created.to_string(), self.text.clone()]
+    }
+}
+
+fn expiry(options: &HashMap<String, String>) -> Duration {
+    Duration::hours(match options.get("paste_expiry") {
+        Some(paste_expiry) => paste_expiry.parse().unwrap_or(24),
+        None => 24,
+    })
+}
+
+// Stores output that would take more than paste_lines lines on the channel and returns a short
+// summary with a link to it instead. Without a paste_url the output is returned untouched.
+pub async fn overflow(
+    output: String,
+    options: &HashMap<String, String>,
+    db: Arc<Mutex<Database>>,
+) -> String {
+    let paste_url = match options.get("paste_url") {
+        Some(paste_url) => paste_url.trim_end_matches('/'),
+        None => return output,
+    };
+    let paste_lines = match options.get("paste_lines") {
+        Some(paste_lines) => paste_lines.parse().unwrap_or(5),
+        None => 5,
+    };
+    let paste_max_size = match options.get("paste_max_size") {
+        Some(paste_max_size) => paste_max_size.parse().unwrap_or(65536),
+        None => 65536,
+    };
+    let max_chunk_size = match options.get("max_chunk_size") {
+        Some(max_chunk_size) => max_chunk_size.parse().unwrap_or(410),
+        None => 410,
+    };
+    let chunks = utils::split_message(&output, max_chunk_size);
+
+    if chunks.len() <= paste_lines {
+        return output;
+    }
+
+    let mut text = output.replace("\r\n", "\n");
+
+    if text.len() > paste_max_size {
+        let mut end = paste_max_size;
+
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        text.truncate(end);
+    }
+
+    let id: String = StdRng::from_entropy()
+        .sample_iter(&Alphanumeric)
+        .take(ID_LENGTH)
+        .map(char::from)
+        .collect();
+    let now = Utc::now();
+    let expiry = expiry(options);
+    let db = db.lock().await;
+
+    if let Err(error) = db.delete("pastes", |p: &&Paste| {
+        now.signed_duration_since(p.created) > expiry
+    }) {
+        eprintln!("{error}");
+    }
+
+    if let Err(error) = db.insert(
+        "pastes",
+        Paste {
+            id: id.clone(),
+            created: now,
+            text,
+        },
+    ) {
+        eprintln!("{error}");
+
+        return output;
+    }
+
+    format!(
+        "{} [+{} lines: {}/p/{}]",
+        chunks[0],
+        chunks.len() - 1,
+        paste_url,
+        id
+    )
+}
+
+pub async fn find(
+    id: &str,
+    options: &HashMap<String, String>,
+    db: Arc<Mutex<Database>>,
+) -> Option<String> {
+    let expiry = expiry(options);
+    let pastes = db
+        .lock()
+        .await
+        .select("pastes", |p: &Paste| {
+            p.id == id && Utc::now().signed_duration_since(p.created) <= expiry
+        })
+        .ok()??;
+
+    pastes.into_iter().next().map(|p| p.text)
+}
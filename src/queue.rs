@@ -0,0 +1,135 @@
+use crate::utils;
+use std::collections::VecDeque;
+use tokio::sync::mpsc::{self, error::SendError, UnboundedReceiver, UnboundedSender};
+
+const MAX_LINE_LENGTH: usize = 512;
+// Room for the "!user@host " part of the source prefix the server adds when relaying our messages.
+const USERHOST_RESERVE: usize = 77;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Priority {
+    High,
+    Normal,
+    Low,
+}
+
+// How a message is sent, all of them go through the queue to be split and paced alike.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessageKind {
+    Message,
+    Notice,
+    Action,
+}
+
+#[derive(Debug)]
+pub struct OutgoingMessage {
+    pub target: String,
+    pub text: String,
+    pub priority: Priority,
+    pub kind: MessageKind,
+}
+
+#[derive(Clone)]
+pub struct MessageQueue {
+    sender: UnboundedSender<OutgoingMessage>,
+}
+
+impl MessageQueue {
+    pub fn new() -> (Self, QueueReceiver) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        (
+            Self { sender },
+            QueueReceiver {
+                receiver,
+                pending: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            },
+        )
+    }
+
+    pub fn send(
+        &self,
+        target: &str,
+        text: &str,
+        priority: Priority,
+    ) -> Result<(), SendError<OutgoingMessage>> {
+        self.send_as(MessageKind::Message, target, text, priority)
+    }
+
+    pub fn send_as(
+        &self,
+        kind: MessageKind,
+        target: &str,
+        text: &str,
+        priority: Priority,
+    ) -> Result<(), SendError<OutgoingMessage>> {
+        self.sender.send(OutgoingMessage {
+            target: String::from(target),
+            text: String::from(text),
+            priority,
+            kind,
+        })
+    }
+}
+
+pub struct QueueReceiver {
+    receiver: UnboundedReceiver<OutgoingMessage>,
+    pending: [VecDeque<OutgoingMessage>; 3],
+}
+
+impl QueueReceiver {
+    // Waits for the next line to send, always taking lines of a higher priority first. Messages are
+    // split into lines as they are received, so that announcements can be sent in between the lines
+    // of a long message with a lower priority.
+    pub async fn next(&mut self, nick: &str, max_chunk_size: usize) -> Option<OutgoingMessage> {
+        if self.pending.iter().all(VecDeque::is_empty) {
+            let message = self.receiver.recv().await?;
+
+            self.push(message, nick, max_chunk_size);
+        }
+
+        self.try_next(nick, max_chunk_size)
+    }
+
+    // Takes the next line to send without waiting for one.
+    pub fn try_next(&mut self, nick: &str, max_chunk_size: usize) -> Option<OutgoingMessage> {
+        while let Ok(message) = self.receiver.try_recv() {
+            self.push(message, nick, max_chunk_size);
+        }
+
+        self.pending.iter_mut().find_map(VecDeque::pop_front)
+    }
+
+    fn push(&mut self, message: OutgoingMessage, nick: &str, max_chunk_size: usize) {
+        let max_length = max_line_length(nick, &message.target, message.kind, max_chunk_size);
+        let lines = utils::split_message(&message.text, max_length)
+            .into_iter()
+            .map(|line| OutgoingMessage {
+                target: message.target.clone(),
+                text: String::from(line),
+                priority: message.priority,
+                kind: message.kind,
+            });
+
+        self.pending[message.priority as usize].extend(lines);
+    }
+}
+
+// The longest text that fits in ":nick!user@host PRIVMSG target :text\r\n" as relayed by the server,
+// or in the NOTICE and CTCP ACTION variants of it.
+fn max_line_length(nick: &str, target: &str, kind: MessageKind, max_chunk_size: usize) -> usize {
+    let command = match kind {
+        MessageKind::Message => "PRIVMSG ".len(),
+        MessageKind::Notice => "NOTICE ".len(),
+        MessageKind::Action => "PRIVMSG ".len() + "\x01ACTION \x01".len(),
+    };
+    let overhead = ":".len()
+        + nick.len()
+        + USERHOST_RESERVE
+        + command
+        + target.len()
+        + " :".len()
+        + "\r\n".len();
+
+    MAX_LINE_LENGTH.saturating_sub(overhead).min(max_chunk_size)
+}
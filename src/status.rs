@@ -0,0 +1,192 @@
+use crate::transport::Transport;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    Running,
+    Stopped,
+    Failed,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct FeedPoll {
+    pub id: u64,
+    pub url: String,
+    pub polled: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ChannelStatus {
+    pub name: String,
+    pub users: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StatusReport {
+    pub network: String,
+    pub connected: bool,
+    pub connected_since: Option<DateTime<Utc>>,
+    pub nick: String,
+    pub channels: Vec<ChannelStatus>,
+    pub uptime: i64,
+    pub tasks: BTreeMap<String, TaskState>,
+    pub feeds: Vec<FeedPoll>,
+    pub pending_reminders: usize,
+}
+
+// Decrements the pending reminders count when dropped, so that reminders cut short by the command
+// timeout are also accounted for.
+pub struct PendingReminder(Arc<AtomicUsize>);
+
+impl Drop for PendingReminder {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub struct BotStatus {
+    network: String,
+    started: DateTime<Utc>,
+    connected_since: Option<DateTime<Utc>>,
+    tasks: BTreeMap<String, TaskState>,
+    feeds: BTreeMap<u64, FeedPoll>,
+    reminders: Arc<AtomicUsize>,
+}
+
+impl BotStatus {
+    pub fn new(network: &str) -> Self {
+        Self {
+            network: String::from(network),
+            started: Utc::now(),
+            connected_since: None,
+            tasks: BTreeMap::new(),
+            feeds: BTreeMap::new(),
+            reminders: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn set_connected(&mut self, connected: bool) {
+        self.connected_since = match connected {
+            true => Some(Utc::now()),
+            false => None,
+        };
+    }
+
+    pub fn set_task(&mut self, name: &str, state: TaskState) {
+        self.tasks.insert(String::from(name), state);
+    }
+
+    // A task that returns before being cancelled has stopped on its own, which is a failure.
+    pub fn finish_task(&mut self, name: &str, cancelled: bool) {
+        match cancelled {
+            true => self.set_task(name, TaskState::Stopped),
+            false => self.set_task(name, TaskState::Failed),
+        }
+    }
+
+    pub fn feed_polled(&mut self, id: u64, url: &str) {
+        self.feeds.insert(
+            id,
+            FeedPoll {
+                id,
+                url: String::from(url),
+                polled: Utc::now(),
+            },
+        );
+    }
+
+    pub fn pending_reminder(&self) -> PendingReminder {
+        self.reminders.fetch_add(1, Ordering::SeqCst);
+
+        PendingReminder(Arc::clone(&self.reminders))
+    }
+
+    // The nick and channels are only known while connected.
+    pub async fn report(&self, transport: Option<&dyn Transport>) -> StatusReport {
+        let (nick, channels) = match transport {
+            Some(transport) => (transport.nick().await, transport.channels().await),
+            None => (String::new(), Vec::new()),
+        };
+
+        StatusReport {
+            network: self.network.clone(),
+            connected: self.connected_since.is_some(),
+            connected_since: self.connected_since,
+            nick,
+            channels,
+            uptime: Utc::now().signed_duration_since(self.started).num_seconds(),
+            tasks: self.tasks.clone(),
+            feeds: self.feeds.values().cloned().collect(),
+            pending_reminders: self.reminders.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl StatusReport {
+    pub fn summary(&self) -> String {
+        let connection = match self.connected_since {
+            Some(connected_since) => format!(
+                "Connected to {} as {} for {}",
+                self.network,
+                self.nick,
+                format_duration(Utc::now().signed_duration_since(connected_since))
+            ),
+            None => format!("Disconnected from {}", self.network),
+        };
+        let channels = self
+            .channels
+            .iter()
+            .map(|c| format!("{} ({})", c.name, c.users))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let tasks = self
+            .tasks
+            .iter()
+            .map(|(name, state)| {
+                format!(
+                    "{} {}",
+                    name,
+                    match state {
+                        TaskState::Running => "running",
+                        TaskState::Stopped => "stopped",
+                        TaskState::Failed => "failed",
+                    }
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        let feeds = match self.feeds.iter().map(|f| f.polled).max() {
+            Some(polled) => format!(
+                "{} polled, last at {}",
+                self.feeds.len(),
+                polled.format("%H:%M UTC")
+            ),
+            None => String::from("none polled"),
+        };
+
+        format!(
+            "{} | Uptime: {} | Channels: {} | Tasks: {} | Feeds: {} | Reminders: {}",
+            connection,
+            format_duration(Duration::seconds(self.uptime)),
+            channels,
+            tasks,
+            feeds,
+            self.pending_reminders
+        )
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!(
+        "{}d {}h {}m",
+        duration.num_days(),
+        duration.num_hours() % 24,
+        duration.num_minutes() % 60
+    )
+}
@@ -1,4 +1,7 @@
 pub mod base;
 pub mod feeds;
+pub mod logs;
 pub mod next;
+pub mod outgoing;
+pub mod plugins;
 pub mod train_game;
@@ -1,78 +1,269 @@
-use irc::client::prelude::Command;
-use irc::client::Client;
-use std::io::ErrorKind;
-use std::sync::Arc;
-use tokio::io::AsyncBufReadExt;
-use tokio::sync::Mutex;
-use tokio::time::{sleep, Duration};
-use tokio::{
-    fs::{File, OpenOptions},
-    io::BufReader,
-};
+use crate::bot::Session;
+use crate::queue::{MessageKind, Priority};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, DirBuilder, Permissions};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::Path;
+use std::process;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::task;
 use tokio_util::sync::CancellationToken;
 
-pub async fn external_message(client: Arc<Mutex<Client>>, token: CancellationToken) {
+// A line of JSON sent to the socket or written to the FIFO.
+#[derive(Deserialize)]
+struct Request {
+    id: Option<Value>,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    target: String,
+    #[serde(default)]
+    text: String,
+    nick: Option<String>,
+    #[serde(default)]
+    quiet: bool,
+}
+
+// The line of JSON sent back on the socket for every request.
+#[derive(Serialize)]
+struct Ack {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Value>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// Lets local programs send messages and run commands through a Unix socket, and through a FIFO
+// when fifo_path is set.
+pub async fn external_message(network: String, session: Session, token: CancellationToken) {
+    // Each network needs its own socket when running more than one network.
+    let path = match session.options.get("socket_path") {
+        Some(path) => path.clone(),
+        None => format!("{network}.sock"),
+    };
+
+    // A socket left behind by a previous run is in the way, anything else at the path is kept.
+    if let Ok(metadata) = fs::symlink_metadata(&path) {
+        if metadata.file_type().is_socket() {
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    let listener = match bind(&path) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Could not listen on {path}: {error}");
+
+            return;
+        }
+    };
+
+    if let Some(fifo_path) = session.options.get("fifo_path") {
+        task::spawn(fifo(fifo_path.clone(), session.clone(), token.clone()));
+    }
+
     while !token.is_cancelled() {
-        let file = match OpenOptions::new()
-            .read(true)
-            .write(true)
-            .truncate(true)
-            .open("out.txt")
-            .await
-        {
-            Ok(file) => file,
-            Err(error) => match error.kind() {
-                ErrorKind::NotFound => match File::create("out.txt").await {
-                    Ok(_) => continue,
-                    Err(_) => {
-                        eprintln!("Could not create out.txt.");
-
-                        return;
-                    }
-                },
-                _ => {
-                    eprintln!("Could not create out.txt.");
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(error) => {
+                    eprintln!("{error}");
 
-                    return;
+                    continue;
                 }
             },
+            _ = token.cancelled() => break,
         };
 
-        let mut reader = BufReader::new(file);
+        task::spawn(client(stream, session.clone(), token.clone()));
+    }
 
-        while !token.is_cancelled() {
-            let mut line = String::new();
+    let _ = fs::remove_file(&path);
+}
 
-            match reader.read_line(&mut line).await {
-                Ok(0) => {
-                    sleep(Duration::from_secs(1)).await;
+// Only the user running the bot may use the socket. It's bound in a directory nobody else can enter
+// and linked to path once its permissions are set, so nobody can connect in between. Linking fails
+// when something is already at path.
+fn bind(path: &str) -> io::Result<UnixListener> {
+    let path = Path::new(path);
+    let dir = path.with_file_name(format!(
+        ".{}.{}",
+        path.file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default(),
+        process::id()
+    ));
 
-                    continue;
+    DirBuilder::new().mode(0o700).create(&dir)?;
+
+    let bound = dir.join("socket");
+    let listener = UnixListener::bind(&bound).and_then(|listener| {
+        fs::set_permissions(&bound, Permissions::from_mode(0o600))?;
+        fs::hard_link(&bound, path)?;
+
+        Ok(listener)
+    });
+    let _ = fs::remove_dir_all(&dir);
+
+    listener
+}
+
+// Handles the requests of a connected client, one per line, until it disconnects.
+async fn client(stream: UnixStream, session: Session, token: CancellationToken) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => line,
+                _ => return,
+            },
+            _ = token.cancelled() => return,
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let ack = match serde_json::to_string(&handle(&line, &session).await) {
+            Ok(ack) => format!("{ack}\n"),
+            Err(_) => continue,
+        };
+
+        if writer.write_all(ack.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+// Reads requests from a FIFO created by the user, opening it again whenever the writer is done.
+// Nobody reads the acknowledgements, so only the failures are logged.
+async fn fifo(path: String, session: Session, token: CancellationToken) {
+    while !token.is_cancelled() {
+        // Opening a FIFO waits for a writer.
+        let file = tokio::select! {
+            file = File::open(&path) => match file {
+                Ok(file) => file,
+                Err(error) => {
+                    eprintln!("Could not open {path}: {error}");
+
+                    return;
                 }
-                Ok(_) => {
-                    if line.len() > 2 && line.starts_with('#') {
-                        let split_line: Vec<&str> = line.split_ascii_whitespace().collect();
-
-                        if split_line.len() > 1 {
-                            if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-                                split_line[0].to_string(),
-                                split_line[1..].join(" "),
-                            )) {
+            },
+            _ = token.cancelled() => return,
+        };
+        let mut lines = BufReader::new(file).lines();
+
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => line,
+                    _ => break,
+                },
+                _ = token.cancelled() => return,
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(error) = handle(&line, &session).await.error {
+                eprintln!("{path}: {error}");
+            }
+        }
+    }
+}
+
+async fn handle(line: &str, session: &Session) -> Ack {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(error) => {
+            return Ack {
+                id: None,
+                ok: false,
+                output: None,
+                error: Some(format!("Invalid request: {error}")),
+            }
+        }
+    };
+    let result = if request.target.is_empty() {
+        Err(String::from("Missing target."))
+    } else {
+        match request.kind.as_str() {
+            "message" => session
+                .queue
+                .send(&request.target, &request.text, Priority::Normal)
+                .map(|_| None)
+                .map_err(|error| error.to_string()),
+            "notice" => session
+                .queue
+                .send_as(
+                    MessageKind::Notice,
+                    &request.target,
+                    &request.text,
+                    Priority::Normal,
+                )
+                .map(|_| None)
+                .map_err(|error| error.to_string()),
+            "action" => session
+                .queue
+                .send_as(
+                    MessageKind::Action,
+                    &request.target,
+                    &request.text,
+                    Priority::Normal,
+                )
+                .map(|_| None)
+                .map_err(|error| error.to_string()),
+            "join" => session.transport.join(&request.target).await.map(|_| None),
+            "part" => session.transport.part(&request.target).await.map(|_| None),
+            "command" => {
+                let nick = request.nick.unwrap_or_else(|| String::from("external"));
+
+                match session
+                    .command(&request.text, Some(nick), &request.target)
+                    .await
+                {
+                    Some(output) => {
+                        if !request.quiet {
+                            if let Err(error) =
+                                session
+                                    .queue
+                                    .send(&request.target, &output, Priority::Normal)
+                            {
                                 eprintln!("{error}");
                             }
                         }
-                    }
-
-                    break;
-                }
-                Err(err) => {
-                    sleep(Duration::from_secs(1)).await;
-
-                    eprintln!("Error reading line: {}", err);
 
-                    continue;
+                        Ok(Some(output))
+                    }
+                    None => Err(String::from("Not a command.")),
                 }
             }
+            kind => Err(format!("Unknown request type {kind}.")),
         }
+    };
+
+    match result {
+        Ok(output) => Ack {
+            id: request.id,
+            ok: true,
+            output,
+            error: None,
+        },
+        Err(error) => Ack {
+            id: request.id,
+            ok: false,
+            output: None,
+            error: Some(error),
+        },
     }
 }
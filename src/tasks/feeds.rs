@@ -1,8 +1,12 @@
-use crate::database::{CsvRecord, Database};
+use crate::database::{CsvRecord, Database, Identified};
+use crate::network;
+use crate::queue::{MessageQueue, Priority};
+use crate::status::BotStatus;
+use crate::webhook;
 use chrono::{DateTime, Utc};
 use feed_rs::parser;
-use irc::client::prelude::Command;
-use irc::client::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -10,14 +14,20 @@ use tokio::task;
 use tokio::time::{sleep, Duration};
 use tokio_util::sync::CancellationToken;
 use url::Url;
-
-#[derive(Debug, PartialEq)]
-struct Feed {
-    id: u32,
-    category: String,
-    url: String,
-    channel: String,
-    published: DateTime<Utc>,
+use utoipa::ToSchema;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Feed {
+    #[serde(default)]
+    pub id: u64,
+    pub category: String,
+    pub url: String,
+    pub channel: String,
+    // Time of the last item sent, later items are sent on the next refresh.
+    #[serde(default = "Utc::now")]
+    pub published: DateTime<Utc>,
+    #[serde(default)]
+    pub network: String,
 }
 
 impl CsvRecord for Feed {
@@ -31,6 +41,10 @@ impl CsvRecord for Feed {
                 Ok(published) => published,
                 Err(_) => Utc::now(),
             },
+            network: fields
+                .get(5)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -41,14 +55,27 @@ impl CsvRecord for Feed {
             self.url.clone(),
             self.channel.clone(),
             self.published.to_string(),
+            self.network.clone(),
         ]
     }
 }
 
+impl Identified for Feed {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+}
+
 pub async fn feeds(
+    network: String,
     options: Arc<HashMap<String, String>>,
-    client: Arc<Mutex<Client>>,
+    queue: MessageQueue,
     db: Arc<Mutex<Database>>,
+    status: Arc<Mutex<BotStatus>>,
     token: CancellationToken,
 ) {
     while !token.is_cancelled() {
@@ -71,7 +98,11 @@ pub async fn feeds(
         // }))
         // .await;
 
-        let feeds: Vec<Feed> = match db.lock().await.select("feeds", |_| true) {
+        let feeds: Vec<Feed> = match db
+            .lock()
+            .await
+            .select("feeds", |f: &Feed| f.network == network)
+        {
             Ok(feeds) => match feeds {
                 Some(feeds) => feeds,
                 None => continue,
@@ -83,8 +114,11 @@ pub async fn feeds(
             }
         };
         for feed in feeds {
-            let client_clone = Arc::clone(&client);
+            let queue_clone = queue.clone();
             let db_clone = Arc::clone(&db);
+            let status_clone = Arc::clone(&status);
+            let network_clone = network.clone();
+            let options_clone = Arc::clone(&options);
 
             task::spawn(async move {
                 let id = feed.id;
@@ -110,6 +144,8 @@ pub async fn feeds(
                     Err(_) => return,
                 };
 
+                status_clone.lock().await.feed_polled(id, &url);
+
                 let mut entries = feed.entries;
                 entries.sort_by(|a, b| b.published.cmp(&a.published));
 
@@ -123,13 +159,16 @@ pub async fn feeds(
                     };
 
                     if entry_published > last_modified {
-                        if let Err(error) = client_clone.lock().await.send(Command::PRIVMSG(
-                            channel.clone(),
-                            match entry.title {
-                                Some(title) => format!("\x02[{}]\x02", title.content),
+                        let title = entry.title.map(|title| title.content);
+
+                        if let Err(error) = queue_clone.send(
+                            &channel,
+                            &match &title {
+                                Some(title) => format!("\x02[{title}]\x02"),
                                 None => String::from(""),
                             },
-                        )) {
+                            Priority::Low,
+                        ) {
                             eprintln!("{error}");
                         }
 
@@ -141,14 +180,24 @@ pub async fn feeds(
                             Err(_) => entry.links[0].href.clone(),
                         };
 
-                        if let Err(error) = client_clone
-                            .lock()
-                            .await
-                            .send(Command::PRIVMSG(channel.clone(), clean_link))
-                        {
+                        if let Err(error) = queue_clone.send(&channel, &clean_link, Priority::Low) {
                             eprintln!("{error}");
                         }
 
+                        webhook::notify(
+                            "feed_item",
+                            &network_clone,
+                            json!({
+                                "category": category,
+                                "title": title,
+                                "url": clean_link,
+                                "channel": channel,
+                                "published": entry_published.to_rfc3339(),
+                            }),
+                            &options_clone,
+                            Arc::clone(&db_clone),
+                        );
+
                         if db_clone
                             .lock()
                             .await
@@ -160,6 +209,7 @@ pub async fn feeds(
                                     url: url.clone(),
                                     channel: channel.clone(),
                                     published: entry_published,
+                                    network: network_clone.clone(),
                                 },
                                 |f: &&Feed| f.id == id,
                             )
@@ -0,0 +1,28 @@
+use crate::clock::Clock;
+use crate::commands::{log, seen};
+use crate::database::Database;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+use tokio_util::sync::CancellationToken;
+
+// Deletes the logged lines of the network that are past their retention and drops the older
+// sightings of the seen table, once an hour.
+pub async fn logs(
+    network: String,
+    options: Arc<HashMap<String, String>>,
+    db: Arc<Mutex<Database>>,
+    clock: Clock,
+    token: CancellationToken,
+) {
+    while !token.is_cancelled() {
+        log::prune(&network, &options, &clock, &db).await;
+        seen::compact(&db).await;
+
+        tokio::select! {
+            _ = sleep(Duration::from_secs(3600)) => (),
+            _ = token.cancelled() => return,
+        }
+    }
+}
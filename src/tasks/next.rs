@@ -1,16 +1,22 @@
-use crate::database::{CsvRecord, Database};
+use crate::clock::Clock;
+use crate::commands::f1bet::{self, Bet, ScoringSystem};
+use crate::database::{CsvRecord, Database, Identified};
+use crate::network;
+use crate::queue::{MessageQueue, Priority};
+use crate::webhook;
 use chrono::{DateTime, Utc};
 use circular_queue::CircularQueue;
-use irc::client::prelude::Command;
-use irc::client::Client;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration};
 use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
 
 #[derive(Hash, PartialEq)]
 struct Event {
@@ -21,6 +27,7 @@ struct Event {
     channel: String,
     tags: String,
     notify: bool,
+    network: String,
 }
 
 impl CsvRecord for Event {
@@ -36,6 +43,10 @@ impl CsvRecord for Event {
             channel: fields[4].clone(),
             tags: fields[5].clone(),
             notify: fields[6].parse().unwrap_or_default(),
+            network: fields
+                .get(7)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -48,25 +59,54 @@ impl CsvRecord for Event {
             self.channel.clone(),
             self.tags.clone(),
             self.notify.to_string(),
+            self.network.clone(),
         ]
     }
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Interest {
+    // Stored in the last column.
+    #[serde(default)]
+    pub id: u64,
     pub nick: String,
     pub tags: String,
+    #[serde(default)]
+    pub network: String,
 }
 
 impl CsvRecord for Interest {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(3)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             nick: fields[0].clone(),
             tags: fields[1].clone(),
+            network: fields
+                .get(2)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
     fn to_fields(&self) -> Vec<String> {
-        vec![self.nick.clone(), self.tags.clone()]
+        vec![
+            self.nick.clone(),
+            self.tags.clone(),
+            self.network.clone(),
+            self.id.to_string(),
+        ]
+    }
+}
+
+impl Identified for Interest {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
     }
 }
 
@@ -77,15 +117,71 @@ fn calculate_hash<T: Hash>(t: &T) -> u64 {
     s.finish()
 }
 
-pub async fn next(client: Arc<Mutex<Client>>, db: Arc<Mutex<Database>>, token: CancellationToken) {
+// The race of the latest result, which is the one the bets were last scored on.
+async fn last_race(db: &Arc<Mutex<Database>>) -> Option<String> {
+    match db.lock().await.select("results", |_: &Bet| true) {
+        Ok(Some(results)) => results.last().map(|r| r.race.clone()),
+        _ => None,
+    }
+}
+
+// Tells the webhooks how the bets on race scored.
+async fn bet_results(
+    race: &str,
+    network: &str,
+    options: &HashMap<String, String>,
+    db: &Arc<Mutex<Database>>,
+) {
+    let bets: Vec<Bet> = match db.lock().await.select("bets", |b: &Bet| {
+        b.race.to_lowercase() == race.to_lowercase() && b.network == network
+    }) {
+        Ok(Some(bets)) => bets,
+        _ => return,
+    };
+    let results: Vec<Bet> = match db.lock().await.select("results", |_| true) {
+        Ok(Some(results)) => results,
+        _ => return,
+    };
+    let scores: Vec<_> = f1bet::score_bets(bets, results, ScoringSystem::from_options(options))
+        .into_iter()
+        .map(|(nick, points)| json!({"nick": nick, "points": points}))
+        .collect();
+
+    webhook::notify(
+        "bet_results",
+        network,
+        json!({"race": race, "scores": scores}),
+        options,
+        Arc::clone(db),
+    );
+}
+
+pub async fn next(
+    network: String,
+    options: Arc<HashMap<String, String>>,
+    queue: MessageQueue,
+    db: Arc<Mutex<Database>>,
+    clock: Clock,
+    token: CancellationToken,
+) {
     let mut hashes = CircularQueue::with_capacity(10);
+    let mut race = last_race(&db).await;
 
     while !token.is_cancelled() {
         sleep(Duration::from_secs(30)).await;
 
+        // A new race result means the bets on that race can be scored.
+        if let Some(latest_race) = last_race(&db).await {
+            if race.as_ref() != Some(&latest_race) {
+                bet_results(&latest_race, &network, &options, &db).await;
+                race = Some(latest_race);
+            }
+        }
+
         let events: Vec<Event> = match db.lock().await.select("events", |e: &Event| {
-            e.datetime.signed_duration_since(Utc::now()).num_seconds() <= 300
-                && e.datetime.signed_duration_since(Utc::now()).num_seconds() > 240
+            e.datetime.signed_duration_since(clock.now()).num_seconds() <= 300
+                && e.datetime.signed_duration_since(clock.now()).num_seconds() > 240
+                && e.network == network
         }) {
             Ok(events) => match events {
                 Some(events) => events
@@ -105,22 +201,41 @@ pub async fn next(client: Arc<Mutex<Client>>, db: Arc<Mutex<Database>>, token: C
             let hash = calculate_hash(&event);
 
             if !hashes.iter().any(|h| h == &hash) {
-                if let Err(error) = client.lock().await.send(Command::PRIVMSG(
-                    event.channel.clone(),
-                    format!(
+                if let Err(error) = queue.send(
+                    &event.channel,
+                    &format!(
                         "\x034Starting in 5 minutes:\x03 \x02{} {} {}\x02",
                         event.category, event.name, event.description
                     ),
-                )) {
+                    Priority::High,
+                ) {
                     eprintln!("{error}");
                 }
 
+                webhook::notify(
+                    "event_start",
+                    &network,
+                    json!({
+                        "category": event.category,
+                        "name": event.name,
+                        "description": event.description,
+                        "datetime": event.datetime.to_rfc3339(),
+                        "channel": event.channel,
+                        "tags": event.tags,
+                    }),
+                    &options,
+                    Arc::clone(&db),
+                );
+
                 if event.notify {
-                    let interests: Option<Vec<Interest>> =
-                        match db.lock().await.select("interests", |_| true) {
-                            Ok(interests) => interests,
-                            Err(_) => None,
-                        };
+                    let interests: Option<Vec<Interest>> = match db
+                        .lock()
+                        .await
+                        .select("interests", |i: &Interest| i.network == network)
+                    {
+                        Ok(interests) => interests,
+                        Err(_) => None,
+                    };
 
                     if let Some(interests) = interests {
                         let mut mentions: String = String::new();
@@ -134,10 +249,8 @@ pub async fn next(client: Arc<Mutex<Client>>, db: Arc<Mutex<Database>>, token: C
                         }
 
                         if !mentions.is_empty() {
-                            if let Err(error) = client
-                                .lock()
-                                .await
-                                .send(Command::PRIVMSG(event.channel.clone(), mentions))
+                            if let Err(error) =
+                                queue.send(&event.channel, &mentions, Priority::High)
                             {
                                 eprintln!("{error}");
                             }
@@ -0,0 +1,61 @@
+use crate::activity::{Activity, ActivityKind};
+use crate::clock::Clock;
+use crate::queue::{MessageKind, QueueReceiver};
+use crate::transport::Transport;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+use tokio_util::sync::CancellationToken;
+
+pub async fn outgoing(
+    transport: Arc<dyn Transport>,
+    receiver: Arc<Mutex<QueueReceiver>>,
+    options: Arc<HashMap<String, String>>,
+    activity: Activity,
+    clock: Clock,
+    token: CancellationToken,
+) {
+    let message_interval = match options.get("message_interval") {
+        Some(message_interval) => message_interval.parse().unwrap_or(1000),
+        None => 1000,
+    };
+    let max_chunk_size = match options.get("max_chunk_size") {
+        Some(max_chunk_size) => max_chunk_size.parse().unwrap_or(410),
+        None => 410,
+    };
+
+    // The receiver outlives each connection, so messages queued while disconnected are delivered
+    // once the bot reconnects.
+    let mut receiver = receiver.lock().await;
+
+    while !token.is_cancelled() {
+        let nick = transport.nick().await;
+        let message = tokio::select! {
+            message = receiver.next(&nick, max_chunk_size) => match message {
+                Some(message) => message,
+                None => return,
+            },
+            _ = token.cancelled() => return,
+        };
+
+        let sent = match message.kind {
+            MessageKind::Message => transport.send(&message.target, &message.text).await,
+            MessageKind::Notice => transport.notice(&message.target, &message.text).await,
+            MessageKind::Action => transport.action(&message.target, &message.text).await,
+        };
+
+        match sent {
+            Ok(()) => activity.publish(
+                ActivityKind::Output,
+                Some(nick),
+                &message.target,
+                &message.text,
+                &clock,
+            ),
+            Err(error) => eprintln!("{error}"),
+        }
+
+        sleep(Duration::from_millis(message_interval)).await;
+    }
+}
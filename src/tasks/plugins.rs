@@ -0,0 +1,239 @@
+use crate::clock::Clock;
+use crate::commands::plugin::{self, Manifest, Plugins};
+use crate::queue::MessageQueue;
+use crate::transport::{Incoming, IncomingKind};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{self, error::TrySendError, Receiver, Sender, UnboundedReceiver};
+use tokio::task;
+use tokio::time::{self, sleep, Duration, Interval};
+use tokio_util::sync::CancellationToken;
+
+// Events waiting to be written to a plugin that is not keeping up. Further events are dropped.
+const EVENT_BUFFER: usize = 100;
+const RESTART_DELAY: u64 = 30;
+
+// Written to the stdin of the events plugins as a single line of JSON.
+#[derive(Clone, Serialize)]
+pub struct PluginEvent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    network: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nick: Option<String>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    target: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    text: String,
+    time: String,
+}
+
+impl PluginEvent {
+    pub fn new(incoming: &Incoming, network: &str, clock: &Clock) -> Self {
+        Self {
+            kind: match incoming.kind {
+                IncomingKind::Message => "message",
+                IncomingKind::Join => "join",
+                IncomingKind::Part => "part",
+                IncomingKind::Quit => "quit",
+                IncomingKind::Nick => "nick",
+            },
+            network: String::from(network),
+            nick: incoming.nick.clone(),
+            target: incoming.target.clone(),
+            text: incoming.text.clone(),
+            time: clock.now().to_rfc3339(),
+        }
+    }
+
+    fn timer(network: &str, clock: &Clock) -> Self {
+        Self {
+            kind: "timer",
+            network: String::from(network),
+            nick: None,
+            target: String::new(),
+            text: String::new(),
+            time: clock.now().to_rfc3339(),
+        }
+    }
+}
+
+// Runs every events plugin while connected and hands each of them the events they may see.
+pub async fn plugins(
+    network: String,
+    plugins: Arc<Plugins>,
+    options: Arc<HashMap<String, String>>,
+    queue: MessageQueue,
+    clock: Clock,
+    mut events: UnboundedReceiver<PluginEvent>,
+    token: CancellationToken,
+) {
+    let mut senders: Vec<(Manifest, Sender<PluginEvent>)> = Vec::new();
+
+    for manifest in plugins.events() {
+        let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+
+        task::spawn(service(
+            manifest.clone(),
+            network.clone(),
+            Arc::clone(&options),
+            queue.clone(),
+            clock.clone(),
+            receiver,
+            token.clone(),
+        ));
+        senders.push((manifest.clone(), sender));
+    }
+
+    while !token.is_cancelled() {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => event,
+                None => return,
+            },
+            _ = token.cancelled() => return,
+        };
+
+        for (manifest, sender) in &senders {
+            // Quits and nick changes have no channel, so they go to every plugin.
+            if !event.target.is_empty() && !manifest.allowed(&event.target) {
+                continue;
+            }
+
+            if let Err(TrySendError::Full(_)) = sender.try_send(event.clone()) {
+                eprintln!(
+                    "Plugin {} is not keeping up, dropped an event.",
+                    manifest.name
+                );
+            }
+        }
+    }
+}
+
+// Keeps an events plugin running, restarting it whenever it stops.
+async fn service(
+    manifest: Manifest,
+    network: String,
+    options: Arc<HashMap<String, String>>,
+    queue: MessageQueue,
+    clock: Clock,
+    mut events: Receiver<PluginEvent>,
+    token: CancellationToken,
+) {
+    let name = manifest.name.clone();
+    let timeout = manifest.timeout(&options);
+    let max_output = plugin::max_output(&options);
+
+    while !token.is_cancelled() {
+        let mut command = match plugin::command(&manifest, std::slice::from_ref(&network), &options)
+        {
+            Some(command) => command,
+            None => {
+                eprintln!("Could not find plugin {name}.");
+
+                return;
+            }
+        };
+
+        // Whatever the plugin logs ends up with the logs of the bot.
+        command.stderr(Stdio::inherit());
+
+        // The child is killed when dropped, so leaving this block stops the plugin.
+        match command.spawn() {
+            Ok(mut child) => {
+                let mut stdin = child.stdin.take();
+                let stdout = child.stdout.take();
+                let name_clone = name.clone();
+                let queue_clone = queue.clone();
+
+                // Lines are read by a task of their own, since a read cancelled half way would
+                // lose part of a line.
+                let mut reader = task::spawn(async move {
+                    if let Some(stdout) = stdout {
+                        let mut stdout = BufReader::new(stdout);
+
+                        loop {
+                            let mut line = String::new();
+
+                            match (&mut stdout).take(max_output).read_line(&mut line).await {
+                                Ok(0) => break,
+                                Ok(_) => plugin::act(&name_clone, &line, &queue_clone),
+                                Err(error) => {
+                                    eprintln!("{error}");
+
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                });
+                let mut timer = manifest
+                    .interval
+                    .map(|interval| time::interval(Duration::from_secs(interval.max(1))));
+
+                loop {
+                    let event = tokio::select! {
+                        _ = &mut reader => break,
+                        event = events.recv() => match event {
+                            Some(event) => event,
+                            None => {
+                                reader.abort();
+
+                                return;
+                            }
+                        },
+                        _ = tick(&mut timer) => PluginEvent::timer(&network, &clock),
+                        _ = token.cancelled() => {
+                            reader.abort();
+
+                            return;
+                        }
+                    };
+                    let line = match serde_json::to_string(&event) {
+                        Ok(line) => format!("{line}\n"),
+                        Err(_) => continue,
+                    };
+                    let written = match stdin.as_mut() {
+                        Some(stdin) => {
+                            time::timeout(
+                                Duration::from_secs(timeout),
+                                stdin.write_all(line.as_bytes()),
+                            )
+                            .await
+                        }
+                        None => break,
+                    };
+
+                    // A plugin that stopped reading its events is restarted.
+                    if !matches!(written, Ok(Ok(()))) {
+                        reader.abort();
+
+                        break;
+                    }
+                }
+            }
+            Err(error) => eprintln!("Could not run plugin {name}: {error}"),
+        }
+
+        eprintln!("Plugin {name} stopped. Restarting in {RESTART_DELAY} seconds.");
+
+        tokio::select! {
+            _ = sleep(Duration::from_secs(RESTART_DELAY)) => {},
+            _ = token.cancelled() => return,
+        }
+    }
+}
+
+// Waits for the next tick of the timer, forever when there is no timer.
+async fn tick(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => future::pending().await,
+    }
+}
@@ -1,12 +1,15 @@
-use crate::database::{CsvRecord, Database};
+use crate::clock::Clock;
+use crate::database::{CsvRecord, Database, Identified};
+use crate::network;
+use crate::queue::{MessageQueue, Priority};
+use crate::webhook;
 use chrono::DateTime;
-use chrono::Datelike;
 use chrono::Timelike;
 use chrono::Utc;
-use irc::client::prelude::Command;
-use irc::client::Client;
 use itertools::Itertools;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
@@ -14,25 +17,37 @@ use tokio::sync::Mutex;
 use tokio::task;
 use tokio::time;
 use tokio_util::sync::CancellationToken;
+use utoipa::ToSchema;
 
 const MAX_DELAY: u64 = 8;
 const STOP_TIME: u64 = 5;
 const DERAIL_PROB: u8 = 5;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct TrainSchedule {
-    number: usize,
-    name: String,
-    hour: u32,
-    minute: u32,
-    delta: u64,
-    score: u64,
-    route: Vec<String>,
+    // Stored in the last column.
+    #[serde(default)]
+    pub id: u64,
+    pub number: usize,
+    pub name: String,
+    pub hour: u32,
+    pub minute: u32,
+    // Minutes between stations.
+    pub delta: u64,
+    pub score: u64,
+    // Channels the train stops at, stored separated by colons.
+    pub route: Vec<String>,
+    #[serde(default)]
+    pub network: String,
 }
 
 impl CsvRecord for TrainSchedule {
     fn from_fields(fields: &[String]) -> Self {
         Self {
+            id: fields
+                .get(8)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default(),
             number: fields[0].parse().unwrap_or(0),
             name: fields[1].parse().unwrap_or_default(),
             hour: fields[2].parse().unwrap_or(25),
@@ -40,6 +55,10 @@ impl CsvRecord for TrainSchedule {
             delta: fields[4].parse().unwrap_or(60),
             score: fields[5].parse().unwrap_or(10),
             route: fields[6].split(':').map(String::from).collect(),
+            network: fields
+                .get(7)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -52,17 +71,33 @@ impl CsvRecord for TrainSchedule {
             self.delta.to_string(),
             self.score.to_string(),
             self.route.join(":"),
+            self.network.clone(),
+            self.id.to_string(),
         ]
     }
 }
 
+impl Identified for TrainSchedule {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+}
+
 struct RandTrainScheduleIter {
     index: u32,
+    clock: Clock,
 }
 
 impl RandTrainScheduleIter {
-    fn new() -> Self {
-        Self { index: 0 }
+    fn new(clock: &Clock) -> Self {
+        Self {
+            index: 0,
+            clock: clock.clone(),
+        }
     }
 }
 
@@ -73,10 +108,12 @@ impl Iterator for RandTrainScheduleIter {
         if self.index < u32::MAX {
             let number = 6000;
             let name = String::from("Random Train");
-            let hour =
-                StdRng::seed_from_u64((Utc::now().day() + self.index) as u64).gen_range(0..23);
-            let minute =
-                StdRng::seed_from_u64((Utc::now().day() + self.index) as u64).gen_range(0..59);
+            let mut rng = self.clock.daily_rng(
+                &format!("train_{}", self.index),
+                self.clock.now().date_naive(),
+            );
+            let hour = rng.gen_range(0..23);
+            let minute = rng.gen_range(0..59);
             let delta = 10;
             let score = 10;
             let route = vec![String::from("#geeks"), String::from("#nerds")];
@@ -84,6 +121,7 @@ impl Iterator for RandTrainScheduleIter {
             self.index += 1;
 
             Some(TrainSchedule {
+                id: 0,
                 number,
                 name,
                 hour,
@@ -91,6 +129,7 @@ impl Iterator for RandTrainScheduleIter {
                 delta,
                 score,
                 route,
+                network: String::new(),
             })
         } else {
             None
@@ -102,6 +141,7 @@ pub struct Arrival {
     datetime: DateTime<Utc>,
     nick: String,
     number: usize,
+    network: String,
 }
 
 impl CsvRecord for Arrival {
@@ -110,6 +150,10 @@ impl CsvRecord for Arrival {
             datetime: fields[0].parse().unwrap_or_default(),
             nick: fields[1].to_lowercase(),
             number: fields[2].parse().unwrap_or_default(),
+            network: fields
+                .get(3)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -118,6 +162,7 @@ impl CsvRecord for Arrival {
             self.datetime.to_string(),
             self.nick.clone(),
             self.number.to_string(),
+            self.network.clone(),
         ]
     }
 }
@@ -127,6 +172,7 @@ pub struct Boarding {
     nick: String,
     number: usize,
     station: String,
+    network: String,
 }
 
 impl CsvRecord for Boarding {
@@ -135,6 +181,10 @@ impl CsvRecord for Boarding {
             nick: fields[0].clone(),
             number: fields[1].parse().unwrap_or(0),
             station: fields[2].clone(),
+            network: fields
+                .get(3)
+                .cloned()
+                .unwrap_or_else(network::default_network),
         }
     }
 
@@ -143,41 +193,57 @@ impl CsvRecord for Boarding {
             self.nick.clone(),
             self.number.to_string(),
             self.station.clone(),
+            self.network.clone(),
         ]
     }
 }
 
 pub struct TrainGame {
     token: CancellationToken,
-    client: Arc<Mutex<Client>>,
+    network: String,
+    options: Arc<HashMap<String, String>>,
+    queue: MessageQueue,
     db: Arc<Mutex<Database>>,
+    clock: Clock,
 }
 
 impl TrainGame {
     pub async fn new(
-        client: Arc<Mutex<Client>>,
+        network: String,
+        options: Arc<HashMap<String, String>>,
+        queue: MessageQueue,
         db: Arc<Mutex<Database>>,
+        clock: Clock,
         token: CancellationToken,
     ) -> Self {
         if let Err(error) = db
             .lock()
             .await
-            .delete("train_boardings", |_: &&Boarding| true)
+            .delete("train_boardings", |b: &&Boarding| b.network == network)
         {
             eprintln!("{error}");
         }
 
-        Self { token, client, db }
+        Self {
+            token,
+            network,
+            options,
+            queue,
+            db,
+            clock,
+        }
     }
 
     pub async fn run(&self) {
         while !self.token.is_cancelled() {
-            let mut rand_schedule_iter = RandTrainScheduleIter::new();
+            let mut rand_schedule_iter = RandTrainScheduleIter::new(&self.clock);
             let schedules: Vec<TrainSchedule> = self
                 .db
                 .lock()
                 .await
-                .select("train_schedules", |_: &TrainSchedule| true)
+                .select("train_schedules", |s: &TrainSchedule| {
+                    s.network == self.network
+                })
                 .unwrap_or_default()
                 .unwrap_or_default()
                 .into_iter()
@@ -185,6 +251,7 @@ impl TrainGame {
                     if (6000..7000).contains(&s.number) {
                         if let Some(rand_schedule) = rand_schedule_iter.next() {
                             TrainSchedule {
+                                id: s.id,
                                 number: s.number,
                                 name: s.name,
                                 hour: rand_schedule.hour,
@@ -192,6 +259,7 @@ impl TrainGame {
                                 delta: s.delta,
                                 score: s.score,
                                 route: s.route,
+                                network: s.network,
                             }
                         } else {
                             s
@@ -203,9 +271,19 @@ impl TrainGame {
                 .collect();
             let services: Vec<TrainService> = schedules
                 .into_iter()
-                .map(|s| TrainService::new(self.client.clone(), self.db.clone(), s, &[]))
+                .map(|s| {
+                    TrainService::new(
+                        self.network.clone(),
+                        Arc::clone(&self.options),
+                        self.queue.clone(),
+                        self.db.clone(),
+                        self.clock.clone(),
+                        s,
+                        &[],
+                    )
+                })
                 .collect();
-            let now = Utc::now();
+            let now = self.clock.now();
 
             for service in &services {
                 let mut service = service.clone();
@@ -224,22 +302,31 @@ impl TrainGame {
 
 #[derive(Clone)]
 struct TrainService {
-    client: Arc<Mutex<Client>>,
+    network: String,
+    options: Arc<HashMap<String, String>>,
+    queue: MessageQueue,
     db: Arc<Mutex<Database>>,
+    clock: Clock,
     schedule: TrainSchedule,
     passengers: Vec<String>,
 }
 
 impl TrainService {
     fn new(
-        client: Arc<Mutex<Client>>,
+        network: String,
+        options: Arc<HashMap<String, String>>,
+        queue: MessageQueue,
         db: Arc<Mutex<Database>>,
+        clock: Clock,
         schedule: TrainSchedule,
         passengers: &[String],
     ) -> Self {
         Self {
-            client,
+            network,
+            options,
+            queue,
             db,
+            clock,
             schedule,
             passengers: passengers.to_vec(),
         }
@@ -250,9 +337,10 @@ impl TrainService {
             .passengers
             .iter()
             .map(|p| Arrival {
-                datetime: Utc::now(),
+                datetime: self.clock.now(),
                 nick: p.to_lowercase(),
                 number: self.schedule.number,
+                network: self.network.clone(),
             })
             .collect();
 
@@ -269,7 +357,9 @@ impl TrainService {
             .lock()
             .await
             .select("train_boardings", |b: &Boarding| {
-                b.number == number && b.station.to_lowercase() == station
+                b.number == number
+                    && b.station.to_lowercase() == station
+                    && b.network == self.network
             })
             .unwrap_or_default()
             .unwrap_or_default();
@@ -287,7 +377,7 @@ impl TrainService {
             .lock()
             .await
             .delete("train_boardings", |b: &&Boarding| {
-                b.number == self.schedule.number
+                b.number == self.schedule.number && b.network == self.network
             })
         {
             eprintln!("{error}");
@@ -314,25 +404,38 @@ impl TrainService {
         let route = self.schedule.route.clone();
 
         for (index, station) in route.iter().enumerate() {
-            let mut rng = StdRng::from_entropy();
+            let mut rng = self.clock.rng();
             let delay = rng.gen_range(0..=MAX_DELAY);
 
             time::sleep(Duration::from_secs((self.schedule.delta + delay) * 60)).await;
 
             if rng.gen_range(1..=100) <= DERAIL_PROB {
-                if let Err(error) = self.client.lock().await.send(Command::PRIVMSG(
-                    station.to_owned(),
-                    format!(
+                if let Err(error) = self.queue.send(
+                    station,
+                    &format!(
                         "!!! ⚠️ {} {} has derailed before reaching {}! Survivors: {}",
                         self.schedule.number,
                         self.schedule.name,
                         station,
                         self.passengers()
                     ),
-                )) {
+                    Priority::High,
+                ) {
                     eprintln!("{error}");
                 }
 
+                webhook::notify(
+                    "derailment",
+                    &self.network,
+                    json!({
+                        "number": self.schedule.number,
+                        "name": self.schedule.name,
+                        "station": station,
+                        "survivors": self.passengers,
+                    }),
+                    &self.options,
+                    Arc::clone(&self.db),
+                );
                 self.deboard().await;
 
                 return;
@@ -342,47 +445,56 @@ impl TrainService {
                 &self.schedule.number.to_string(),
                 station,
                 &[self.schedule.number.to_string()],
+                &self.network,
                 self.db.clone(),
             )
             .await;
 
-            if let Err(error) = self.client.lock().await.send(Command::PRIVMSG(
-                station.to_owned(),
-                format!(
+            if let Err(error) = self.queue.send(
+                station,
+                &format!(
                     "--> 🚉 {} {} has arrived at {} ({} min delayed). Points: {}. To board: !board {}",
                     self.schedule.number, self.schedule.name, station, delay, self.schedule.score, self.schedule.number
                 ),
-            )) {
+                Priority::High,
+            ) {
                 eprintln!("{error}");
             }
 
             time::sleep(Duration::from_secs(STOP_TIME * 60)).await;
-            deboard(&self.schedule.number.to_string(), self.db.clone()).await;
+            deboard(
+                &self.schedule.number.to_string(),
+                &self.network,
+                self.db.clone(),
+            )
+            .await;
             self.board(self.schedule.number, station).await;
 
             if index != route.len() - 1 {
-                if let Err(error) = self.client.lock().await.send(Command::PRIVMSG(
-                    station.to_owned(),
-                    format!(
+                if let Err(error) = self.queue.send(
+                    station,
+                    &format!(
                         "<-- 🚉 {} {} has departed {}. Passengers: {}",
                         self.schedule.number,
                         self.schedule.name,
                         station,
                         self.passengers()
                     ),
-                )) {
+                    Priority::High,
+                ) {
                     eprintln!("{error}");
                 }
-            } else if let Err(error) = self.client.lock().await.send(Command::PRIVMSG(
-                station.to_owned(),
-                format!(
+            } else if let Err(error) = self.queue.send(
+                station,
+                &format!(
                     "--- 🛑 {} {} has ended. Passengers: {}. Route: {:?}",
                     self.schedule.number,
                     self.schedule.name,
                     self.passengers(),
                     self.schedule.route
                 ),
-            )) {
+                Priority::High,
+            ) {
                 eprintln!("{error}");
             }
         }
@@ -392,7 +504,13 @@ impl TrainService {
     }
 }
 
-pub async fn board(nick: &str, station: &str, args: &[String], db: Arc<Mutex<Database>>) -> String {
+pub async fn board(
+    nick: &str,
+    station: &str,
+    args: &[String],
+    network: &str,
+    db: Arc<Mutex<Database>>,
+) -> String {
     let number = args
         .first()
         .unwrap_or(&String::from(""))
@@ -403,7 +521,7 @@ pub async fn board(nick: &str, station: &str, args: &[String], db: Arc<Mutex<Dat
         .lock()
         .await
         .select("train_boardings", |b: &Boarding| {
-            b.nick.to_lowercase().as_str() == number.to_string()
+            b.nick.to_lowercase().as_str() == number.to_string() && b.network == network
         })
         .is_ok_and(|f| f.is_some() || f.is_none() && nick == number.to_string())
     {
@@ -411,7 +529,7 @@ pub async fn board(nick: &str, station: &str, args: &[String], db: Arc<Mutex<Dat
     }
 
     if let Ok(Some(boardings)) = db.lock().await.select("train_boardings", |b: &Boarding| {
-        b.nick.to_lowercase() == nick.to_lowercase()
+        b.nick.to_lowercase() == nick.to_lowercase() && b.network == network
     }) {
         if let Some(boarding) = boardings.first() {
             return format!(
@@ -425,31 +543,32 @@ pub async fn board(nick: &str, station: &str, args: &[String], db: Arc<Mutex<Dat
         nick: nick.to_lowercase(),
         number,
         station: station.to_lowercase(),
+        network: String::from(network),
     };
 
     db.lock()
         .await
         .update("train_boardings", boarding, |b: &&Boarding| {
-            b.nick.to_lowercase() == nick.to_lowercase()
+            b.nick.to_lowercase() == nick.to_lowercase() && b.network == network
         })
         .unwrap_or_default();
 
     format!("You boarded train {}.", number)
 }
 
-pub async fn deboard(nick: &str, db: Arc<Mutex<Database>>) {
+pub async fn deboard(nick: &str, network: &str, db: Arc<Mutex<Database>>) {
     if let Err(error) = db.lock().await.delete("train_boardings", |b: &&Boarding| {
-        b.nick.to_lowercase() == nick.to_lowercase()
+        b.nick.to_lowercase() == nick.to_lowercase() && b.network == network
     }) {
         eprintln!("{error}");
     }
 }
 
-pub async fn schedules(db: Arc<Mutex<Database>>) -> String {
+pub async fn schedules(network: &str, db: Arc<Mutex<Database>>) -> String {
     let schedules = db
         .lock()
         .await
-        .select("train_schedules", |_: &TrainSchedule| true)
+        .select("train_schedules", |s: &TrainSchedule| s.network == network)
         .unwrap_or_default()
         .unwrap_or_default();
 
@@ -468,12 +587,12 @@ pub async fn schedules(db: Arc<Mutex<Database>>) -> String {
         .join(" | ")
 }
 
-pub async fn scores(db: Arc<Mutex<Database>>) -> HashMap<usize, u64> {
+pub async fn scores(network: &str, db: Arc<Mutex<Database>>) -> HashMap<usize, u64> {
     let mut scores: HashMap<usize, u64> = HashMap::new();
     match db
         .lock()
         .await
-        .select("train_schedules", |_: &TrainSchedule| true)
+        .select("train_schedules", |s: &TrainSchedule| s.network == network)
     {
         Ok(Some(schedules)) => {
             for schedule in schedules {
@@ -486,9 +605,13 @@ pub async fn scores(db: Arc<Mutex<Database>>) -> HashMap<usize, u64> {
     }
 }
 
-pub async fn points(db: Arc<Mutex<Database>>) -> String {
-    let scores = scores(db.clone()).await;
-    let arrivals = match db.lock().await.select("train_arrivals", |_: &Arrival| true) {
+pub async fn points(network: &str, db: Arc<Mutex<Database>>) -> String {
+    let scores = scores(network, db.clone()).await;
+    let arrivals = match db
+        .lock()
+        .await
+        .select("train_arrivals", |a: &Arrival| a.network == network)
+    {
         Ok(Some(arrivals)) => arrivals,
         Ok(None) => return String::from("There are no arrivals."),
         Err(_) => return String::from("Could not read arrivals."),
@@ -0,0 +1,73 @@
+pub mod console;
+pub mod irc;
+pub mod matrix;
+
+use crate::network::Network;
+use crate::status::ChannelStatus;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IncomingKind {
+    Message,
+    Join,
+    Part,
+    Quit,
+    Nick,
+}
+
+// A message addressed to the bot or to one of its channels, or a user joining, leaving or
+// changing nick. Quits and nick changes have no target, the text is the reason of a part or quit
+// and the new nick of a nick change.
+#[derive(Clone)]
+pub struct Incoming {
+    pub kind: IncomingKind,
+    pub nick: Option<String>,
+    pub target: String,
+    pub text: String,
+}
+
+// A chat service the bot can connect to. Commands and games only talk to the chat through a
+// transport, so the same logic serves IRC channels and Matrix rooms.
+#[rocket::async_trait]
+pub trait Transport: Send + Sync {
+    // Name of the bot on this transport.
+    async fn nick(&self) -> String;
+
+    async fn channels(&self) -> Vec<ChannelStatus>;
+
+    async fn send(&self, target: &str, text: &str) -> Result<(), String>;
+
+    async fn notice(&self, target: &str, text: &str) -> Result<(), String>;
+
+    // Sends the text as an action, like /me on IRC.
+    async fn action(&self, target: &str, text: &str) -> Result<(), String>;
+
+    async fn join(&self, channel: &str) -> Result<(), String>;
+
+    async fn part(&self, channel: &str) -> Result<(), String>;
+
+    // Whether nick is an operator of the channel, or a moderator of the room.
+    async fn is_op(&self, channel: &str, nick: &str) -> bool;
+
+    // Waits for the next message or event, returning None once the connection is lost.
+    async fn receive(&self) -> Option<Incoming>;
+}
+
+// Connects to a network with the transport set by its transport option, IRC by default.
+pub async fn connect(network: &Network) -> Result<Arc<dyn Transport>, String> {
+    match network
+        .config
+        .options
+        .get("transport")
+        .map(|t| t.to_lowercase())
+    {
+        None => Ok(Arc::new(irc::IrcTransport::connect(&network.config).await?)),
+        Some(transport) => match transport.as_str() {
+            "irc" => Ok(Arc::new(irc::IrcTransport::connect(&network.config).await?)),
+            "matrix" => Ok(Arc::new(
+                matrix::MatrixTransport::connect(&network.config).await?,
+            )),
+            _ => Err(format!("Unknown transport ({transport}).")),
+        },
+    }
+}
@@ -0,0 +1,98 @@
+use super::{Incoming, IncomingKind, Transport};
+use crate::status::ChannelStatus;
+use tokio::io::{self, AsyncBufReadExt, BufReader, Lines, Stdin};
+use tokio::sync::Mutex;
+
+// Reads messages from stdin as a single user of a single channel and prints what the bot sends,
+// so that commands can be tried without connecting to a server.
+pub struct ConsoleTransport {
+    bot_nick: String,
+    nick: String,
+    channel: String,
+    lines: Mutex<Lines<BufReader<Stdin>>>,
+}
+
+impl ConsoleTransport {
+    pub fn new(bot_nick: &str, nick: &str, channel: &str) -> Self {
+        Self {
+            bot_nick: String::from(bot_nick),
+            nick: String::from(nick),
+            channel: String::from(channel),
+            lines: Mutex::new(BufReader::new(io::stdin()).lines()),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Transport for ConsoleTransport {
+    async fn nick(&self) -> String {
+        self.bot_nick.clone()
+    }
+
+    // The channel is shared by the bot and the console user.
+    async fn channels(&self) -> Vec<ChannelStatus> {
+        vec![ChannelStatus {
+            name: self.channel.clone(),
+            users: 2,
+        }]
+    }
+
+    async fn send(&self, target: &str, text: &str) -> Result<(), String> {
+        println!("{} <{}> {}", target, self.bot_nick, text);
+
+        Ok(())
+    }
+
+    async fn notice(&self, target: &str, text: &str) -> Result<(), String> {
+        println!("{} -{}- {}", target, self.bot_nick, text);
+
+        Ok(())
+    }
+
+    async fn action(&self, target: &str, text: &str) -> Result<(), String> {
+        println!("{} * {} {}", target, self.bot_nick, text);
+
+        Ok(())
+    }
+
+    async fn join(&self, channel: &str) -> Result<(), String> {
+        println!("{} joined {}", self.bot_nick, channel);
+
+        Ok(())
+    }
+
+    async fn part(&self, channel: &str) -> Result<(), String> {
+        println!("{} left {}", self.bot_nick, channel);
+
+        Ok(())
+    }
+
+    // The console user runs the channel.
+    async fn is_op(&self, channel: &str, nick: &str) -> bool {
+        channel == self.channel && nick == self.nick
+    }
+
+    async fn receive(&self) -> Option<Incoming> {
+        let mut lines = self.lines.lock().await;
+
+        loop {
+            match lines.next_line().await {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => {
+                    return Some(Incoming {
+                        kind: IncomingKind::Message,
+                        nick: Some(self.nick.clone()),
+                        target: self.channel.clone(),
+                        text: line,
+                    })
+                }
+                Ok(None) => return None,
+                Err(error) => {
+                    eprintln!("{error}");
+
+                    return None;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use super::{Incoming, IncomingKind, Transport};
+use crate::network;
+use crate::status::ChannelStatus;
+use futures::prelude::*;
+use irc::client::data::AccessLevel;
+use irc::client::prelude::*;
+use irc::client::ClientStream;
+use tokio::sync::Mutex;
+
+pub struct IrcTransport {
+    client: Client,
+    stream: Mutex<ClientStream>,
+}
+
+impl IrcTransport {
+    // Connects the IRC client to the IRC server and identifies the bot.
+    pub async fn connect(config: &Config) -> Result<Self, String> {
+        let mut client = network::connect(config).await?;
+        let stream = match client.stream() {
+            Ok(stream) => stream,
+            Err(error) => return Err(error.to_string()),
+        };
+
+        if let Err(error) = client.identify() {
+            eprintln!("{error}");
+        }
+
+        Ok(Self {
+            client,
+            stream: Mutex::new(stream),
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl Transport for IrcTransport {
+    async fn nick(&self) -> String {
+        String::from(self.client.current_nickname())
+    }
+
+    async fn channels(&self) -> Vec<ChannelStatus> {
+        self.client
+            .list_channels()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| ChannelStatus {
+                users: self.client.list_users(&name).unwrap_or_default().len(),
+                name,
+            })
+            .collect()
+    }
+
+    async fn send(&self, target: &str, text: &str) -> Result<(), String> {
+        match self.client.send_privmsg(target, text) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    async fn notice(&self, target: &str, text: &str) -> Result<(), String> {
+        match self.client.send_notice(target, text) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    async fn action(&self, target: &str, text: &str) -> Result<(), String> {
+        match self.client.send_action(target, text) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    async fn join(&self, channel: &str) -> Result<(), String> {
+        match self.client.send_join(channel) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    async fn part(&self, channel: &str) -> Result<(), String> {
+        match self.client.send_part(channel) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    // Owners, admins and operators of the channel count, half-operators don't.
+    async fn is_op(&self, channel: &str, nick: &str) -> bool {
+        self.client
+            .list_users(channel)
+            .unwrap_or_default()
+            .iter()
+            .filter(|u| u.get_nickname().eq_ignore_ascii_case(nick))
+            .flat_map(|u| u.access_levels())
+            .any(|l| {
+                matches!(
+                    l,
+                    AccessLevel::Owner | AccessLevel::Admin | AccessLevel::Oper
+                )
+            })
+    }
+
+    // Only PRIVMSG, JOIN, PART, QUIT and NICK are of interest to the bot, any other message is
+    // skipped.
+    async fn receive(&self) -> Option<Incoming> {
+        let mut stream = self.stream.lock().await;
+
+        while let Ok(Some(message)) = stream.next().await.transpose() {
+            let nick = match message.prefix {
+                Some(Prefix::Nickname(nick, _, _)) => Some(nick),
+                Some(Prefix::ServerName(_)) => None,
+                None => None,
+            };
+
+            let (kind, target, text) = match message.command {
+                Command::PRIVMSG(target, text) => (IncomingKind::Message, target, text),
+                Command::JOIN(channels, _, _) => (IncomingKind::Join, channels, String::new()),
+                Command::PART(channels, reason) => {
+                    (IncomingKind::Part, channels, reason.unwrap_or_default())
+                }
+                Command::QUIT(reason) => (
+                    IncomingKind::Quit,
+                    String::new(),
+                    reason.unwrap_or_default(),
+                ),
+                Command::NICK(new_nick) => (IncomingKind::Nick, String::new(), new_nick),
+                _ => continue,
+            };
+
+            return Some(Incoming {
+                kind,
+                nick,
+                target,
+                text,
+            });
+        }
+
+        None
+    }
+}
@@ -0,0 +1,362 @@
+use super::{Incoming, IncomingKind, Transport};
+use crate::status::ChannelStatus;
+use irc::client::prelude::Config;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tokio::time::Duration;
+use url::form_urlencoded;
+
+// The power level of moderators, who are the operators of a room.
+const MODERATOR_LEVEL: i64 = 50;
+const SYNC_TIMEOUT: u64 = 30000;
+const TIMEOUT: u64 = 60;
+
+#[derive(Deserialize)]
+struct Content {
+    msgtype: Option<String>,
+    body: Option<String>,
+    membership: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RoomEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: String,
+    content: Content,
+}
+
+#[derive(Default, Deserialize)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<RoomEvent>,
+}
+
+#[derive(Default, Deserialize)]
+struct Summary {
+    #[serde(rename = "m.joined_member_count")]
+    joined_member_count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct JoinedRoom {
+    #[serde(default)]
+    timeline: Timeline,
+    #[serde(default)]
+    summary: Summary,
+}
+
+#[derive(Default, Deserialize)]
+struct Rooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Deserialize)]
+struct PowerLevels {
+    #[serde(default)]
+    users: HashMap<String, i64>,
+}
+
+#[derive(Deserialize)]
+struct SyncResponse {
+    next_batch: String,
+    #[serde(default)]
+    rooms: Rooms,
+}
+
+// Talks to a Matrix homeserver through the client-server API. Rooms take the place of channels
+// and the localpart of each user ID takes the place of nicks.
+pub struct MatrixTransport {
+    client: Client,
+    homeserver: String,
+    user_id: String,
+    access_token: String,
+    since: Mutex<Option<String>>,
+    pending: Mutex<VecDeque<Incoming>>,
+    rooms: Mutex<BTreeMap<String, usize>>,
+    transaction: AtomicU64,
+}
+
+impl MatrixTransport {
+    // Joins the rooms in the channels setting and skips any history from before the connection.
+    pub async fn connect(config: &Config) -> Result<Self, String> {
+        let homeserver = match config.options.get("matrix_homeserver") {
+            Some(homeserver) => homeserver.trim_end_matches('/').to_string(),
+            None => return Err(String::from("Could not find matrix_homeserver option.")),
+        };
+        let user_id = match config.options.get("matrix_user_id") {
+            Some(user_id) => user_id.clone(),
+            None => return Err(String::from("Could not find matrix_user_id option.")),
+        };
+        let access_token = match config.options.get("matrix_access_token") {
+            Some(access_token) => access_token.clone(),
+            None => return Err(String::from("Could not find matrix_access_token option.")),
+        };
+        let client = match Client::builder()
+            .timeout(Duration::from_secs(TIMEOUT))
+            .build()
+        {
+            Ok(client) => client,
+            Err(error) => return Err(error.to_string()),
+        };
+        let transport = Self {
+            client,
+            homeserver,
+            user_id,
+            access_token,
+            since: Mutex::new(None),
+            pending: Mutex::new(VecDeque::new()),
+            rooms: Mutex::new(BTreeMap::new()),
+            transaction: AtomicU64::new(0),
+        };
+
+        for room in &config.channels {
+            if let Err(error) = transport.join_room(room).await {
+                eprintln!("Could not join {room}. Cause: {error}.");
+            }
+        }
+
+        transport.sync(0).await?;
+        transport.pending.lock().await.clear();
+
+        Ok(transport)
+    }
+
+    // Sends a message of the given msgtype (m.text, m.notice or m.emote) to a room.
+    async fn send_message(&self, target: &str, msgtype: &str, text: &str) -> Result<(), String> {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let transaction = format!(
+            "{}.{}",
+            started,
+            self.transaction.fetch_add(1, Ordering::SeqCst)
+        );
+        let response = self
+            .client
+            .put(self.url(&format!(
+                "/rooms/{}/send/m.room.message/{}",
+                encode(target),
+                transaction
+            )))
+            .bearer_auth(&self.access_token)
+            .json(&json!({"msgtype": msgtype, "body": text}))
+            .send()
+            .await;
+
+        match response.and_then(|r| r.error_for_status()) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/_matrix/client/v3{}", self.homeserver, path)
+    }
+
+    async fn join_room(&self, room: &str) -> Result<(), String> {
+        let response = self
+            .client
+            .post(self.url(&format!("/join/{}", encode(room))))
+            .bearer_auth(&self.access_token)
+            .json(&json!({}))
+            .send()
+            .await;
+
+        match response.and_then(|r| r.error_for_status()) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    // Fetches the events since the last sync, queueing the messages sent by others.
+    async fn sync(&self, timeout: u64) -> Result<(), String> {
+        let mut since = self.since.lock().await;
+        let mut query = vec![("timeout", timeout.to_string())];
+
+        if let Some(since) = since.as_ref() {
+            query.push(("since", since.clone()));
+        }
+
+        let response = self
+            .client
+            .get(self.url("/sync"))
+            .bearer_auth(&self.access_token)
+            .query(&query)
+            .send()
+            .await;
+        let sync: SyncResponse = match response.and_then(|r| r.error_for_status()) {
+            Ok(response) => match response.json().await {
+                Ok(sync) => sync,
+                Err(error) => return Err(error.to_string()),
+            },
+            Err(error) => return Err(error.to_string()),
+        };
+        let mut pending = self.pending.lock().await;
+        let mut rooms = self.rooms.lock().await;
+
+        for (room_id, room) in sync.rooms.join {
+            for event in room.timeline.events {
+                if event.sender == self.user_id {
+                    continue;
+                }
+
+                // Members joining or leaving a room are the joins and parts of IRC.
+                if event.kind == "m.room.member" {
+                    let kind = match event.content.membership.as_deref() {
+                        Some("join") => IncomingKind::Join,
+                        Some("leave") => IncomingKind::Part,
+                        _ => continue,
+                    };
+
+                    pending.push_back(Incoming {
+                        kind,
+                        nick: Some(localpart(&event.sender)),
+                        target: room_id.clone(),
+                        text: String::new(),
+                    });
+
+                    continue;
+                }
+
+                if event.kind != "m.room.message"
+                    || event.content.msgtype.as_deref() != Some("m.text")
+                {
+                    continue;
+                }
+
+                if let Some(body) = event.content.body {
+                    pending.push_back(Incoming {
+                        kind: IncomingKind::Message,
+                        nick: Some(localpart(&event.sender)),
+                        target: room_id.clone(),
+                        text: body,
+                    });
+                }
+            }
+
+            let users = rooms.entry(room_id).or_default();
+
+            if let Some(joined_member_count) = room.summary.joined_member_count {
+                *users = joined_member_count;
+            }
+        }
+
+        *since = Some(sync.next_batch);
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl Transport for MatrixTransport {
+    async fn nick(&self) -> String {
+        localpart(&self.user_id)
+    }
+
+    async fn channels(&self) -> Vec<ChannelStatus> {
+        self.rooms
+            .lock()
+            .await
+            .iter()
+            .map(|(name, users)| ChannelStatus {
+                name: name.clone(),
+                users: *users,
+            })
+            .collect()
+    }
+
+    async fn send(&self, target: &str, text: &str) -> Result<(), String> {
+        self.send_message(target, "m.text", text).await
+    }
+
+    async fn notice(&self, target: &str, text: &str) -> Result<(), String> {
+        self.send_message(target, "m.notice", text).await
+    }
+
+    async fn action(&self, target: &str, text: &str) -> Result<(), String> {
+        self.send_message(target, "m.emote", text).await
+    }
+
+    async fn join(&self, channel: &str) -> Result<(), String> {
+        self.join_room(channel).await
+    }
+
+    // Rooms can only be left by their ID, not by an alias.
+    async fn part(&self, channel: &str) -> Result<(), String> {
+        let response = self
+            .client
+            .post(self.url(&format!("/rooms/{}/leave", encode(channel))))
+            .bearer_auth(&self.access_token)
+            .json(&json!({}))
+            .send()
+            .await;
+
+        match response.and_then(|r| r.error_for_status()) {
+            Ok(_) => {
+                self.rooms.lock().await.remove(channel);
+
+                Ok(())
+            }
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    async fn is_op(&self, channel: &str, nick: &str) -> bool {
+        let response = self
+            .client
+            .get(self.url(&format!(
+                "/rooms/{}/state/m.room.power_levels",
+                encode(channel)
+            )))
+            .bearer_auth(&self.access_token)
+            .send()
+            .await;
+        let power_levels: PowerLevels = match response.and_then(|r| r.error_for_status()) {
+            Ok(response) => match response.json().await {
+                Ok(power_levels) => power_levels,
+                Err(_) => return false,
+            },
+            Err(_) => return false,
+        };
+
+        power_levels.users.iter().any(|(user_id, level)| {
+            localpart(user_id).eq_ignore_ascii_case(nick) && *level >= MODERATOR_LEVEL
+        })
+    }
+
+    async fn receive(&self) -> Option<Incoming> {
+        loop {
+            if let Some(message) = self.pending.lock().await.pop_front() {
+                return Some(message);
+            }
+
+            if let Err(error) = self.sync(SYNC_TIMEOUT).await {
+                eprintln!("{error}");
+
+                return None;
+            }
+        }
+    }
+}
+
+fn encode(text: &str) -> String {
+    form_urlencoded::byte_serialize(text.as_bytes()).collect()
+}
+
+// The localpart of @alice:example.org is alice.
+fn localpart(user_id: &str) -> String {
+    user_id
+        .trim_start_matches('@')
+        .split(':')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
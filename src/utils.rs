@@ -3,11 +3,11 @@ use regex::Regex;
 use reqwest::{header::USER_AGENT, Client};
 use scraper::{Html, Selector};
 use serde::Deserialize;
-use std::{cmp, error::Error};
+use std::error::Error;
 use tokio::time::Duration;
 
 const TIMEOUT: u64 = 10;
-const YOUTUBE_API_BASE: &str = "https://www.googleapis.com/youtube/v3";
+pub const YOUTUBE_API_BASE: &str = "https://www.googleapis.com/youtube/v3";
 const USER_AGENT_STRING: &str =
     "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/111.0";
 
@@ -85,10 +85,14 @@ pub fn upper_initials(text: &str) -> String {
         .join(" ")
 }
 
-pub async fn youtube_data(api_key: &str, video_id: &str) -> Result<Option<String>, Box<dyn Error>> {
+pub async fn youtube_data(
+    api_url: &str,
+    api_key: &str,
+    video_id: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
     let url = format!(
         "{}/videos?part=snippet&part=contentDetails&part=statistics&id={}&key={}",
-        YOUTUBE_API_BASE, video_id, api_key
+        api_url, video_id, api_key
     );
     let client = Client::builder()
         .timeout(Duration::from_secs(TIMEOUT))
@@ -165,27 +169,56 @@ pub fn extract_video_id(url: &str) -> Option<String> {
     }
 }
 
+// Splits a message into lines no longer than max_chunk_size bytes. Existing line breaks are kept,
+// long lines are broken at the last whitespace that fits (or at a character boundary if a single
+// word doesn't fit) and empty lines are dropped, since IRC can't send them.
 pub fn split_message(message: &str, max_chunk_size: usize) -> Vec<&str> {
-    let message_len = message.len();
-    let num_chunks = message_len.div_ceil(max_chunk_size);
     let mut chunks = Vec::new();
 
-    let all_chunks_have_newline = (0..num_chunks).all(|chunk| {
-        let start = chunk * max_chunk_size;
-        let end = cmp::min(start + max_chunk_size, message_len);
-        message[start..end].contains('\n')
-    });
+    // A lone CR ends a line for IRC servers as well, so it never makes it into a chunk.
+    for line in message.split(['\r', '\n']) {
+        let mut rest = line.trim_end();
 
-    if all_chunks_have_newline {
-        return message.lines().collect();
-    }
+        while rest.len() > max_chunk_size {
+            let mut end = max_chunk_size;
+
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
 
-    for chunk in 0..num_chunks {
-        let start = chunk * max_chunk_size;
-        let end = cmp::min((chunk * max_chunk_size) + max_chunk_size, message_len);
+            if end == 0 {
+                end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+            }
+
+            let split = match rest[..end].rfind(char::is_whitespace) {
+                Some(index) if index > 0 => index,
+                _ => end,
+            };
+            let chunk = rest[..split].trim_end();
+
+            if !chunk.is_empty() {
+                chunks.push(chunk);
+            }
 
-        chunks.push(&message[start..end]);
+            rest = rest[split..].trim_start();
+        }
+
+        if !rest.is_empty() {
+            chunks.push(rest);
+        }
     }
 
     chunks
 }
+
+// Time elapsed from datetime to now, as shown by the commands about past messages.
+pub fn format_ago(datetime: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let duration = now.signed_duration_since(datetime);
+
+    format!(
+        "{}d {}h {}m ago",
+        duration.num_days(),
+        duration.num_hours() % 24,
+        duration.num_minutes() % 60
+    )
+}
@@ -0,0 +1,199 @@
+use crate::database::{CsvRecord, Database};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task;
+use tokio::time::{sleep, Duration};
+
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_RETRY_DELAY: u64 = 5;
+const TIMEOUT: u64 = 10;
+
+// The JSON body posted to every webhook.
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: &'a str,
+    network: &'a str,
+    time: String,
+    data: Value,
+}
+
+// A delivery that still failed after every retry, kept for inspection or manual replay.
+#[derive(PartialEq)]
+pub struct DeadLetter {
+    pub url: String,
+    pub event: String,
+    pub payload: String,
+    pub error: String,
+    pub datetime: DateTime<Utc>,
+}
+
+impl CsvRecord for DeadLetter {
+    fn from_fields(fields: &[String]) -> Self {
+        Self {
+            url: fields[0].clone(),
+            event: fields[1].clone(),
+            payload: fields[2].clone(),
+            error: fields[3].clone(),
+            datetime: fields[4].parse().unwrap_or_default(),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.url.clone(),
+            self.event.clone(),
+            self.payload.clone(),
+            self.error.clone(),
+            self.datetime.to_string(),
+        ]
+    }
+}
+
+// Hex encoded HMAC-SHA256 of the body, sent as X-Gluon-Signature: sha256=<signature>.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return String::new(),
+    };
+
+    mac.update(body.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Posts event to every URL in webhook_urls, unless webhook_events leaves it out. Deliveries run
+// in the background and the ones that keep failing end up in the webhook_dead_letters table.
+pub fn notify(
+    event: &str,
+    network: &str,
+    data: Value,
+    options: &HashMap<String, String>,
+    db: Arc<Mutex<Database>>,
+) {
+    let urls: Vec<String> = match options.get("webhook_urls") {
+        Some(urls) => urls
+            .split(',')
+            .map(|url| url.trim())
+            .filter(|url| !url.is_empty())
+            .map(String::from)
+            .collect(),
+        None => return,
+    };
+
+    if let Some(events) = options.get("webhook_events") {
+        if !events
+            .split(',')
+            .any(|e| e.trim().eq_ignore_ascii_case(event))
+        {
+            return;
+        }
+    }
+
+    let body = match serde_json::to_string(&Payload {
+        event,
+        network,
+        time: Utc::now().to_rfc3339(),
+        data,
+    }) {
+        Ok(body) => body,
+        Err(error) => {
+            eprintln!("{error}");
+
+            return;
+        }
+    };
+    let signature = options
+        .get("webhook_secret")
+        .map(|secret| format!("sha256={}", sign(secret, &body)));
+    let retries = match options.get("webhook_retries") {
+        Some(retries) => retries.parse().unwrap_or(DEFAULT_RETRIES),
+        None => DEFAULT_RETRIES,
+    };
+    let retry_delay = match options.get("webhook_retry_delay") {
+        Some(retry_delay) => retry_delay.parse().unwrap_or(DEFAULT_RETRY_DELAY),
+        None => DEFAULT_RETRY_DELAY,
+    };
+
+    for url in urls {
+        task::spawn(deliver(
+            url,
+            String::from(event),
+            body.clone(),
+            signature.clone(),
+            retries,
+            retry_delay,
+            Arc::clone(&db),
+        ));
+    }
+}
+
+// Tries the delivery once and then up to retries more times, doubling the delay each time.
+async fn deliver(
+    url: String,
+    event: String,
+    body: String,
+    signature: Option<String>,
+    retries: u32,
+    retry_delay: u64,
+    db: Arc<Mutex<Database>>,
+) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(TIMEOUT))
+        .build()
+    {
+        Ok(client) => client,
+        Err(error) => {
+            eprintln!("{error}");
+
+            return;
+        }
+    };
+    let mut error = String::new();
+
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            sleep(Duration::from_secs(
+                retry_delay.saturating_mul(1 << (attempt - 1).min(16)),
+            ))
+            .await;
+        }
+
+        let mut request = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("User-Agent", "gluon_bot")
+            .header("X-Gluon-Event", &event)
+            .body(body.clone());
+
+        if let Some(signature) = &signature {
+            request = request.header("X-Gluon-Signature", signature);
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => error = format!("HTTP {}", response.status()),
+            Err(request_error) => error = request_error.to_string(),
+        }
+    }
+
+    eprintln!("Could not deliver {event} to {url}: {error}");
+
+    if let Err(error) = db.lock().await.insert(
+        "webhook_dead_letters",
+        DeadLetter {
+            url,
+            event,
+            payload: body,
+            error,
+            datetime: Utc::now(),
+        },
+    ) {
+        eprintln!("{error}");
+    }
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::{rocket_client, TestDir};
+use gluon_bot::api::{self, keys};
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::Client;
+use serde_json::Value;
+
+async fn client(dir: &TestDir) -> Client {
+    let rocket = rocket::build()
+        .mount("/api", rocket::routes![api::audit, api::status])
+        .mount(
+            "/",
+            rocket::routes![api::admin::index, api::admin::script, api::admin::style],
+        );
+
+    rocket_client(dir, rocket).await.0
+}
+
+#[tokio::test]
+async fn the_dashboard_is_served() {
+    let dir = TestDir::new(&[]);
+    let client = client(&dir).await;
+
+    for (path, content_type, text) in [
+        ("/admin", ContentType::HTML, "/admin/admin.js"),
+        (
+            "/admin/admin.js",
+            ContentType::JavaScript,
+            "/api/openapi.json",
+        ),
+        ("/admin/admin.css", ContentType::CSS, "form.record"),
+    ] {
+        let response = client.get(path).dispatch().await;
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(content_type));
+        assert!(response
+            .into_string()
+            .await
+            .unwrap_or_default()
+            .contains(text));
+    }
+}
+
+#[tokio::test]
+async fn the_audit_log_is_shown_to_admin_keys_newest_first() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let client = client(&dir).await;
+    let admin_key = keys::create("admin", &[String::from("admin")], &[], None, &db)
+        .await
+        .expect("Could not create key.");
+    let say_key = keys::create("say", &[String::from("say")], &[], None, &db)
+        .await
+        .expect("Could not create key.");
+
+    assert_eq!(
+        client
+            .get("/api/audit")
+            .header(Header::new("x-api-key", say_key))
+            .dispatch()
+            .await
+            .status(),
+        Status::Forbidden
+    );
+
+    let response = client
+        .get("/api/audit?limit=1")
+        .header(Header::new("x-api-key", admin_key))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("X-Total-Count"), Some("2"));
+
+    let entries: Value = serde_json::from_str(&response.into_string().await.unwrap_or_default())
+        .expect("Invalid JSON body.");
+
+    assert_eq!(entries.as_array().map(|e| e.len()), Some(1));
+    assert_eq!(entries[0]["key"], "admin");
+    assert_eq!(entries[0]["outcome"], "allowed");
+}
+
+#[tokio::test]
+async fn the_status_is_shown_to_admin_keys_only() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let client = client(&dir).await;
+    let admin_key = keys::create("admin", &[String::from("admin")], &[], None, &db)
+        .await
+        .expect("Could not create key.");
+    let say_key = keys::create("say", &[String::from("say")], &[], None, &db)
+        .await
+        .expect("Could not create key.");
+
+    assert_eq!(
+        client.get("/api/status").dispatch().await.status(),
+        Status::Unauthorized
+    );
+    assert_eq!(
+        client
+            .get("/api/status")
+            .header(Header::new("x-api-key", say_key))
+            .dispatch()
+            .await
+            .status(),
+        Status::Forbidden
+    );
+
+    let response = client
+        .get("/api/status")
+        .header(Header::new("x-api-key", admin_key))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let reports: Value = serde_json::from_str(&response.into_string().await.unwrap_or_default())
+        .expect("Invalid JSON body.");
+
+    assert_eq!(reports.as_array().map(|r| r.len()), Some(1));
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use common::{rocket_client, TestDir};
+use gluon_bot::api::{self, keys};
+use gluon_bot::queue::QueueReceiver;
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::Client;
+use std::fs;
+
+async fn client(dir: &TestDir) -> (Client, QueueReceiver) {
+    let rocket = rocket::build().mount("/api", rocket::routes![api::say, api::score_f1_bets]);
+
+    rocket_client(dir, rocket).await
+}
+
+fn scopes(scopes: &str) -> Vec<String> {
+    scopes.split_whitespace().map(String::from).collect()
+}
+
+async fn say(client: &Client, key: Option<&str>, channel: &str) -> (Status, String) {
+    let mut request = client
+        .post("/api/say")
+        .header(ContentType::JSON)
+        .body(format!(r#"{{"channel": "{channel}", "body": "Hello"}}"#));
+
+    if let Some(key) = key {
+        request = request.header(Header::new("x-api-key", String::from(key)));
+    }
+
+    let response = request.dispatch().await;
+
+    (
+        response.status(),
+        response.into_string().await.unwrap_or_default(),
+    )
+}
+
+#[tokio::test]
+async fn keys_are_stored_hashed() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let key = keys::create("deploy", &scopes("say"), &[], None, &db)
+        .await
+        .expect("Could not create key.");
+
+    assert!(key.starts_with("gluon_"));
+    assert!(!dir.read("api_keys").contains(&key));
+    assert!(dir.read("api_keys").contains(&keys::hash(&key)));
+    assert_eq!(
+        keys::create("deploy", &scopes("say"), &[], None, &db).await,
+        Err(String::from("Key deploy already exists."))
+    );
+    assert!(keys::create("other", &scopes("write"), &[], None, &db)
+        .await
+        .is_err_and(|e| e.starts_with("Invalid scope write.")));
+}
+
+#[tokio::test]
+async fn routes_need_a_key_with_their_scope() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let (client, mut receiver) = client(&dir).await;
+    let say_key = keys::create("say", &scopes("say"), &[], None, &db)
+        .await
+        .expect("Could not create key.");
+    let bets_key = keys::create("bets", &scopes("bets:read"), &[], None, &db)
+        .await
+        .expect("Could not create key.");
+    let admin_key = keys::create("admin", &scopes("admin"), &[], None, &db)
+        .await
+        .expect("Could not create key.");
+
+    assert_eq!(say(&client, None, "#test").await.0, Status::Unauthorized);
+    assert_eq!(
+        say(&client, Some("gluon_wrong"), "#test").await.0,
+        Status::Unauthorized
+    );
+    assert_eq!(
+        say(&client, Some(&bets_key), "#test").await.0,
+        Status::Forbidden
+    );
+    assert_eq!(
+        say(&client, Some(&say_key), "#test").await,
+        (Status::Ok, String::from("Success"))
+    );
+    assert_eq!(
+        say(&client, Some(&admin_key), "#test").await,
+        (Status::Ok, String::from("Success"))
+    );
+    assert!(receiver.try_next("gluon", 410).is_some());
+    assert!(receiver.try_next("gluon", 410).is_some());
+    assert_eq!(
+        client
+            .get("/api/f1bets/scores")
+            .header(Header::new("x-api-key", bets_key))
+            .dispatch()
+            .await
+            .status(),
+        Status::Ok
+    );
+    assert_eq!(
+        client.get("/api/f1bets/scores").dispatch().await.status(),
+        Status::Unauthorized
+    );
+
+    let audit = dir.read("api_audit");
+
+    assert!(audit.contains(",say,POST /api/say,"));
+    assert!(audit.contains(",bets,POST /api/say,,denied scope"));
+    assert!(audit.contains(",,POST /api/say,,denied invalid key"));
+}
+
+#[tokio::test]
+async fn keys_can_be_restricted_expired_and_revoked() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let (client, _receiver) = client(&dir).await;
+    let channel_key = keys::create("channel", &scopes("say"), &scopes("#test"), None, &db)
+        .await
+        .expect("Could not create key.");
+    let expired_key = keys::create(
+        "expired",
+        &scopes("say"),
+        &[],
+        keys::parse_expiry("2020-01-01"),
+        &db,
+    )
+    .await
+    .expect("Could not create key.");
+
+    assert_eq!(
+        say(&client, Some(&channel_key), "#TEST").await,
+        (Status::Ok, String::from("Success"))
+    );
+    assert_eq!(
+        say(&client, Some(&channel_key), "#other").await,
+        (Status::Ok, String::from("Failure"))
+    );
+    assert!(dir
+        .read("api_audit")
+        .contains(",channel,POST /api/say,,denied channel #other"));
+    assert_eq!(
+        say(&client, Some(&expired_key), "#test").await.0,
+        Status::Unauthorized
+    );
+
+    keys::revoke("channel", &db)
+        .await
+        .expect("Could not revoke key.");
+
+    assert_eq!(
+        say(&client, Some(&channel_key), "#test").await.0,
+        Status::Unauthorized
+    );
+    assert_eq!(
+        keys::revoke("channel", &db).await,
+        Err(String::from("Could not find key channel."))
+    );
+}
+
+#[tokio::test]
+async fn plaintext_keys_are_imported() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let path = dir.path().join("api_keys.txt");
+    let legacy_key = "0123456789abcdef0123456789abcdef";
+
+    fs::write(&path, format!("short\n{legacy_key}\n")).expect("Could not write api_keys.txt.");
+    keys::import_legacy(&path.display().to_string(), &db).await;
+
+    let imported = keys::list(&db).await;
+
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].name, "legacy2");
+    assert!(imported[0].allows("quotes:write"));
+    assert!(!path.exists());
+    assert!(keys::find(legacy_key, &db).await.is_some());
+}
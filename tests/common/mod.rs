@@ -0,0 +1,457 @@
+// Helpers shared by the integration tests: a fake IRC server the bot connects to, a local HTTP
+// server standing in for the external APIs, a bot running against both and local clients of the
+// API.
+#![allow(dead_code)]
+
+use gluon_bot::activity::Activity;
+use gluon_bot::api::{self, BotState};
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::seen::PendingTells;
+use gluon_bot::database::Database;
+use gluon_bot::network::{self, NetworkHandle};
+use gluon_bot::queue::{MessageQueue, QueueReceiver};
+use gluon_bot::status::BotStatus;
+use gluon_bot::{bot, transport};
+use rocket::local::asynchronous::Client;
+use rocket::{Build, Rocket};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Mutex;
+use tokio::task;
+use tokio::time::{self, Duration, Instant};
+
+pub const NETWORK: &str = "test";
+pub const CHANNEL: &str = "#test";
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+// Contents of a file in tests/fixtures.
+pub fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+
+    fs::read_to_string(&path).unwrap_or_else(|_| panic!("Could not read {}.", path.display()))
+}
+
+// A directory of its own for each test, holding the config file and the database. It is removed
+// once the test finishes.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    // Creates the directory with a data subfolder holding each (table, rows) pair as a CSV file.
+    pub fn new(tables: &[(&str, &str)]) -> Self {
+        let path = env::temp_dir().join(format!(
+            "gluon_bot_test_{}_{}",
+            process::id(),
+            DIRS.fetch_add(1, Ordering::SeqCst)
+        ));
+        let test_dir = Self { path };
+
+        fs::create_dir_all(test_dir.data()).expect("Could not create data directory.");
+
+        for (table, rows) in tables {
+            test_dir.write(table, rows);
+        }
+
+        test_dir
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn data(&self) -> PathBuf {
+        self.path.join("data")
+    }
+
+    pub fn database(&self) -> Arc<Mutex<Database>> {
+        Arc::new(Mutex::new(Database::new(
+            &format!("{}/", self.data().display()),
+            None,
+        )))
+    }
+
+    // Replaces the rows of a table.
+    pub fn write(&self, table: &str, rows: &str) {
+        fs::write(self.data().join(format!("{table}.csv")), rows)
+            .unwrap_or_else(|_| panic!("Could not write {table}."));
+    }
+
+    // Rows of a table, or an empty string if it doesn't exist yet.
+    pub fn read(&self, table: &str) -> String {
+        fs::read_to_string(self.data().join(format!("{table}.csv"))).unwrap_or_default()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// The state of the API for a single network without a transport, named NETWORK, and the receiver
+// of the messages queued on it.
+pub fn bot_state(dir: &TestDir) -> (BotState, QueueReceiver) {
+    let (queue, receiver) = MessageQueue::new();
+    let state = BotState {
+        db: dir.database(),
+        options: Arc::new(HashMap::new()),
+        networks: vec![NetworkHandle {
+            name: String::from(NETWORK),
+            data: String::from(NETWORK),
+            transport: Arc::new(Mutex::new(None)),
+            queue,
+            status: Arc::new(Mutex::new(BotStatus::new(NETWORK))),
+            activity: Activity::new(NETWORK),
+        }],
+        hooks: Vec::new(),
+    };
+
+    (state, receiver)
+}
+
+// A local client of the routes mounted on rocket, with the JSON error catcher of the API.
+pub async fn api_client(rocket: Rocket<Build>, state: BotState) -> Client {
+    let rocket = rocket
+        .register("/api", rocket::catchers![api::catch_default])
+        .manage(state);
+
+    Client::untracked(rocket)
+        .await
+        .expect("Could not build the API.")
+}
+
+// A local client of the routes mounted on rocket, with the state of bot_state.
+pub async fn rocket_client(dir: &TestDir, rocket: Rocket<Build>) -> (Client, QueueReceiver) {
+    let (state, receiver) = bot_state(dir);
+
+    (api_client(rocket, state).await, receiver)
+}
+
+// A fake IRC server that accepts a single client, welcomes it and relays lines both ways.
+pub struct IrcServer {
+    port: u16,
+    to_bot: UnboundedSender<String>,
+    from_bot: UnboundedReceiver<String>,
+}
+
+impl IrcServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind IRC server.");
+        let port = listener
+            .local_addr()
+            .expect("Could not get IRC server address.")
+            .port();
+        let (to_bot, mut to_bot_receiver) = mpsc::unbounded_channel::<String>();
+        let (from_bot_sender, from_bot) = mpsc::unbounded_channel();
+
+        task::spawn(async move {
+            let (socket, _) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(_) => return,
+            };
+            let (reader, mut writer) = socket.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut nick = String::from("*");
+
+            loop {
+                let output = tokio::select! {
+                    line = lines.next_line() => {
+                        let line = match line {
+                            Ok(Some(line)) => line,
+                            _ => return,
+                        };
+                        let reply = reply(&line, &mut nick);
+
+                        if from_bot_sender.send(line).is_err() {
+                            return;
+                        }
+
+                        reply
+                    }
+                    line = to_bot_receiver.recv() => match line {
+                        Some(line) => format!("{line}\r\n"),
+                        None => return,
+                    },
+                };
+
+                if writer.write_all(output.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            port,
+            to_bot,
+            from_bot,
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    // Sends a message to target as if nick had written it.
+    pub fn say(&self, nick: &str, target: &str, text: &str) {
+        self.to_bot
+            .send(format!(":{nick}!{nick}@mock PRIVMSG {target} :{text}"))
+            .expect("The IRC server is not running.");
+    }
+
+    // Waits for the bot to join channel.
+    pub async fn joined(&mut self, channel: &str) {
+        let deadline = Instant::now() + TIMEOUT;
+
+        loop {
+            let line = self.next_line(deadline, &format!("JOIN {channel}")).await;
+
+            if let Some(joined) = line.strip_prefix("JOIN ") {
+                if joined.trim_start_matches(':') == channel {
+                    return;
+                }
+            }
+        }
+    }
+
+    // Waits for the bot to send target a message containing text and returns the whole message.
+    // Any other message sent in the meantime is skipped.
+    pub async fn expect(&mut self, target: &str, text: &str) -> String {
+        self.expect_within(TIMEOUT, target, text).await
+    }
+
+    pub async fn expect_within(&mut self, timeout: Duration, target: &str, text: &str) -> String {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let line = self.next_line(deadline, text).await;
+
+            if let Some((line_target, message)) = privmsg(&line) {
+                if line_target == target && message.contains(text) {
+                    return String::from(message);
+                }
+            }
+        }
+    }
+
+    async fn next_line(&mut self, deadline: Instant, waiting_for: &str) -> String {
+        match time::timeout_at(deadline, self.from_bot.recv()).await {
+            Ok(Some(line)) => line,
+            Ok(None) => panic!("The bot disconnected while waiting for \"{waiting_for}\"."),
+            Err(_) => panic!("Timeout while waiting for \"{waiting_for}\"."),
+        }
+    }
+}
+
+// Just enough of the registration and keepalive replies for the IRC client to join its channels.
+fn reply(line: &str, nick: &mut String) -> String {
+    let mut words = line.split_whitespace();
+
+    match words.next() {
+        Some("NICK") => {
+            if let Some(new_nick) = words.next() {
+                *nick = String::from(new_nick.trim_start_matches(':'));
+            }
+
+            String::new()
+        }
+        Some("USER") => {
+            format!(":mock 001 {nick} :Welcome\r\n:mock 422 {nick} :MOTD File is missing\r\n")
+        }
+        Some("PING") => format!(":mock PONG mock {}\r\n", words.next().unwrap_or_default()),
+        Some("JOIN") => format!(
+            ":{nick}!{nick}@mock JOIN {}\r\n",
+            words.next().unwrap_or_default()
+        ),
+        _ => String::new(),
+    }
+}
+
+// Target and text of a PRIVMSG line.
+fn privmsg(line: &str) -> Option<(&str, &str)> {
+    let (target, text) = line.strip_prefix("PRIVMSG ")?.split_once(' ')?;
+
+    Some((target, text.strip_prefix(':').unwrap_or(text)))
+}
+
+// A local HTTP server answering GET requests with fixed bodies, standing in for external APIs.
+pub struct HttpStub {
+    port: u16,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl HttpStub {
+    // Serves the body of each (path, content type, body) route. Any other path gets a 404.
+    pub async fn start(routes: &[(&str, &str, String)]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind HTTP stub.");
+        let port = listener
+            .local_addr()
+            .expect("Could not get HTTP stub address.")
+            .port();
+        let routes: Arc<HashMap<String, (String, String)>> = Arc::new(
+            routes
+                .iter()
+                .map(|(path, content_type, body)| {
+                    (
+                        String::from(*path),
+                        (String::from(*content_type), body.clone()),
+                    )
+                })
+                .collect(),
+        );
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let requests_clone = Arc::clone(&requests);
+
+        task::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let routes = Arc::clone(&routes);
+                let requests = Arc::clone(&requests_clone);
+
+                task::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut buffer = [0; 1024];
+
+                    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => head.extend_from_slice(&buffer[..read]),
+                        }
+                    }
+
+                    let head = String::from_utf8_lossy(&head);
+                    let target = String::from(head.split_whitespace().nth(1).unwrap_or("/"));
+                    let path = target.split('?').next().unwrap_or("/");
+                    let response = match routes.get(path) {
+                        Some((content_type, body)) => format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len()
+                        ),
+                        None => String::from(
+                            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        ),
+                    };
+
+                    requests.lock().await.push(target);
+
+                    if socket.write_all(response.as_bytes()).await.is_ok() {
+                        let _ = socket.shutdown().await;
+                    }
+                });
+            }
+        });
+
+        Self { port, requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{path}", self.port)
+    }
+
+    // Path and query of every request received so far.
+    pub async fn requests(&self) -> Vec<String> {
+        self.requests.lock().await.clone()
+    }
+}
+
+// A bot connected to an IrcServer as gluon, on the test network and the #test channel.
+pub struct Bot {
+    pub dir: TestDir,
+    pub db: Arc<Mutex<Database>>,
+    pub clock: Clock,
+}
+
+impl Bot {
+    // Writes a config file with the given options and the given tables, then runs the bot in the
+    // background until the test finishes.
+    pub async fn start(
+        server: &IrcServer,
+        options: &[(&str, &str)],
+        tables: &[(&str, &str)],
+    ) -> Self {
+        Self::start_with_clock(server, Clock::system(""), options, tables).await
+    }
+
+    // Same as start, but with the clock the bot should use.
+    pub async fn start_with_clock(
+        server: &IrcServer,
+        clock: Clock,
+        options: &[(&str, &str)],
+        tables: &[(&str, &str)],
+    ) -> Self {
+        let dir = TestDir::new(tables);
+        let mut all_options = HashMap::from([
+            (
+                String::from("database_path"),
+                format!("{}/", dir.data().display()),
+            ),
+            (
+                String::from("socket_path"),
+                dir.path().join("bot.sock").display().to_string(),
+            ),
+            (String::from("message_interval"), String::from("0")),
+            (String::from("network"), String::from(NETWORK)),
+        ]);
+
+        for (key, value) in options {
+            all_options.insert(String::from(*key), String::from(*value));
+        }
+
+        let mut config = format!(
+            "nickname = \"gluon\"\nowners = [\"owner\"]\nserver = \"127.0.0.1\"\nport = {}\nuse_tls = false\nchannels = [\"{CHANNEL}\"]\n\n[options]\n",
+            server.port()
+        );
+
+        for (key, value) in &all_options {
+            config.push_str(&format!("{key} = {value:?}\n"));
+        }
+
+        let path = dir.path().join("config.toml");
+
+        fs::write(&path, config).expect("Could not write config.toml.");
+
+        let mut networks =
+            network::load(&path.display().to_string()).expect("Could not load config.toml.");
+        let network = networks.remove(0);
+        let db = dir.database();
+        let transport = transport::connect(&network)
+            .await
+            .expect("Could not connect to the IRC server.");
+        let (queue, queue_receiver) = MessageQueue::new();
+        let handle = NetworkHandle {
+            name: network.name.clone(),
+            data: network.data.clone(),
+            transport: Arc::new(Mutex::new(Some(transport))),
+            queue,
+            status: Arc::new(Mutex::new(BotStatus::new(&network.name))),
+            activity: Activity::new(&network.name),
+        };
+
+        task::spawn(bot::run(
+            network,
+            handle,
+            Arc::new(Mutex::new(queue_receiver)),
+            Arc::clone(&db),
+            clock.clone(),
+            PendingTells::load(&db).await,
+        ));
+
+        Self { dir, db, clock }
+    }
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use common::{fixture, Bot, HttpStub, IrcServer, CHANNEL};
+
+async fn start() -> (IrcServer, HttpStub, Bot) {
+    let stub = HttpStub::start(&[
+        ("/omdb/", "application/json", fixture("omdb.json")),
+        (
+            "/currencyapi/",
+            "application/json",
+            fixture("currencyapi.json"),
+        ),
+        (
+            "/ergast/constructorStandings.json",
+            "application/json",
+            fixture("ergast_constructor_standings.json"),
+        ),
+        (
+            "/ergast/driverStandings.json",
+            "application/json",
+            fixture("ergast_driver_standings.json"),
+        ),
+        (
+            "/youtube/videos",
+            "application/json",
+            fixture("youtube.json"),
+        ),
+        ("/f1/results.html", "text/html", fixture("f1_results.html")),
+        (
+            "/owm/geo/1.0/direct",
+            "application/json",
+            fixture("owm_geocoding.json"),
+        ),
+        (
+            "/owm/data/3.0/onecall",
+            "application/json",
+            fixture("owm_onecall.json"),
+        ),
+        (
+            "/owm/data/2.5/forecast",
+            "application/json",
+            fixture("owm_forecast.json"),
+        ),
+    ])
+    .await;
+    let omdb_api_url = stub.url("/omdb");
+    let currency_api_url = stub.url("/currencyapi");
+    let ergast_api_url = stub.url("/ergast");
+    let youtube_api_url = stub.url("/youtube");
+    let f1_results_url = stub.url("/f1/results.html");
+    let owm_api_url = stub.url("/owm");
+    let mut server = IrcServer::start().await;
+    let bot = Bot::start(
+        &server,
+        &[
+            ("omdb_api_key", "omdb_key"),
+            ("omdb_api_url", &omdb_api_url),
+            ("currency_api_key", "currency_key"),
+            ("currency_api_url", &currency_api_url),
+            ("ergast_api_url", &ergast_api_url),
+            ("youtube_api_key", "youtube_key"),
+            ("youtube_api_url", &youtube_api_url),
+            ("f1_results_url", &f1_results_url),
+            ("owm_api_key", "owm_key"),
+            ("owm_api_url", &owm_api_url),
+            ("owm_api_units", "metric"),
+        ],
+        &[],
+    )
+    .await;
+
+    server.joined(CHANNEL).await;
+
+    (server, stub, bot)
+}
+
+#[tokio::test]
+async fn omdb_shows_the_movie() {
+    let (mut server, stub, _bot) = start().await;
+
+    server.say("alice", CHANNEL, "!imdb Rush");
+    server
+        .expect(
+            CHANNEL,
+            "Title: Rush | Year: 2013 | Genre: Action, Biography, Drama | Director: Ron Howard | IMDB Rating: 8.1",
+        )
+        .await;
+
+    assert!(stub
+        .requests()
+        .await
+        .contains(&String::from("/omdb/?apikey=omdb_key&t=Rush")));
+}
+
+#[tokio::test]
+async fn rates_shows_the_exchange_rates() {
+    let (mut server, _stub, _bot) = start().await;
+
+    server.say("alice", CHANNEL, "!rates USD");
+    server.expect(CHANNEL, "CUR:\x02 USD").await;
+    server.expect(CHANNEL, "EUR: 0.92").await;
+}
+
+#[tokio::test]
+async fn f1standings_shows_both_championships() {
+    let (mut server, _stub, _bot) = start().await;
+
+    server.say("alice", CHANNEL, "!wdc");
+    server
+        .expect(CHANNEL, "WCC: 1. RBR 120 | 2. Ferrari 90")
+        .await;
+    server
+        .expect(CHANNEL, "WDC: 1. VER 77 (3 wins) | 2. LEC 50")
+        .await;
+}
+
+#[tokio::test]
+async fn f1results_shows_the_latest_results() {
+    let (mut server, _stub, _bot) = start().await;
+
+    server.say("alice", CHANNEL, "!f1results");
+    server
+        .expect(CHANNEL, "Monaco Grand Prix 2025 - Race Result")
+        .await;
+    server.expect(CHANNEL, "1 VER 78 1:40:33.843 25").await;
+}
+
+#[tokio::test]
+async fn youtube_links_show_the_video() {
+    let (mut server, stub, _bot) = start().await;
+
+    server.say(
+        "alice",
+        CHANNEL,
+        "Have a look https://www.youtube.com/watch?v=abc123",
+    );
+    server.expect(CHANNEL, "Monaco GP highlights").await;
+    server.expect(CHANNEL, "👁 1000").await;
+
+    assert!(stub
+        .requests()
+        .await
+        .iter()
+        .any(|r| r.starts_with("/youtube/videos?") && r.contains("id=abc123")));
+}
+
+#[tokio::test]
+async fn weather_shows_the_current_weather_and_forecast() {
+    let (mut server, stub, _bot) = start().await;
+
+    server.say("alice", CHANNEL, "!weather lisbon");
+    server
+        .expect(
+            CHANNEL,
+            "Lisbon: clear sky 21.5C | Humidity: 60% | Pressure: 1015hPa | Wind: 4.1m/s @ 320 6.2m/s",
+        )
+        .await;
+    server
+        .expect(
+            CHANNEL,
+            "14:00 CEST: few clouds 22C | 17:00 CEST: clear sky 24C | 20:00 CEST: light rain 19C",
+        )
+        .await;
+
+    assert!(stub.requests().await.contains(&String::from(
+        "/owm/geo/1.0/direct?q=lisbon&limit=1&appid=owm_key"
+    )));
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::{TestDir, CHANNEL, NETWORK};
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::f1bet;
+use std::collections::HashMap;
+
+const DRIVERS: &str = "1,VER\n44,HAM\n16,LEC\n4,NOR\n55,SAI\n11,PER\n";
+// The Monaco GP race of the test network, held on 2025-05-25.
+const EVENTS: &str =
+    "[Formula 1],Monaco GP,Race,2025-05-25 13:00:00 UTC,#test,f1 formula1,true,test\n";
+
+// Bets are placed through f1bet directly, since the !bet command is disabled between seasons.
+async fn place(nick: &str, drivers: &str, clock: &Clock, dir: &TestDir) -> String {
+    let args: Vec<String> = drivers.split_whitespace().map(String::from).collect();
+
+    f1bet::bet(
+        &args,
+        nick,
+        CHANNEL,
+        NETWORK,
+        &HashMap::new(),
+        clock,
+        dir.database(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn bets_are_placed_on_the_next_race_and_scored() {
+    let clock = Clock::pinned(
+        "2025-05-22 12:00:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    let dir = TestDir::new(&[("events", EVENTS), ("drivers", DRIVERS)]);
+
+    assert_eq!(
+        place("alice", "VER HAM LEC NOR SAI", &clock, &dir).await,
+        "Your bet for the Monaco GP was successfully updated."
+    );
+    assert_eq!(
+        place("bob", "VER LEC HAM NOR PER", &clock, &dir).await,
+        "Your bet for the Monaco GP was successfully updated."
+    );
+    assert_eq!(
+        place("carol", "VER HAM LEC NOR XXX", &clock, &dir).await,
+        "Invalid drivers."
+    );
+    assert_eq!(
+        place("carol", "VER HAM", &clock, &dir).await,
+        "The bet must contain 5 drivers: <1st> <2nd> <3rd> <4th> <5th>."
+    );
+
+    dir.write("results", "Monaco GP,result,ver,ham,lec,nor,sai\n");
+
+    // Alice got the whole top five right, Bob only P1, P4 and two drivers in the wrong place.
+    assert_eq!(
+        f1bet::points(false, NETWORK, &HashMap::new(), dir.database()).await,
+        "1. ALI 26 | 2. BOB 9"
+    );
+    assert_eq!(
+        place("alice", "", &clock, &dir).await,
+        "Monaco GP: VER HAM LEC NOR SAI 26"
+    );
+    assert_eq!(
+        f1bet::points(false, "other", &HashMap::new(), dir.database()).await,
+        "Could not find any bets."
+    );
+}
+
+#[tokio::test]
+async fn bets_need_an_upcoming_race() {
+    let clock = Clock::pinned(
+        "2025-05-25 13:00:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    let dir = TestDir::new(&[("events", EVENTS), ("drivers", DRIVERS)]);
+
+    assert_eq!(
+        place("alice", "VER HAM LEC NOR SAI", &clock, &dir).await,
+        "Could not find next race."
+    );
+}
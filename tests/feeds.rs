@@ -0,0 +1,19 @@
+mod common;
+
+use common::{fixture, Bot, HttpStub, IrcServer, CHANNEL, NETWORK};
+
+#[tokio::test]
+async fn new_feed_entries_are_announced() {
+    let stub = HttpStub::start(&[("/feed.xml", "application/rss+xml", fixture("feed.xml"))]).await;
+    let feeds = format!(
+        "1,News,{},{CHANNEL},2024-05-01 12:00:00 UTC,{NETWORK}\n",
+        stub.url("/feed.xml")
+    );
+    let mut server = IrcServer::start().await;
+    let _bot = Bot::start(&server, &[("feed_refresh", "1")], &[("feeds", &feeds)]).await;
+
+    server.expect(CHANNEL, "[Second entry]").await;
+    server.expect(CHANNEL, "https://example.com/news/2").await;
+
+    assert!(stub.requests().await.contains(&String::from("/feed.xml")));
+}
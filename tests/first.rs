@@ -0,0 +1,91 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use common::{Bot, IrcServer, CHANNEL, NETWORK};
+use gluon_bot::clock::Clock;
+
+const OPTIONS: [(&str, &str); 4] = [
+    ("first_open_hour", "5"),
+    ("first_open_min", "30"),
+    ("first_close_hour", "21"),
+    ("first_close_min", "0"),
+];
+
+fn at(datetime: &str) -> DateTime<Utc> {
+    datetime.parse().expect("Invalid date time.")
+}
+
+async fn start(clock: Clock, options: &[(&str, &str)]) -> (IrcServer, Bot) {
+    let time_zones = format!("alice,UTC,{NETWORK}\nbob,UTC,{NETWORK}\n");
+    let mut server = IrcServer::start().await;
+    let bot = Bot::start_with_clock(&server, clock, options, &[("time_zones", &time_zones)]).await;
+
+    server.joined(CHANNEL).await;
+
+    (server, bot)
+}
+
+#[tokio::test]
+async fn first_ranks_players_in_the_order_they_play() {
+    let clock = Clock::pinned(at("2025-05-01 10:00:00 UTC"), "salt");
+    let (mut server, _bot) = start(clock.clone(), &OPTIONS).await;
+
+    server.say("alice", CHANNEL, "!first");
+    server.expect(CHANNEL, "You are currently P1.").await;
+    server
+        .expect(
+            CHANNEL,
+            "STATUS open (today opened at 05H30 UTC | tomorrow opens at 05H30 UTC)",
+        )
+        .await;
+
+    clock.advance(Duration::minutes(1));
+    server.say("bob", CHANNEL, "!first");
+    server.expect(CHANNEL, "You are currently P2.").await;
+
+    server.say("alice", CHANNEL, "!first");
+    server.expect(CHANNEL, "STATUS played").await;
+
+    server.say("bob", CHANNEL, "!first_results");
+    server.expect(CHANNEL, "1. ALI | 10:00:00 (UTC)").await;
+    server.expect(CHANNEL, "2. BOB | 10:01:00 (UTC)").await;
+
+    clock.set(at("2025-05-01 21:30:00 UTC"));
+    server.say("bob", CHANNEL, "!first");
+    server
+        .expect(CHANNEL, "STATUS: closed (closes at 21H00 UTC)")
+        .await;
+}
+
+#[tokio::test]
+async fn first_opens_at_the_same_random_time_all_day() {
+    let options = [
+        ("first_open_hour", "random"),
+        ("first_open_min", "random"),
+        ("first_close_hour", "21"),
+        ("first_close_min", "0"),
+    ];
+    let clock = Clock::pinned(at("2025-05-01 04:00:00 UTC"), "salt");
+    let (mut server, _bot) = start(clock.clone(), &options).await;
+
+    // The random opening time is always between 05H00 and 11H58.
+    server.say("alice", CHANNEL, "!first");
+    let closed = server.expect(CHANNEL, "STATUS closed (opens at").await;
+
+    clock.advance(Duration::minutes(30));
+    server.say("bob", CHANNEL, "!first");
+    assert_eq!(server.expect(CHANNEL, "STATUS closed").await, closed);
+
+    clock.set(at("2025-05-01 12:00:00 UTC"));
+    server.say("alice", CHANNEL, "!first");
+    server.expect(CHANNEL, "You are currently P1.").await;
+}
+
+#[tokio::test]
+async fn first_requires_a_time_zone() {
+    let clock = Clock::pinned(at("2025-05-01 10:00:00 UTC"), "salt");
+    let (mut server, _bot) = start(clock, &OPTIONS).await;
+
+    server.say("carol", CHANNEL, "!first");
+    server.expect(CHANNEL, "Set a time zone.").await;
+}
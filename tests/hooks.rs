@@ -0,0 +1,170 @@
+mod common;
+
+use common::{api_client, bot_state, TestDir};
+use gluon_bot::api;
+use gluon_bot::hooks::{self, Hook};
+use gluon_bot::queue::QueueReceiver;
+use gluon_bot::webhook;
+use rocket::http::{Header, Status};
+use rocket::local::asynchronous::Client;
+use serde_json::json;
+use std::fs;
+
+const CONFIG: &str = r##"
+[options]
+database_path = "data/"
+
+[[hooks]]
+name = "github"
+secret = "github_secret"
+channels = ["#geeks", "#test"]
+template = """
+[{repository.full_name}] {pusher.name} pushed {commits.#} commits: {commits.*.message}
+{{{compare}}}"""
+
+[[hooks]]
+name = "grafana"
+secret = "grafana_secret"
+signature_header = "X-Grafana-Alerting-Signature"
+network = "test"
+channels = ["#test"]
+template = "{status}: {alerts.0.labels.alertname}"
+"##;
+
+fn load(config: &str) -> Result<Vec<Hook>, String> {
+    let dir = TestDir::new(&[]);
+    let path = dir.path().join("config.toml");
+
+    fs::write(&path, config).expect("Could not write config.toml.");
+
+    hooks::load(&path.display().to_string())
+}
+
+async fn client(dir: &TestDir) -> (Client, QueueReceiver) {
+    let (mut state, receiver) = bot_state(dir);
+
+    state.hooks = load(CONFIG).expect("Could not load hooks.");
+
+    (
+        api_client(
+            rocket::build().mount("/api", rocket::routes![api::hook]),
+            state,
+        )
+        .await,
+        receiver,
+    )
+}
+
+fn messages(receiver: &mut QueueReceiver) -> Vec<(String, String)> {
+    let mut messages = Vec::new();
+
+    while let Some(message) = receiver.try_next("gluon", 410) {
+        messages.push((message.target, message.text));
+    }
+
+    messages
+}
+
+#[test]
+fn templates_pick_values_from_the_payload() {
+    let payload = json!({
+        "repository": {"full_name": "gluon/bot"},
+        "pusher": {"name": "alice"},
+        "commits": [{"message": "Fix the clock\n\nDetails"}, {"message": "Add hooks"}],
+        "compare": "https://example.com/compare",
+    });
+    let hooks = load(CONFIG).expect("Could not load hooks.");
+
+    assert_eq!(
+        hooks::render(&hooks[0].template, &payload),
+        vec![
+            "[gluon/bot] alice pushed 2 commits: Fix the clock  Details, Add hooks",
+            "{https://example.com/compare}",
+        ]
+    );
+    assert_eq!(
+        hooks::render("{missing.path} {commits.5.message}", &payload),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn hooks_need_a_unique_name_and_a_secret() {
+    let hooks = load(CONFIG).expect("Could not load hooks.");
+
+    assert_eq!(hooks.len(), 2);
+    assert_eq!(hooks[0].signature_header, "X-Hub-Signature-256");
+    assert_eq!(hooks[0].network, None);
+    assert!(load("").expect("Could not load hooks.").is_empty());
+    assert!(load(&format!(
+        "{CONFIG}\n[[hooks]]\nname = \"github\"\nsecret = \"s\"\nchannels = []\ntemplate = \"\"\n"
+    ))
+    .is_err_and(|e| e.contains("duplicate hook github")));
+    assert!(
+        load("[[hooks]]\nname = \"ci\"\nsecret = \"\"\nchannels = []\ntemplate = \"\"\n")
+            .is_err_and(|e| e.contains("hook ci has no secret"))
+    );
+}
+
+#[tokio::test]
+async fn signed_payloads_are_sent_to_the_channels() {
+    let dir = TestDir::new(&[]);
+    let (client, mut receiver) = client(&dir).await;
+    let body = json!({
+        "status": "firing",
+        "alerts": [{"labels": {"alertname": "DiskFull"}}],
+    })
+    .to_string();
+    let response = client
+        .post("/api/hooks/grafana")
+        .header(Header::new(
+            "X-Grafana-Alerting-Signature",
+            webhook::sign("grafana_secret", &body),
+        ))
+        .body(&body)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        messages(&mut receiver),
+        vec![(String::from("#test"), String::from("firing: DiskFull"))]
+    );
+}
+
+#[tokio::test]
+async fn unsigned_or_unknown_hooks_are_rejected() {
+    let dir = TestDir::new(&[]);
+    let (client, mut receiver) = client(&dir).await;
+    let body = r#"{"status": "firing"}"#;
+
+    for (path, signature, status) in [
+        ("/api/hooks/grafana", None, Status::Unauthorized),
+        (
+            "/api/hooks/grafana",
+            Some(webhook::sign("wrong_secret", body)),
+            Status::Unauthorized,
+        ),
+        // The GitHub hook has another header and secret.
+        (
+            "/api/hooks/github",
+            Some(webhook::sign("grafana_secret", body)),
+            Status::Unauthorized,
+        ),
+        (
+            "/api/hooks/gitea",
+            Some(webhook::sign("grafana_secret", body)),
+            Status::NotFound,
+        ),
+    ] {
+        let mut request = client.post(path).body(body);
+
+        if let Some(signature) = signature {
+            request = request.header(Header::new("X-Grafana-Alerting-Signature", signature));
+        }
+
+        assert_eq!(request.dispatch().await.status(), status);
+    }
+
+    assert!(messages(&mut receiver).is_empty());
+}
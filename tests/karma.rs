@@ -0,0 +1,122 @@
+mod common;
+
+use chrono::Duration;
+use common::{rocket_client, TestDir, CHANNEL, NETWORK};
+use gluon_bot::api;
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::karma;
+use rocket::http::Status;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn args(args: &str) -> Vec<String> {
+    args.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn votes_are_found_at_the_start_of_messages() {
+    assert_eq!(
+        karma::parse("Alice++ for the pit stop"),
+        Some((String::from("alice"), 1, String::from("for the pit stop")))
+    );
+    assert_eq!(
+        karma::parse("rain--"),
+        Some((String::from("rain"), -1, String::new()))
+    );
+    assert_eq!(karma::parse("++"), None);
+    assert_eq!(karma::parse("---"), None);
+    assert_eq!(karma::parse("I like alice++"), None);
+}
+
+#[tokio::test]
+async fn karma_is_given_with_a_cooldown_and_ranked() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let clock = Clock::pinned(
+        "2025-05-22 10:00:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    let options = HashMap::new();
+    let give = |nick: &'static str, message: &'static str| {
+        let clock = clock.clone();
+        let db = Arc::clone(&db);
+        let options = options.clone();
+
+        async move { karma::give(nick, CHANNEL, message, NETWORK, &options, &clock, &db).await }
+    };
+
+    assert_eq!(
+        give("bob", "alice++ for the pit stop").await.as_deref(),
+        Some("alice has 1 karma.")
+    );
+    assert_eq!(
+        give("bob", "alice++").await.as_deref(),
+        Some("Please wait before giving karma again.")
+    );
+    assert_eq!(
+        give("alice", "Alice++").await.as_deref(),
+        Some("You can't give karma to yourself.")
+    );
+    assert_eq!(give("alice", "just chatting").await, None);
+    assert_eq!(
+        give("alice", "rain--").await.as_deref(),
+        Some("rain has -1 karma.")
+    );
+
+    clock.advance(Duration::seconds(60));
+
+    assert_eq!(
+        give("bob", "ALICE++").await.as_deref(),
+        Some("alice has 2 karma.")
+    );
+    assert_eq!(
+        give("carol", "sun++").await.as_deref(),
+        Some("sun has 1 karma.")
+    );
+
+    assert_eq!(
+        karma::karma(&args("alice"), CHANNEL, NETWORK, dir.database()).await,
+        "alice has 2 karma, last for: for the pit stop"
+    );
+    assert_eq!(
+        karma::karma(&args("alice"), "#other", NETWORK, dir.database()).await,
+        "alice has no karma."
+    );
+    assert_eq!(
+        karma::karma(&args("top"), CHANNEL, NETWORK, dir.database()).await,
+        "Top karma: alice (2), sun (1), rain (-1)"
+    );
+    assert_eq!(
+        karma::karma(&args("bottom"), CHANNEL, NETWORK, dir.database()).await,
+        "Bottom karma: rain (-1), sun (1), alice (2)"
+    );
+}
+
+#[tokio::test]
+async fn karma_is_listed_highest_first() {
+    let dir = TestDir::new(&[(
+        "karma",
+        "rain,-3,,#test,test\nalice,5,pit stop,#test,test\nbob,2,,#other,test\n",
+    )]);
+    let (client, _receiver) = rocket_client(
+        &dir,
+        rocket::build().mount("/api", rocket::routes![api::karma]),
+    )
+    .await;
+
+    let response = client.get("/api/karma?channel=%23TEST").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("X-Total-Count"), Some("2"));
+
+    let karma: Value = serde_json::from_str(&response.into_string().await.unwrap_or_default())
+        .expect("Invalid JSON body.");
+
+    assert_eq!(karma[0]["thing"], "alice");
+    assert_eq!(karma[0]["score"], 5);
+    assert_eq!(karma[0]["reason"], "pit stop");
+    assert_eq!(karma[1]["thing"], "rain");
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use common::{rocket_client, TestDir, CHANNEL, NETWORK};
+use gluon_bot::api::{self, keys};
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::log;
+use rocket::http::{Header, Status};
+use rocket::local::asynchronous::Client;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const LOGS: &str = "2025-05-20 10:00:00 UTC,#test,alice,Lights out and away we go,test\n\
+    2025-05-21 10:00:00 UTC,#test,bob,Box box,test\n\
+    2025-05-22 10:00:00 UTC,#other,alice,Hello there,test\n\
+    2025-05-22 11:00:00 UTC,#test,alice,What a race,test\n";
+
+fn args(args: &str) -> Vec<String> {
+    args.split_whitespace().map(String::from).collect()
+}
+
+fn clock() -> Clock {
+    Clock::pinned(
+        "2025-05-22 12:30:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    )
+}
+
+async fn client(dir: &TestDir) -> Client {
+    let rocket = rocket::build().mount("/api", rocket::routes![api::logs]);
+
+    rocket_client(dir, rocket).await.0
+}
+
+#[tokio::test]
+async fn only_logged_channels_are_recorded() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let options = HashMap::from([(String::from("log_channels"), String::from("#Test"))]);
+
+    assert!(log::logged(CHANNEL, &options));
+    assert!(!log::logged("#other", &options));
+    assert!(!log::logged(CHANNEL, &HashMap::new()));
+
+    log::record("alice", CHANNEL, "Hi", NETWORK, &clock(), &db).await;
+
+    assert_eq!(
+        dir.read("logs"),
+        "2025-05-22 12:30:00 UTC,#test,alice,Hi,test\n"
+    );
+}
+
+#[tokio::test]
+async fn logs_are_searched_by_nick_and_pattern() {
+    let dir = TestDir::new(&[("logs", LOGS)]);
+    let clock = clock();
+
+    assert_eq!(
+        log::last(&args("alice 5"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "[2d 2h 30m ago] <alice> Lights out and away we go\n[0d 1h 30m ago] <alice> What a race"
+    );
+    assert_eq!(
+        log::grep(&args("box|RACE"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "[1d 2h 30m ago] <bob> Box box\n[0d 1h 30m ago] <alice> What a race"
+    );
+    assert_eq!(
+        log::grep(&args("hello"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "Could not find hello in the logs of #test."
+    );
+    assert_eq!(
+        log::grep(&args("(unclosed"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "Invalid pattern."
+    );
+}
+
+#[tokio::test]
+async fn users_can_opt_out_and_old_lines_are_pruned() {
+    let dir = TestDir::new(&[("logs", LOGS)]);
+    let db = dir.database();
+    let clock = clock();
+
+    assert_eq!(
+        log::log(&args("off"), "Alice", NETWORK, dir.database()).await,
+        "Your messages won't be logged anymore and those already logged were deleted."
+    );
+
+    log::record("alice", CHANNEL, "Hi", NETWORK, &clock, &db).await;
+
+    assert_eq!(
+        dir.read("logs"),
+        "2025-05-21 10:00:00 UTC,#test,bob,Box box,test\n"
+    );
+    assert_eq!(
+        log::log(&[], "alice", NETWORK, dir.database()).await,
+        "Your messages are not logged. Use log on to opt back in."
+    );
+    assert_eq!(
+        log::log(&args("on"), "alice", NETWORK, dir.database()).await,
+        "Your messages are logged again on logged channels."
+    );
+
+    log::record("alice", CHANNEL, "Hi", NETWORK, &clock, &db).await;
+
+    let options = HashMap::from([(String::from("log_retention"), String::from("1"))]);
+
+    log::prune(NETWORK, &options, &clock, &db).await;
+
+    assert_eq!(
+        dir.read("logs"),
+        "2025-05-22 12:30:00 UTC,#test,alice,Hi,test\n"
+    );
+}
+
+#[tokio::test]
+async fn logs_are_served_newest_first_to_allowed_keys() {
+    let dir = TestDir::new(&[("logs", LOGS)]);
+    let db = dir.database();
+    let client = client(&dir).await;
+    let key = keys::create("logs", &args("logs:read"), &[], None, &db)
+        .await
+        .expect("Could not create key.");
+    let other_key = keys::create("other", &args("logs:read"), &args("#other"), None, &db)
+        .await
+        .expect("Could not create key.");
+
+    let response = client
+        .get("/api/logs?nick=ALICE&from=2025-05-20&to=2025-05-21T00:00:00Z&text=lights")
+        .header(Header::new("x-api-key", key.clone()))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let lines: Value = serde_json::from_str(&response.into_string().await.unwrap_or_default())
+        .expect("Invalid JSON body.");
+
+    assert_eq!(lines.as_array().map(|l| l.len()), Some(1));
+    assert_eq!(lines[0]["text"], "Lights out and away we go");
+
+    // Keys restricted to some channels only see those channels.
+    let response = client
+        .get("/api/logs")
+        .header(Header::new("x-api-key", other_key.clone()))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.headers().get_one("X-Total-Count"), Some("1"));
+
+    for (path, key, status) in [
+        (
+            "/api/logs?channel=%23test",
+            other_key.as_str(),
+            Status::Forbidden,
+        ),
+        ("/api/logs?from=yesterday", key.as_str(), Status::BadRequest),
+    ] {
+        let response = client
+            .get(path)
+            .header(Header::new("x-api-key", String::from(key)))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), status);
+    }
+}
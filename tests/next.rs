@@ -0,0 +1,41 @@
+mod common;
+
+use common::{Bot, IrcServer, CHANNEL, NETWORK};
+use gluon_bot::clock::Clock;
+use std::time::Duration;
+
+#[tokio::test]
+async fn events_are_announced_five_minutes_before_they_start() {
+    // The next task checks for events every 30 seconds, while the clock stays 290 seconds before the
+    // events start.
+    let clock = Clock::pinned(
+        "2025-05-25 12:55:10 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    let start = "2025-05-25 13:00:00 UTC";
+    let events = format!(
+        "[Formula 1],Monaco GP,Race,{start},{CHANNEL},f1 formula1,true,{NETWORK}\n\
+        [MotoGP],Mugello GP,Race,{start},{CHANNEL},motogp,true,other\n"
+    );
+    let interests = format!("alice,f1,{NETWORK}\nbob,motogp,{NETWORK}\n");
+    let mut server = IrcServer::start().await;
+    let _bot = Bot::start_with_clock(
+        &server,
+        clock,
+        &[],
+        &[("events", &events), ("interests", &interests)],
+    )
+    .await;
+
+    let announcement = server
+        .expect_within(Duration::from_secs(45), CHANNEL, "Starting in 5 minutes")
+        .await;
+
+    assert!(announcement.contains("[Formula 1] Monaco GP Race"));
+
+    let mentions = server.expect(CHANNEL, "alice").await;
+
+    assert!(!mentions.contains("bob"));
+}
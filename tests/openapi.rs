@@ -0,0 +1,94 @@
+use gluon_bot::api::openapi::{self, ApiDoc};
+use rocket::http::{ContentType, Status};
+use rocket::local::asynchronous::Client;
+use serde_json::Value;
+use utoipa::OpenApi;
+
+fn document() -> Value {
+    serde_json::to_value(ApiDoc::openapi()).expect("Could not serialize the document.")
+}
+
+#[test]
+fn every_route_is_described() {
+    let document = document();
+    let paths = &document["paths"];
+
+    assert_eq!(document["openapi"], "3.0.3");
+    assert_eq!(document["servers"][0]["url"], "/api");
+
+    for (path, methods) in [
+        ("/events", &["get", "post"][..]),
+        ("/events/{id}", &["get", "put", "patch", "delete"]),
+        ("/quotes", &["get", "post"]),
+        ("/quotes/{id}", &["get", "put", "patch", "delete"]),
+        ("/feeds/{id}", &["get", "put", "patch", "delete"]),
+        ("/f1bets", &["get", "post"]),
+        ("/f1bets/scores", &["get"]),
+        ("/say", &["post"]),
+        ("/status", &["get"]),
+        ("/hooks/{name}", &["post"]),
+    ] {
+        for method in methods {
+            assert!(
+                paths[path][method].is_object(),
+                "{} {path} is missing.",
+                method.to_uppercase()
+            );
+        }
+    }
+}
+
+#[test]
+fn parameters_bodies_and_keys_come_from_the_routes() {
+    let document = document();
+    let events = &document["paths"]["/events"];
+    let parameters: Vec<&str> = events["get"]["parameters"]
+        .as_array()
+        .expect("Missing parameters.")
+        .iter()
+        .filter_map(|p| p["name"].as_str())
+        .collect();
+
+    assert!(parameters.contains(&"channel"));
+    assert!(parameters.contains(&"limit"));
+    assert!(events["get"]["responses"]["200"]["headers"]["X-Total-Count"].is_object());
+    assert!(events["get"]["security"].is_null());
+    assert_eq!(
+        events["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/Event"
+    );
+    assert_eq!(
+        events["post"]["security"][0]["api_key"],
+        Value::Array(Vec::new())
+    );
+    assert_eq!(
+        document["components"]["securitySchemes"]["api_key"]["name"],
+        "x-api-key"
+    );
+    assert!(document["components"]["schemas"]["Quote"]["properties"]["id"].is_object());
+}
+
+#[tokio::test]
+async fn the_document_and_docs_page_are_served() {
+    let rocket = rocket::build().mount("/api", rocket::routes![openapi::openapi, openapi::docs]);
+    let client = Client::untracked(rocket)
+        .await
+        .expect("Could not build the API.");
+    let response = client.get("/api/openapi.json").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        serde_json::from_str::<Value>(&response.into_string().await.unwrap_or_default())
+            .expect("Invalid JSON body."),
+        document()
+    );
+
+    let response = client.get("/api/docs").dispatch().await;
+
+    assert_eq!(response.content_type(), Some(ContentType::HTML));
+    assert!(response
+        .into_string()
+        .await
+        .unwrap_or_default()
+        .contains("/api/openapi.json"));
+}
@@ -0,0 +1,527 @@
+mod common;
+
+use common::{HttpStub, TestDir, CHANNEL, NETWORK, TIMEOUT};
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::plugin::{self, Plugins};
+use gluon_bot::commands::seen::PendingTells;
+use gluon_bot::commands::{base, BotCommand};
+use gluon_bot::database::Database;
+use gluon_bot::queue::MessageQueue;
+use gluon_bot::status::BotStatus;
+use gluon_bot::tasks::{self, plugins::PluginEvent};
+use gluon_bot::transport::console::ConsoleTransport;
+use gluon_bot::transport::{Incoming, IncomingKind, Transport};
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+// Writes an executable script to the plugins folder of the test directory.
+fn script(dir: &TestDir, name: &str, body: &str) {
+    let plugins = dir.path().join("plugins");
+    let path = plugins.join(name);
+
+    fs::create_dir_all(&plugins).expect("Could not create plugins directory.");
+    fs::write(&path, format!("#!/bin/sh\n{body}")).expect("Could not write plugin.");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+        .expect("Could not make plugin executable.");
+}
+
+// The runtime reads the text format as well, so WebAssembly plugins are written as text.
+fn module(dir: &TestDir, name: &str, wat: &str) {
+    let plugins = dir.path().join("plugins");
+
+    fs::create_dir_all(&plugins).expect("Could not create plugins directory.");
+    fs::write(plugins.join(format!("{name}.wasm")), wat).expect("Could not write module.");
+}
+
+fn manifest(dir: &TestDir, file_name: &str, contents: &str) {
+    fs::write(dir.path().join("plugins").join(file_name), contents)
+        .expect("Could not write manifest.");
+}
+
+fn options(dir: &TestDir, extra: &[(&str, &str)]) -> HashMap<String, String> {
+    let mut options = HashMap::from([
+        (
+            String::from("database_path"),
+            format!("{}/", dir.data().display()),
+        ),
+        (
+            String::from("plugins_path"),
+            dir.path().join("plugins").display().to_string(),
+        ),
+    ]);
+
+    for (key, value) in extra {
+        options.insert(String::from(*key), String::from(*value));
+    }
+
+    options
+}
+
+// Runs the plugin found for command like BotCommand does, after loading the plugins.
+async fn run(command: &str, args: &[&str], options: &HashMap<String, String>) -> String {
+    run_on(command, args, CHANNEL, options).await
+}
+
+async fn run_on(
+    command: &str,
+    args: &[&str],
+    target: &str,
+    options: &HashMap<String, String>,
+) -> String {
+    let args: Vec<String> = args.iter().map(|arg| String::from(*arg)).collect();
+    let (queue, _) = MessageQueue::new();
+    let plugins = Plugins::load(options);
+    let db = Arc::new(Mutex::new(Database::new(&options["database_path"], None)));
+
+    match plugins.find(command) {
+        Some(manifest) => {
+            plugin::plugin(
+                manifest, &args, "alice", target, NETWORK, options, db, queue,
+            )
+            .await
+        }
+        None => String::new(),
+    }
+}
+
+#[tokio::test]
+async fn plugins_get_the_arguments_and_a_clean_environment() {
+    let dir = TestDir::new(&[]);
+
+    std::env::set_var("GLUON_BOT_SECRET", "secret");
+    script(
+        &dir,
+        "echo",
+        "echo \"$1 $2 $3 ${GLUON_BOT_SECRET:-none}\"\nread request\necho \"$request\"\n",
+    );
+
+    assert_eq!(
+        run("echo", &["hello"], &options(&dir, &[])).await,
+        "alice #test hello none\r\n{\"command\":\"echo\",\"args\":[\"hello\"],\"nick\":\"alice\",\"target\":\"#test\",\"network\":\"test\"}\r\n"
+    );
+    assert_eq!(run("missing", &[], &options(&dir, &[])).await, "");
+}
+
+#[tokio::test]
+async fn plugins_can_reply_with_json() {
+    let dir = TestDir::new(&[]);
+    let args: Vec<String> = Vec::new();
+    let (queue, mut receiver) = MessageQueue::new();
+    let options = options(&dir, &[]);
+
+    script(
+        &dir,
+        "json",
+        "echo '{\"messages\":[{\"text\":\"here\"},{\"target\":\"#other\",\"text\":\"there\"}],\"error\":\"oops\"}'\n",
+    );
+
+    let plugins = Plugins::load(&options);
+    let manifest = plugins.find("json").expect("Could not find plugin.");
+
+    assert_eq!(
+        plugin::plugin(
+            manifest,
+            &args,
+            "alice",
+            CHANNEL,
+            NETWORK,
+            &options,
+            dir.database(),
+            queue
+        )
+        .await,
+        "here\r\nPlugin json error: oops"
+    );
+
+    let message = receiver
+        .try_next("gluon", 410)
+        .expect("Nothing was queued.");
+
+    assert_eq!(
+        (message.target.as_str(), message.text.as_str()),
+        ("#other", "there")
+    );
+}
+
+#[tokio::test]
+async fn plugins_are_stopped_and_capped() {
+    let dir = TestDir::new(&[]);
+
+    script(&dir, "slow", "sleep 30\n");
+    script(&dir, "chatty", "yes\n");
+    script(&dir, "broken", "exit 3\n");
+
+    let options = options(&dir, &[("plugin_timeout", "1"), ("plugin_max_output", "8")]);
+
+    assert_eq!(run("slow", &[], &options).await, "Plugin slow timed out.");
+    assert_eq!(run("chatty", &[], &options).await, "y\r\ny\r\ny\r\ny\r\n");
+    assert_eq!(run("broken", &[], &options).await, "Plugin broken failed.");
+}
+
+#[tokio::test]
+async fn plugins_are_described_by_their_manifest() {
+    let dir = TestDir::new(&[]);
+
+    script(&dir, "hello", "echo \"Hello $3!\"\n");
+    script(&dir, "slow", "sleep 2\necho done\n");
+    script(&dir, "secret", "echo secret\n");
+    manifest(
+        &dir,
+        "hello.toml",
+        "name = \"hello\"\naliases = [\"hi\"]\ndescription = \"Says hello.\"\nusage = \"!hello <nick>\"\n",
+    );
+    manifest(&dir, "slow.toml", "name = \"slow\"\ntimeout = 3\n");
+    manifest(
+        &dir,
+        "secret.toml",
+        "name = \"secret\"\nchannels = [\"#private\"]\n",
+    );
+    manifest(&dir, "escape.toml", "name = \"../hello\"\n");
+
+    let options = options(&dir, &[("plugin_timeout", "1")]);
+    let plugins = Plugins::load(&options);
+
+    assert_eq!(
+        plugin::plugins(&[], &plugins).await,
+        "Plugins: hello | secret | slow"
+    );
+    assert_eq!(
+        plugin::plugins(&[String::from("HI")], &plugins).await,
+        "hello (hi): Says hello. Usage: !hello <nick>"
+    );
+    assert_eq!(
+        plugin::plugins(&[String::from("typo")], &plugins).await,
+        "Could not find plugin typo."
+    );
+    assert_eq!(run("hi", &["bob"], &options).await, "Hello bob!\r\n");
+    assert_eq!(run("slow", &[], &options).await, "done\r\n");
+    assert_eq!(
+        run("secret", &[], &options).await,
+        "This command is not available here."
+    );
+    assert_eq!(
+        run_on("secret", &[], "#Private", &options).await,
+        "secret\r\n"
+    );
+    assert!(plugins.find("../hello").is_none());
+    assert_eq!(
+        plugin::plugins(&[], &Plugins::load(&HashMap::new())).await,
+        "No plugins installed."
+    );
+}
+
+#[tokio::test]
+async fn help_lists_the_plugins_and_unknown_commands_point_to_it() {
+    let dir = TestDir::new(&[]);
+
+    script(&dir, "hello", "echo hello\n");
+
+    let options = options(&dir, &[("prefix", "?")]);
+    let plugins = Plugins::load(&options);
+    let owners: Vec<String> = Vec::new();
+    let (queue, _receiver) = MessageQueue::new();
+    let transport: Arc<dyn Transport> = Arc::new(ConsoleTransport::new("gluon", "alice", CHANNEL));
+    let status = Arc::new(Mutex::new(BotStatus::new(NETWORK)));
+
+    assert!(base::help(&plugins)
+        .await
+        .ends_with("| weather. Plugins: hello"));
+
+    let command = BotCommand::new(
+        "?helo there",
+        Some(String::from("alice")),
+        CHANNEL,
+        NETWORK,
+        &options,
+        &owners,
+        &plugins,
+    )
+    .expect("Could not parse the command.");
+
+    assert_eq!(
+        command
+            .handle(
+                dir.database(),
+                Clock::from_options(&options),
+                transport,
+                queue,
+                status,
+                PendingTells::default()
+            )
+            .await,
+        "Unknown command helo, see ?help or ?plugins."
+    );
+}
+
+#[tokio::test]
+async fn events_plugins_receive_events_and_send_messages() {
+    let dir = TestDir::new(&[]);
+
+    script(
+        &dir,
+        "greeter",
+        r##"while read event; do
+  case "$event" in
+    *'"type":"join"'*) echo '{"messages":[{"target":"#test","text":"Welcome!"}]}' ;;
+    *'"type":"message"'*) echo "$event" | sed 's/.*"text":"\([^"]*\)".*/{"messages":[{"target":"#test","text":"Echo \1"}]}/' ;;
+  esac
+done
+"##,
+    );
+    manifest(
+        &dir,
+        "greeter.toml",
+        "name = \"greeter\"\ntype = \"events\"\nchannels = [\"#test\"]\n",
+    );
+
+    let options = Arc::new(options(&dir, &[]));
+    let plugins = Arc::new(Plugins::load(&options));
+    let (queue, mut receiver) = MessageQueue::new();
+    let (events, events_receiver) = mpsc::unbounded_channel();
+    let clock = Clock::system("");
+    let token = CancellationToken::new();
+
+    // Events plugins can't be run as commands.
+    assert_eq!(run("greeter", &[], &options).await, "");
+
+    task::spawn(tasks::plugins::plugins(
+        String::from(NETWORK),
+        plugins,
+        options,
+        queue,
+        clock.clone(),
+        events_receiver,
+        token.clone(),
+    ));
+
+    for (kind, target, text) in [
+        (IncomingKind::Join, "#other", ""),
+        (IncomingKind::Message, "#other", "ignored"),
+        (IncomingKind::Join, CHANNEL, ""),
+        (IncomingKind::Message, CHANNEL, "hello"),
+    ] {
+        let incoming = Incoming {
+            kind,
+            nick: Some(String::from("alice")),
+            target: String::from(target),
+            text: String::from(text),
+        };
+
+        events
+            .send(PluginEvent::new(&incoming, NETWORK, &clock))
+            .expect("Could not send event.");
+    }
+
+    for expected in ["Welcome!", "Echo hello"] {
+        let message = time::timeout(TIMEOUT, receiver.next("gluon", 410))
+            .await
+            .expect("Timed out waiting for the plugin.")
+            .expect("Queue closed.");
+
+        assert_eq!(
+            (message.target.as_str(), message.text.as_str()),
+            (CHANNEL, expected)
+        );
+    }
+
+    token.cancel();
+}
+
+const COUNTER: &str = r##"(module
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "gluon" "reply" (func $reply (param i32 i32)))
+  (import "gluon" "send" (func $send (param i32 i32 i32 i32) (result i32)))
+  (import "gluon" "kv_get" (func $kv_get (param i32 i32) (result i32)))
+  (import "gluon" "kv_set" (func $kv_set (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "hits")
+  (data (i32.const 8) "1")
+  (data (i32.const 16) "first")
+  (data (i32.const 32) "again")
+  (data (i32.const 48) "#other")
+  (data (i32.const 64) "psst")
+  (data (i32.const 100) "from stdout\n")
+  (func (export "_start")
+    (drop (call $send (i32.const 48) (i32.const 6) (i32.const 64) (i32.const 4)))
+    (if (i32.lt_s (call $kv_get (i32.const 0) (i32.const 4)) (i32.const 0))
+      (then
+        (drop (call $kv_set (i32.const 0) (i32.const 4) (i32.const 8) (i32.const 1)))
+        (call $reply (i32.const 16) (i32.const 5)))
+      (else (call $reply (i32.const 32) (i32.const 5))))
+    (i32.store (i32.const 200) (i32.const 100))
+    (i32.store (i32.const 204) (i32.const 12))
+    (drop (call $fd_write (i32.const 1) (i32.const 200) (i32.const 1) (i32.const 208)))))
+"##;
+
+// Fetches the URL stored under the url key, replying with the body or "denied".
+const FETCH: &str = r##"(module
+  (import "gluon" "reply" (func $reply (param i32 i32)))
+  (import "gluon" "kv_get" (func $kv_get (param i32 i32) (result i32)))
+  (import "gluon" "http_get" (func $http_get (param i32 i32) (result i32)))
+  (import "gluon" "result" (func $result (param i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "url")
+  (data (i32.const 16) "denied")
+  (func (export "_start") (local $len i32)
+    (local.set $len (call $kv_get (i32.const 0) (i32.const 3)))
+    (local.set $len (call $result (i32.const 1024) (local.get $len)))
+    (local.set $len (call $http_get (i32.const 1024) (local.get $len)))
+    (if (i32.lt_s (local.get $len) (i32.const 0))
+      (then
+        (call $reply (i32.const 16) (i32.const 6))
+        (return)))
+    (local.set $len (call $result (i32.const 2048) (local.get $len)))
+    (call $reply (i32.const 2048) (local.get $len))))
+"##;
+
+const SPIN: &str = r#"(module
+  (memory (export "memory") 1)
+  (func (export "_start") (loop $spin (br $spin))))
+"#;
+
+#[tokio::test]
+async fn wasm_plugins_use_the_host_api() {
+    let dir = TestDir::new(&[]);
+    let args: Vec<String> = Vec::new();
+    let options = options(&dir, &[]);
+
+    module(&dir, "counter", COUNTER);
+    manifest(
+        &dir,
+        "counter.toml",
+        "name = \"counter\"\ntype = \"wasm\"\nchannels = [\"#test\", \"#other\"]\n",
+    );
+
+    let plugins = Plugins::load(&options);
+    let manifest = plugins.find("counter").expect("Could not find plugin.");
+
+    for expected in ["first\r\nfrom stdout\r\n", "again\r\nfrom stdout\r\n"] {
+        let (queue, mut receiver) = MessageQueue::new();
+
+        assert_eq!(
+            plugin::plugin(
+                manifest,
+                &args,
+                "alice",
+                CHANNEL,
+                NETWORK,
+                &options,
+                dir.database(),
+                queue
+            )
+            .await,
+            expected
+        );
+
+        let message = receiver
+            .try_next("gluon", 410)
+            .expect("Nothing was queued.");
+
+        assert_eq!(
+            (message.target.as_str(), message.text.as_str()),
+            ("#other", "psst")
+        );
+    }
+
+    assert_eq!(dir.read("plugin_store"), "counter,hits,1,test\n");
+}
+
+#[tokio::test]
+async fn wasm_plugins_only_send_where_they_were_run() {
+    let dir = TestDir::new(&[]);
+    let args: Vec<String> = Vec::new();
+    let options = options(&dir, &[]);
+
+    // Without channels in a manifest, the plugin may only send to the channel it was run on.
+    module(&dir, "counter", COUNTER);
+
+    let plugins = Plugins::load(&options);
+    let manifest = plugins.find("counter").expect("Could not find plugin.");
+    let (queue, mut receiver) = MessageQueue::new();
+
+    plugin::plugin(
+        manifest,
+        &args,
+        "alice",
+        CHANNEL,
+        NETWORK,
+        &options,
+        dir.database(),
+        queue,
+    )
+    .await;
+
+    assert!(receiver.try_next("gluon", 410).is_none());
+}
+
+#[tokio::test]
+async fn wasm_plugins_only_fetch_from_allowed_hosts() {
+    let stub = HttpStub::start(&[("/data", "text/plain", String::from("fetched"))]).await;
+    let dir = TestDir::new(&[(
+        "plugin_store",
+        &format!(
+            "fetch,url,{0},test\nnofetch,url,{0},test\n",
+            stub.url("/data")
+        ),
+    )]);
+
+    module(&dir, "fetch", FETCH);
+    module(&dir, "nofetch", FETCH);
+    manifest(
+        &dir,
+        "fetch.toml",
+        "name = \"fetch\"\ntype = \"wasm\"\nhttp_allow = [\"127.0.0.1\"]\n",
+    );
+
+    let options = options(&dir, &[]);
+
+    assert_eq!(run("fetch", &[], &options).await, "fetched\r\n");
+    assert_eq!(run("nofetch", &[], &options).await, "denied\r\n");
+}
+
+#[tokio::test]
+async fn wasm_plugins_are_limited() {
+    let dir = TestDir::new(&[]);
+
+    module(&dir, "spin", SPIN);
+
+    assert_eq!(
+        run("spin", &[], &options(&dir, &[("wasm_fuel", "100000")])).await,
+        "Plugin spin ran out of fuel."
+    );
+    assert_eq!(
+        run(
+            "spin",
+            &[],
+            &options(
+                &dir,
+                &[("plugin_timeout", "1"), ("wasm_fuel", "1000000000000")]
+            )
+        )
+        .await,
+        "Plugin spin timed out."
+    );
+
+    // The manifest overrides the options.
+    manifest(
+        &dir,
+        "spin.toml",
+        "name = \"spin\"\ntype = \"wasm\"\nfuel = 100000\n",
+    );
+
+    assert_eq!(
+        run(
+            "spin",
+            &[],
+            &options(&dir, &[("wasm_fuel", "1000000000000")])
+        )
+        .await,
+        "Plugin spin ran out of fuel."
+    );
+}
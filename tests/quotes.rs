@@ -0,0 +1,167 @@
+mod common;
+
+use common::{rocket_client, TestDir, CHANNEL, NETWORK};
+use gluon_bot::api;
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::base;
+use gluon_bot::transport::console::ConsoleTransport;
+use gluon_bot::transport::Transport;
+use rocket::http::Status;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const QUOTES: &str = "20-05-2025,<alice> Lights out,#test,test,1,bob,alice,2\n\
+    21-05-2025,<bob> Box box,#test,test,2,alice,bob,5\n\
+    22-05-2025,Hello there,#other,test,3,alice,,0\n";
+
+fn args(args: &str) -> Vec<String> {
+    args.split_whitespace().map(String::from).collect()
+}
+
+#[tokio::test]
+async fn quotes_are_numbered_searched_voted_for_and_deleted() {
+    let dir = TestDir::new(&[]);
+    let clock = Clock::pinned(
+        "2025-05-22 10:00:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    // The console user, carol, is the operator of the channel.
+    let transport: Arc<dyn Transport> = Arc::new(ConsoleTransport::new("gluon", "carol", CHANNEL));
+    let owners = [String::from("owner")];
+    let db = dir.database();
+    let quote = |args: &'static str, nick: &'static str| {
+        let clock = clock.clone();
+        let transport = Arc::clone(&transport);
+        let db = Arc::clone(&db);
+        let owners = owners.clone();
+
+        async move {
+            base::quote(
+                &self::args(args),
+                nick,
+                CHANNEL,
+                NETWORK,
+                &owners,
+                &HashMap::new(),
+                &clock,
+                transport,
+                db,
+            )
+            .await
+        }
+    };
+
+    assert_eq!(quote("", "bob").await, "Could not find quotes.");
+    assert_eq!(quote("<@alice> Lights out", "bob").await, "Quote #1 added.");
+    assert_eq!(quote("Box box", "alice").await, "Quote #2 added.");
+    assert_eq!(
+        dir.read("quotes"),
+        "22-05-2025,<@alice> Lights out,#test,test,1,bob,alice,0\n\
+        22-05-2025,Box box,#test,test,2,alice,,0\n"
+    );
+
+    assert_eq!(quote("#2", "bob").await, "#2 22-05-2025 Box box");
+    assert_eq!(quote("3", "bob").await, "Could not find quote #3.");
+    assert_eq!(
+        quote("by ALICE", "bob").await,
+        "#1 22-05-2025 <@alice> Lights out"
+    );
+    assert_eq!(
+        quote("search LIGHTS", "bob").await,
+        "#1 22-05-2025 <@alice> Lights out"
+    );
+    assert_eq!(
+        quote("search rain", "bob").await,
+        "Could not find quotes matching rain."
+    );
+
+    assert_eq!(
+        quote("top", "bob").await,
+        "No quotes have votes on this channel."
+    );
+    assert_eq!(quote("+1 1", "bob").await, "Quote #1 has 1 votes.");
+    assert_eq!(
+        quote("+1 1", "Bob").await,
+        "You already voted for quote #1."
+    );
+    assert_eq!(quote("+1 1", "dave").await, "Quote #1 has 2 votes.");
+    assert_eq!(quote("+1 2", "dave").await, "Quote #2 has 1 votes.");
+    assert_eq!(
+        quote("top", "bob").await,
+        "#1 22-05-2025 <@alice> Lights out (2 votes)\n#2 22-05-2025 Box box (1 votes)"
+    );
+
+    // Votes waiting on the database at the same time are all counted.
+    let lock = db.lock().await;
+    let erin = tokio::spawn(quote("+1 2", "erin"));
+    let frank = tokio::spawn(quote("+1 2", "frank"));
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    drop(lock);
+
+    assert_eq!(erin.await.unwrap(), "Quote #2 has 2 votes.");
+    assert_eq!(frank.await.unwrap(), "Quote #2 has 3 votes.");
+    assert_eq!(
+        quote("top", "bob").await,
+        "#2 22-05-2025 Box box (3 votes)\n#1 22-05-2025 <@alice> Lights out (2 votes)"
+    );
+
+    // Only operators of the channel and owners of the bot can delete quotes.
+    assert_eq!(
+        quote("del 1", "bob").await,
+        "Only channel operators can delete quotes."
+    );
+    assert_eq!(quote("del 1", "carol").await, "Quote #1 deleted.");
+    assert_eq!(quote("del 2", "owner").await, "Quote #2 deleted.");
+    assert_eq!(dir.read("quotes"), "");
+    assert_eq!(dir.read("quote_votes"), "");
+
+    for subcommand in ["search", "by", "+1", "del"] {
+        assert_eq!(
+            quote(subcommand, "bob").await,
+            "Please use quote search <text>, quote by <nick>, quote +1 <id> or quote del <id>."
+        );
+    }
+
+    // Quotes starting with a subcommand are added when its arguments don't fit.
+    assert_eq!(quote("by the way", "bob").await, "Quote #1 added.");
+    assert_eq!(quote("top gear", "bob").await, "Quote #2 added.");
+    assert_eq!(quote("+1 for that", "bob").await, "Quote #3 added.");
+    assert_eq!(quote("del boca vista", "bob").await, "Quote #4 added.");
+}
+
+#[tokio::test]
+async fn quotes_are_filtered_by_nick_and_ordered_by_votes() {
+    let dir = TestDir::new(&[("quotes", QUOTES)]);
+    let (client, _receiver) = rocket_client(
+        &dir,
+        rocket::build().mount("/api", rocket::routes![api::quotes]),
+    )
+    .await;
+
+    for (path, ids) in [
+        ("/api/quotes?added_by=ALICE", vec![2, 3]),
+        ("/api/quotes?said_by=alice", vec![1]),
+        (
+            "/api/quotes?channel=%23test&orderby=votes&descending=true",
+            vec![2, 1],
+        ),
+    ] {
+        let response = client.get(path).dispatch().await;
+
+        assert_eq!(response.status(), Status::Ok);
+
+        let quotes: Value = serde_json::from_str(&response.into_string().await.unwrap_or_default())
+            .expect("Invalid JSON body.");
+        let found: Vec<u64> = quotes
+            .as_array()
+            .map(|q| q.iter().filter_map(|q| q["id"].as_u64()).collect())
+            .unwrap_or_default();
+
+        assert_eq!(found, ids, "{path}");
+    }
+}
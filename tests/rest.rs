@@ -0,0 +1,217 @@
+mod common;
+
+use common::{rocket_client, TestDir, NETWORK};
+use gluon_bot::api::{self, keys};
+use gluon_bot::commands::next::Event;
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::{Client, LocalResponse};
+use serde_json::{json, Value};
+
+const EVENTS: &str = "\
+f1,Bahrain,Race,2023-03-05 15:00:00 UTC,#test,,true,test
+f1,Jeddah,Race,2023-03-19 17:00:00 UTC,#test,,true,test
+f1,Melbourne,Race,2023-04-02 05:00:00 UTC,#other,,true,test
+";
+
+async fn client(dir: &TestDir) -> Client {
+    let rocket = rocket::build().mount(
+        "/api",
+        rocket::routes![
+            api::create_quote,
+            api::event,
+            api::events,
+            api::patch_quote,
+            api::quote,
+            api::remove_event,
+            api::remove_quote,
+            api::replace_quote,
+        ],
+    );
+
+    rocket_client(dir, rocket).await.0
+}
+
+async fn key(dir: &TestDir, scopes: &str, channels: &str) -> Header<'static> {
+    let key = keys::create(
+        scopes,
+        &scopes
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>(),
+        &channels
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>(),
+        None,
+        &dir.database(),
+    )
+    .await
+    .expect("Could not create key.");
+
+    Header::new("x-api-key", key)
+}
+
+async fn json(response: LocalResponse<'_>) -> Value {
+    serde_json::from_str(&response.into_string().await.unwrap_or_default())
+        .expect("Invalid JSON body.")
+}
+
+#[tokio::test]
+async fn existing_records_get_ids() {
+    let dir = TestDir::new(&[("events", EVENTS)]);
+
+    dir.database()
+        .lock()
+        .await
+        .assign_ids::<Event>("events")
+        .expect("Could not assign ids.");
+
+    let client = client(&dir).await;
+    let response = client.get("/api/events/2").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let event = json(response).await;
+
+    assert_eq!(event["id"], 2);
+    assert_eq!(event["name"], "Jeddah");
+    assert!(dir.read("events").contains(",test,3\n"));
+
+    let response = client.get("/api/events/9").dispatch().await;
+
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(json(response).await, json!({"error": "Could not find 9."}));
+}
+
+#[tokio::test]
+async fn quotes_can_be_created_changed_and_deleted() {
+    let dir = TestDir::new(&[]);
+    let client = client(&dir).await;
+    let key = key(&dir, "quotes:write", "").await;
+    let response = client
+        .post("/api/quotes")
+        .header(ContentType::JSON)
+        .header(key.clone())
+        .body(r##"{"date": "05-03-2023", "text": "Lights out", "channel": "#test", "votes": 99}"##)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Created);
+    assert_eq!(
+        response.headers().get_one("Location"),
+        Some("/api/quotes/1")
+    );
+    let quote = json(response).await;
+
+    // Records without a network are stored under the data network of the first network, and votes
+    // are only counted through !quote +1.
+    assert_eq!(quote["id"], 1);
+    assert_eq!(quote["network"], NETWORK);
+    assert_eq!(quote["votes"], 0);
+
+    let response = client
+        .put("/api/quotes/1")
+        .header(ContentType::JSON)
+        .header(key.clone())
+        .body(r##"{"id": 7, "date": "06-03-2023", "text": "Away we go", "channel": "#test"}"##)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(json(response).await["id"], 1);
+
+    let response = client
+        .patch("/api/quotes/1")
+        .header(ContentType::JSON)
+        .header(key.clone())
+        .body(r#"{"text": "And away we go"}"#)
+        .dispatch()
+        .await;
+    let quote = json(response).await;
+
+    assert_eq!(quote["text"], "And away we go");
+    assert_eq!(quote["date"], "06-03-2023");
+    assert_eq!(
+        client
+            .delete("/api/quotes/1")
+            .header(key.clone())
+            .dispatch()
+            .await
+            .status(),
+        Status::NoContent
+    );
+    assert_eq!(
+        client.get("/api/quotes/1").dispatch().await.status(),
+        Status::NotFound
+    );
+    assert_eq!(
+        client
+            .delete("/api/quotes/1")
+            .header(key)
+            .dispatch()
+            .await
+            .status(),
+        Status::NotFound
+    );
+}
+
+#[tokio::test]
+async fn errors_have_a_json_body() {
+    let dir = TestDir::new(&[("events", EVENTS)]);
+
+    dir.database()
+        .lock()
+        .await
+        .assign_ids::<Event>("events")
+        .expect("Could not assign ids.");
+
+    let client = client(&dir).await;
+    let response = client.delete("/api/events/1").dispatch().await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(json(response).await, json!({"error": "Unauthorized"}));
+
+    let response = client
+        .delete("/api/events/3")
+        .header(key(&dir, "events:write", "#test").await)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Forbidden);
+    assert_eq!(
+        json(response).await,
+        json!({"error": "The key can't act on channel #other."})
+    );
+
+    let response = client
+        .post("/api/quotes")
+        .header(ContentType::JSON)
+        .header(key(&dir, "quotes:write", "").await)
+        .body(r#"{"text": "No date"}"#)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert!(json(response).await["error"].is_string());
+}
+
+#[tokio::test]
+async fn lists_are_paginated() {
+    let dir = TestDir::new(&[("events", EVENTS)]);
+
+    dir.database()
+        .lock()
+        .await
+        .assign_ids::<Event>("events")
+        .expect("Could not assign ids.");
+
+    let client = client(&dir).await;
+    let response = client.get("/api/events?offset=1&limit=1").dispatch().await;
+
+    assert_eq!(response.headers().get_one("X-Total-Count"), Some("3"));
+
+    let events = json(response).await;
+
+    assert_eq!(events.as_array().map(|e| e.len()), Some(1));
+    assert_eq!(events[0]["name"], "Jeddah");
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use common::{TestDir, CHANNEL, NETWORK};
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::seen::{self, PendingTells};
+use gluon_bot::queue::MessageQueue;
+use gluon_bot::transport::{Incoming, IncomingKind};
+
+fn args(args: &str) -> Vec<String> {
+    args.split_whitespace().map(String::from).collect()
+}
+
+fn incoming(kind: IncomingKind, nick: &str, target: &str, text: &str) -> Incoming {
+    Incoming {
+        kind,
+        nick: Some(String::from(nick)),
+        target: String::from(target),
+        text: String::from(text),
+    }
+}
+
+#[tokio::test]
+async fn nicks_are_seen_doing_their_last_thing() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let clock = Clock::pinned(
+        "2025-05-22 10:00:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    let (queue, _receiver) = MessageQueue::new();
+
+    for incoming in [
+        incoming(IncomingKind::Join, "alice", CHANNEL, ""),
+        incoming(IncomingKind::Message, "Alice", CHANNEL, "What a race"),
+        // Private messages are not recorded.
+        incoming(IncomingKind::Message, "alice", "gluon", "secret"),
+        incoming(IncomingKind::Quit, "bob", "", "Ping timeout"),
+        incoming(IncomingKind::Nick, "carol", "", "caroline"),
+    ] {
+        seen::record(
+            &incoming,
+            NETWORK,
+            &clock,
+            &db,
+            &queue,
+            &PendingTells::default(),
+        )
+        .await;
+    }
+
+    clock.set(
+        "2025-05-22 11:30:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+    );
+
+    assert_eq!(
+        seen::seen(&args("ALICE"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "Alice was last seen 0d 1h 30m ago in #test, saying: What a race"
+    );
+    assert_eq!(
+        seen::seen(&args("alice"), "#other", NETWORK, &clock, dir.database()).await,
+        "Alice was last seen 0d 1h 30m ago in #test"
+    );
+    assert_eq!(
+        seen::seen(&args("bob"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "bob was last seen 0d 1h 30m ago quitting (Ping timeout)"
+    );
+    assert_eq!(
+        seen::seen(&args("carol"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "carol was last seen 0d 1h 30m ago changing nick to caroline"
+    );
+    assert_eq!(
+        seen::seen(&args("dave"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "I haven't seen dave."
+    );
+
+    // Sightings are appended, compacting keeps the last one of each nick.
+    assert_eq!(dir.read("seen").lines().count(), 4);
+    seen::compact(&db).await;
+    assert_eq!(dir.read("seen").lines().count(), 3);
+    assert_eq!(
+        seen::seen(&args("alice"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "Alice was last seen 0d 1h 30m ago in #test, saying: What a race"
+    );
+}
+
+#[tokio::test]
+async fn the_messages_of_opted_out_nicks_are_not_repeated() {
+    let dir = TestDir::new(&[("log_opt_outs", "alice,test\n")]);
+    let db = dir.database();
+    let clock = Clock::pinned(
+        "2025-05-22 10:00:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    let (queue, _receiver) = MessageQueue::new();
+
+    seen::record(
+        &incoming(IncomingKind::Message, "Alice", CHANNEL, "What a race"),
+        NETWORK,
+        &clock,
+        &db,
+        &queue,
+        &PendingTells::default(),
+    )
+    .await;
+
+    assert!(!dir.read("seen").contains("What a race"));
+    assert_eq!(
+        seen::seen(&args("alice"), CHANNEL, NETWORK, &clock, dir.database()).await,
+        "Alice was last seen 0d 0h 0m ago in #test"
+    );
+}
+
+#[tokio::test]
+async fn tells_are_delivered_once_when_the_nick_speaks_or_joins() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let clock = Clock::pinned(
+        "2025-05-22 10:00:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    let (queue, mut receiver) = MessageQueue::new();
+    let pending = PendingTells::load(&db).await;
+
+    assert_eq!(
+        seen::tell(
+            &args("bob: box this lap"),
+            "alice",
+            NETWORK,
+            &clock,
+            dir.database(),
+            &pending
+        )
+        .await,
+        "I'll tell bob the next time they speak or join."
+    );
+    assert_eq!(
+        seen::tell(
+            &args("alice hi"),
+            "Alice",
+            NETWORK,
+            &clock,
+            dir.database(),
+            &pending
+        )
+        .await,
+        "You can tell yourself that."
+    );
+    assert_eq!(
+        seen::tell(
+            &args("bob"),
+            "alice",
+            NETWORK,
+            &clock,
+            dir.database(),
+            &pending
+        )
+        .await,
+        "Please provide a nick and a message."
+    );
+
+    // A part doesn't deliver, a join does and only once.
+    seen::record(
+        &incoming(IncomingKind::Part, "Bob", CHANNEL, ""),
+        NETWORK,
+        &clock,
+        &db,
+        &queue,
+        &pending,
+    )
+    .await;
+
+    assert!(receiver.try_next("gluon", 410).is_none());
+
+    clock.set(
+        "2025-05-22 12:05:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+    );
+
+    for _ in 0..2 {
+        seen::record(
+            &incoming(IncomingKind::Join, "Bob", CHANNEL, ""),
+            NETWORK,
+            &clock,
+            &db,
+            &queue,
+            &pending,
+        )
+        .await;
+    }
+
+    let message = receiver.try_next("gluon", 410).expect("No message sent.");
+
+    assert_eq!(message.target, CHANNEL);
+    assert_eq!(
+        message.text,
+        "Bob: alice left you a message 0d 2h 5m ago: box this lap"
+    );
+    assert!(receiver.try_next("gluon", 410).is_none());
+    assert_eq!(dir.read("tells"), "");
+}
+
+#[tokio::test]
+async fn tells_stored_before_a_restart_are_delivered() {
+    let dir = TestDir::new(&[(
+        "tells",
+        "alice,bob,box this lap,2025-05-22 10:00:00 UTC,test\n",
+    )]);
+    let db = dir.database();
+    let clock = Clock::pinned(
+        "2025-05-22 10:30:00 UTC"
+            .parse()
+            .expect("Invalid date time."),
+        "salt",
+    );
+    let (queue, mut receiver) = MessageQueue::new();
+
+    seen::record(
+        &incoming(IncomingKind::Message, "bob", CHANNEL, "Copy"),
+        NETWORK,
+        &clock,
+        &db,
+        &queue,
+        &PendingTells::load(&db).await,
+    )
+    .await;
+
+    let message = receiver.try_next("gluon", 410).expect("No message sent.");
+
+    assert_eq!(
+        message.text,
+        "bob: alice left you a message 0d 0h 30m ago: box this lap"
+    );
+    assert_eq!(dir.read("tells"), "");
+}
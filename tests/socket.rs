@@ -0,0 +1,168 @@
+mod common;
+
+use common::{Bot, IrcServer, CHANNEL, TIMEOUT};
+use serde_json::Value;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+use tokio::time::{self, sleep, Duration, Instant};
+
+struct Client {
+    reader: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Client {
+    // The socket only shows up once the bot is connected, so connecting is retried for a while.
+    async fn connect(bot: &Bot) -> Self {
+        let path = bot.dir.path().join("bot.sock");
+        let deadline = Instant::now() + TIMEOUT;
+
+        loop {
+            if let Ok(stream) = UnixStream::connect(&path).await {
+                let (reader, writer) = stream.into_split();
+
+                return Self {
+                    reader: BufReader::new(reader).lines(),
+                    writer,
+                };
+            }
+
+            assert!(
+                Instant::now() < deadline,
+                "Could not connect to the socket."
+            );
+            sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    // Sends a request and returns the acknowledgement.
+    async fn request(&mut self, request: &str) -> Value {
+        self.writer
+            .write_all(format!("{request}\n").as_bytes())
+            .await
+            .expect("Could not write to the socket.");
+
+        let line = time::timeout(TIMEOUT, self.reader.next_line())
+            .await
+            .expect("Timed out waiting for the acknowledgement.")
+            .expect("Could not read from the socket.")
+            .expect("The socket was closed.");
+
+        serde_json::from_str(&line).expect("Invalid acknowledgement.")
+    }
+}
+
+async fn start() -> (IrcServer, Bot, Client) {
+    let mut server = IrcServer::start().await;
+    let bot = Bot::start(&server, &[], &[]).await;
+
+    server.joined(CHANNEL).await;
+
+    let client = Client::connect(&bot).await;
+
+    (server, bot, client)
+}
+
+#[tokio::test]
+async fn messages_are_sent_to_the_target() {
+    let (mut server, _bot, mut client) = start().await;
+
+    assert_eq!(
+        client
+            .request(r##"{"id": 1, "type": "message", "target": "#test", "text": "Lights out!"}"##)
+            .await,
+        serde_json::json!({"id": 1, "ok": true})
+    );
+    server.expect(CHANNEL, "Lights out!").await;
+}
+
+#[tokio::test]
+async fn commands_give_their_output() {
+    let (mut server, _bot, mut client) = start().await;
+
+    assert_eq!(
+        client
+            .request(r##"{"id": "a", "type": "command", "target": "#test", "text": "!ping", "quiet": true}"##)
+            .await,
+        serde_json::json!({"id": "a", "ok": true, "output": "pong"})
+    );
+    assert_eq!(
+        client
+            .request(r##"{"type": "command", "target": "#test", "text": "!ping"}"##)
+            .await,
+        serde_json::json!({"ok": true, "output": "pong"})
+    );
+    // Only the second command was sent to the channel.
+    server.expect(CHANNEL, "pong").await;
+    assert_eq!(
+        client
+            .request(r##"{"type": "command", "target": "#test", "text": "ping"}"##)
+            .await,
+        serde_json::json!({"ok": false, "error": "Not a command."})
+    );
+}
+
+#[tokio::test]
+async fn actions_are_split_into_lines() {
+    let (mut server, _bot, mut client) = start().await;
+
+    assert_eq!(
+        client
+            .request(
+                r##"{"type": "action", "target": "#test", "text": "waves\r\nPRIVMSG #test :hi"}"##
+            )
+            .await,
+        serde_json::json!({"ok": true})
+    );
+    server.expect(CHANNEL, "\u{1}ACTION waves\u{1}").await;
+    server
+        .expect(CHANNEL, "\u{1}ACTION PRIVMSG #test :hi\u{1}")
+        .await;
+}
+
+#[tokio::test]
+async fn only_the_user_may_use_the_socket() {
+    let (_server, bot, _client) = start().await;
+    let metadata =
+        fs::metadata(bot.dir.path().join("bot.sock")).expect("Could not read the socket.");
+
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+}
+
+#[tokio::test]
+async fn the_bot_joins_channels() {
+    let (mut server, _bot, mut client) = start().await;
+
+    assert_eq!(
+        client
+            .request(r##"{"type": "join", "target": "#other"}"##)
+            .await,
+        serde_json::json!({"ok": true})
+    );
+    server.joined("#other").await;
+}
+
+#[tokio::test]
+async fn invalid_requests_are_rejected() {
+    let (_server, _bot, mut client) = start().await;
+
+    assert_eq!(
+        client
+            .request(r##"{"id": 2, "type": "message", "text": "Hello"}"##)
+            .await,
+        serde_json::json!({"id": 2, "ok": false, "error": "Missing target."})
+    );
+    assert_eq!(
+        client
+            .request(r##"{"type": "kick", "target": "#test"}"##)
+            .await,
+        serde_json::json!({"ok": false, "error": "Unknown request type kick."})
+    );
+    assert_eq!(
+        client.request("not json").await["ok"],
+        serde_json::json!(false)
+    );
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use common::{api_client, bot_state, TestDir, NETWORK};
+use gluon_bot::activity::{Activity, ActivityKind};
+use gluon_bot::api::{self, keys};
+use gluon_bot::clock::Clock;
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::{Client, LocalResponse};
+use serde_json::Value;
+use tokio::io::AsyncReadExt;
+use tokio::time::{timeout, Duration};
+
+async fn client(dir: &TestDir, activity: &Activity) -> Client {
+    let (mut state, _receiver) = bot_state(dir);
+
+    state.networks[0].activity = activity.clone();
+
+    api_client(
+        rocket::build().mount("/api", rocket::routes![api::stream]),
+        state,
+    )
+    .await
+}
+
+// Reads the next event of the stream, skipping heartbeats.
+async fn next_event(response: &mut LocalResponse<'_>) -> Value {
+    let mut received = Vec::new();
+    let mut buffer = [0; 1024];
+
+    loop {
+        let text = String::from_utf8_lossy(&received).to_string();
+
+        if let Some(line) = text.lines().find_map(|l| l.strip_prefix("data:")) {
+            return serde_json::from_str(line.trim()).expect("Invalid JSON event.");
+        }
+
+        let read = timeout(Duration::from_secs(5), response.read(&mut buffer))
+            .await
+            .expect("No event received.")
+            .expect("Could not read the stream.");
+
+        received.extend_from_slice(&buffer[..read]);
+    }
+}
+
+#[tokio::test]
+async fn channel_activity_is_streamed() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let activity = Activity::new(NETWORK);
+    let clock = Clock::system("");
+    let client = client(&dir, &activity).await;
+    let key = keys::create("stream", &[String::from("stream:read")], &[], None, &db)
+        .await
+        .expect("Could not create key.");
+    let mut response = client
+        .get("/api/stream?channel=%23Formula1")
+        .header(Header::new("x-api-key", key))
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::EventStream));
+
+    // Private messages, quits and other channels are left out.
+    activity.publish(
+        ActivityKind::Message,
+        Some(String::from("user")),
+        "gluon",
+        "hi",
+        &clock,
+    );
+    activity.publish(
+        ActivityKind::Quit,
+        Some(String::from("user")),
+        "",
+        "bye",
+        &clock,
+    );
+    activity.publish(
+        ActivityKind::Message,
+        Some(String::from("user")),
+        "#other",
+        "hi",
+        &clock,
+    );
+    activity.publish(
+        ActivityKind::Command,
+        Some(String::from("user")),
+        "#formula1",
+        "!next",
+        &clock,
+    );
+
+    let event = next_event(&mut response).await;
+
+    assert_eq!(event["type"], "command");
+    assert_eq!(event["network"], NETWORK);
+    assert_eq!(event["nick"], "user");
+    assert_eq!(event["target"], "#formula1");
+    assert_eq!(event["text"], "!next");
+}
+
+#[tokio::test]
+async fn the_stream_needs_its_scope_and_channel() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let activity = Activity::new(NETWORK);
+    let client = client(&dir, &activity).await;
+    let say_key = keys::create("say", &[String::from("say")], &[], None, &db)
+        .await
+        .expect("Could not create key.");
+    let channel_key = keys::create(
+        "channel",
+        &[String::from("stream:read")],
+        &[String::from("#formula1")],
+        None,
+        &db,
+    )
+    .await
+    .expect("Could not create key.");
+
+    for (path, key, status) in [
+        ("/api/stream", say_key.as_str(), Status::Forbidden),
+        (
+            "/api/stream?channel=%23other",
+            channel_key.as_str(),
+            Status::Forbidden,
+        ),
+        (
+            "/api/stream?network=nowhere",
+            channel_key.as_str(),
+            Status::NotFound,
+        ),
+    ] {
+        let response = client
+            .get(path)
+            .header(Header::new("x-api-key", String::from(key)))
+            .dispatch()
+            .await;
+
+        assert_eq!(response.status(), status);
+    }
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use common::{rocket_client, TestDir, NETWORK};
+use gluon_bot::api::{self, keys};
+use gluon_bot::commands::base;
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::{Client, LocalResponse};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+async fn client(dir: &TestDir) -> Client {
+    let rocket = rocket::build().mount(
+        "/api",
+        rocket::routes![
+            api::create_driver,
+            api::create_train_schedule,
+            api::drivers,
+            api::patch_time_zone,
+            api::remove_driver,
+            api::time_zones,
+            api::train_schedule,
+        ],
+    );
+
+    rocket_client(dir, rocket).await.0
+}
+
+async fn key(dir: &TestDir, name: &str, scopes: &str) -> Header<'static> {
+    let key = keys::create(name, &[String::from(scopes)], &[], None, &dir.database())
+        .await
+        .expect("Could not create key.");
+
+    Header::new("x-api-key", key)
+}
+
+async fn json(response: LocalResponse<'_>) -> Value {
+    serde_json::from_str(&response.into_string().await.unwrap_or_default())
+        .expect("Invalid JSON body.")
+}
+
+#[tokio::test]
+async fn tables_need_an_admin_key() {
+    let dir = TestDir::new(&[("drivers", "1,VER\n")]);
+    let client = client(&dir).await;
+    let say_key = key(&dir, "say", "say").await;
+    let admin_key = key(&dir, "admin", "admin").await;
+
+    assert_eq!(
+        client.get("/api/drivers").dispatch().await.status(),
+        Status::Unauthorized
+    );
+    assert_eq!(
+        client
+            .get("/api/drivers")
+            .header(say_key)
+            .dispatch()
+            .await
+            .status(),
+        Status::Forbidden
+    );
+
+    let response = client
+        .post("/api/drivers")
+        .header(ContentType::JSON)
+        .header(admin_key.clone())
+        .body(r#"{"number": 44, "code": "HAM"}"#)
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Created);
+    // The existing driver has no id yet, so the new one takes the first.
+    assert_eq!(
+        json(response).await,
+        json!({"id": 1, "number": 44, "code": "HAM"})
+    );
+    assert_eq!(dir.read("drivers"), "1,VER,0\n44,HAM,1\n");
+
+    let response = client
+        .get("/api/drivers?limit=1")
+        .header(admin_key.clone())
+        .dispatch()
+        .await;
+
+    assert_eq!(response.headers().get_one("X-Total-Count"), Some("2"));
+    assert_eq!(json(response).await[0]["code"], "VER");
+    assert_eq!(
+        client
+            .delete("/api/drivers/1")
+            .header(admin_key)
+            .dispatch()
+            .await
+            .status(),
+        Status::NoContent
+    );
+    assert_eq!(dir.read("drivers"), "1,VER,0\n");
+}
+
+#[tokio::test]
+async fn records_are_stored_in_their_csv_format() {
+    let dir = TestDir::new(&[]);
+    let client = client(&dir).await;
+    let admin_key = key(&dir, "admin", "admin").await;
+    let response = client
+        .post("/api/train_schedules")
+        .header(ContentType::JSON)
+        .header(admin_key.clone())
+        .body(
+            r##"{"number": 101, "name": "Express", "hour": 8, "minute": 30, "delta": 5,
+                "score": 20, "route": ["#geeks", "#nerds"]}"##,
+        )
+        .dispatch()
+        .await;
+
+    assert_eq!(response.status(), Status::Created);
+    assert_eq!(
+        response.headers().get_one("Location"),
+        Some("/api/train_schedules/1")
+    );
+    assert_eq!(
+        dir.read("train_schedules"),
+        "101,Express,8,30,5,20,#geeks:#nerds,test,1\n"
+    );
+
+    let response = client
+        .get("/api/train_schedules/1")
+        .header(admin_key)
+        .dispatch()
+        .await;
+
+    assert_eq!(json(response).await["route"], json!(["#geeks", "#nerds"]));
+}
+
+#[tokio::test]
+async fn ids_survive_updates_from_commands() {
+    let dir = TestDir::new(&[]);
+    let db = dir.database();
+    let client = client(&dir).await;
+    let admin_key = key(&dir, "admin", "admin").await;
+
+    base::time_zone(
+        &[String::from("Europe/Lisbon")],
+        "alice",
+        NETWORK,
+        Arc::clone(&db),
+    )
+    .await;
+    base::time_zone(
+        &[String::from("Asia/Tokyo")],
+        "bob",
+        NETWORK,
+        Arc::clone(&db),
+    )
+    .await;
+    base::time_zone(
+        &[String::from("Europe/Berlin")],
+        "alice",
+        NETWORK,
+        Arc::clone(&db),
+    )
+    .await;
+
+    let response = client
+        .get("/api/time_zones")
+        .header(admin_key.clone())
+        .dispatch()
+        .await;
+    let time_zones = json(response).await;
+
+    assert_eq!(time_zones[0]["nick"], "bob");
+    assert_eq!(time_zones[0]["id"], 2);
+    assert_eq!(time_zones[1]["name"], "Europe/Berlin");
+    assert_eq!(time_zones[1]["id"], 1);
+
+    let response = client
+        .patch("/api/time_zones/1")
+        .header(ContentType::JSON)
+        .header(admin_key)
+        .body(r#"{"name": "America/New_York"}"#)
+        .dispatch()
+        .await;
+
+    assert_eq!(json(response).await["name"], "America/New_York");
+    assert_eq!(
+        base::time_zone(&[], "alice", NETWORK, db).await,
+        "Your current time zone: America/New_York"
+    );
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{Bot, IrcServer, CHANNEL, NETWORK};
+use gluon_bot::tasks::train_game;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn passengers_board_trains_at_their_station() {
+    let mut server = IrcServer::start().await;
+    let bot = Bot::start(&server, &[], &[]).await;
+
+    server.joined(CHANNEL).await;
+
+    // Stop train 1234 at the channel, the way a service does when it arrives at a station.
+    train_game::board(
+        "1234",
+        CHANNEL,
+        &[String::from("1234")],
+        NETWORK,
+        Arc::clone(&bot.db),
+    )
+    .await;
+
+    server.say("alice", CHANNEL, "!board 1234");
+    server.expect(CHANNEL, "You boarded train 1234.").await;
+
+    server.say("alice", CHANNEL, "!board 1234");
+    server
+        .expect(
+            CHANNEL,
+            "Cannot board 1234! You are currently inside train 1234.",
+        )
+        .await;
+
+    server.say("bob", CHANNEL, "!board 4321");
+    server
+        .expect(CHANNEL, "That train isn't on this station.")
+        .await;
+}
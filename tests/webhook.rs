@@ -0,0 +1,230 @@
+mod common;
+
+use common::{TestDir, CHANNEL, NETWORK, TIMEOUT};
+use gluon_bot::clock::Clock;
+use gluon_bot::commands::base;
+use gluon_bot::transport::console::ConsoleTransport;
+use gluon_bot::webhook;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tokio::task;
+use tokio::time::{self, sleep, Duration, Instant};
+
+// A webhook receiver answering every delivery with status, keeping the headers and body of each.
+struct Receiver {
+    port: u16,
+    deliveries: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+impl Receiver {
+    async fn start(status: &'static str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind webhook receiver.");
+        let port = listener
+            .local_addr()
+            .expect("Could not get webhook receiver address.")
+            .port();
+        let deliveries = Arc::new(Mutex::new(Vec::new()));
+        let deliveries_clone = Arc::clone(&deliveries);
+
+        task::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let deliveries = Arc::clone(&deliveries_clone);
+
+                task::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+
+                    // Read the head, then as much of the body as Content-Length asks for.
+                    let (head, start, length) = loop {
+                        match socket.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => request.extend_from_slice(&buffer[..read]),
+                        }
+
+                        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                            let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                            let length: usize = head
+                                .lines()
+                                .find_map(|l| l.strip_prefix("content-length:"))
+                                .and_then(|l| l.trim().parse().ok())
+                                .unwrap_or(0);
+
+                            break (head, end + 4, end + 4 + length);
+                        }
+                    };
+
+                    while request.len() < length {
+                        match socket.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => request.extend_from_slice(&buffer[..read]),
+                        }
+                    }
+
+                    let body = String::from_utf8_lossy(&request[start..length]);
+
+                    deliveries.lock().await.push((head, String::from(body)));
+
+                    let response = format!(
+                        "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    );
+
+                    if socket.write_all(response.as_bytes()).await.is_ok() {
+                        let _ = socket.shutdown().await;
+                    }
+                });
+            }
+        });
+
+        Self { port, deliveries }
+    }
+
+    fn url(&self) -> String {
+        format!("http://127.0.0.1:{}/hook", self.port)
+    }
+
+    // Waits for count deliveries and returns every delivery received.
+    async fn wait(&self, count: usize) -> Vec<(String, String)> {
+        let deadline = Instant::now() + TIMEOUT;
+
+        loop {
+            let deliveries = self.deliveries.lock().await.clone();
+
+            if deliveries.len() >= count {
+                return deliveries;
+            }
+
+            assert!(
+                Instant::now() < deadline,
+                "Timed out waiting for {count} deliveries, got {}.",
+                deliveries.len()
+            );
+            sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+fn options(receiver: &Receiver, extra: &[(&str, &str)]) -> HashMap<String, String> {
+    let mut options = HashMap::from([(String::from("webhook_urls"), receiver.url())]);
+
+    for (key, value) in extra {
+        options.insert(String::from(*key), String::from(*value));
+    }
+
+    options
+}
+
+#[tokio::test]
+async fn events_are_posted_signed() {
+    let receiver = Receiver::start("200 OK").await;
+    let dir = TestDir::new(&[]);
+
+    webhook::notify(
+        "quote",
+        NETWORK,
+        json!({"text": "Lights out and away we go!"}),
+        &options(&receiver, &[("webhook_secret", "secret")]),
+        dir.database(),
+    );
+
+    let deliveries = receiver.wait(1).await;
+    let (head, body) = &deliveries[0];
+    let payload: Value = serde_json::from_str(body).expect("Invalid payload.");
+
+    assert_eq!(payload["event"], "quote");
+    assert_eq!(payload["network"], NETWORK);
+    assert_eq!(payload["data"]["text"], "Lights out and away we go!");
+    assert!(head.contains("x-gluon-event: quote"));
+    assert!(head.contains(&format!(
+        "x-gluon-signature: sha256={}",
+        webhook::sign("secret", body)
+    )));
+}
+
+#[tokio::test]
+async fn quotes_added_from_a_channel_are_posted() {
+    let receiver = Receiver::start("200 OK").await;
+    let dir = TestDir::new(&[]);
+
+    assert_eq!(
+        base::quote(
+            &[String::from("<alice>"), String::from("Box box")],
+            "bob",
+            CHANNEL,
+            NETWORK,
+            &[],
+            &options(&receiver, &[]),
+            &Clock::from_options(&HashMap::new()),
+            Arc::new(ConsoleTransport::new("gluon", "carol", CHANNEL)),
+            dir.database(),
+        )
+        .await,
+        "Quote #1 added."
+    );
+
+    let deliveries = receiver.wait(1).await;
+    let payload: Value = serde_json::from_str(&deliveries[0].1).expect("Invalid payload.");
+
+    assert_eq!(payload["event"], "quote");
+    assert_eq!(payload["data"]["id"], 1);
+    assert_eq!(payload["data"]["text"], "<alice> Box box");
+    assert_eq!(payload["data"]["said_by"], "alice");
+}
+
+#[tokio::test]
+async fn only_the_listed_events_are_posted() {
+    let receiver = Receiver::start("200 OK").await;
+    let dir = TestDir::new(&[]);
+    let options = options(&receiver, &[("webhook_events", "first, quote")]);
+
+    webhook::notify("feed_item", NETWORK, json!({}), &options, dir.database());
+    webhook::notify("first", NETWORK, json!({}), &options, dir.database());
+
+    let deliveries = receiver.wait(1).await;
+
+    // Skipped events are never sent, so the first delivery is the only one.
+    assert_eq!(deliveries.len(), 1);
+    assert!(deliveries[0].1.contains(r#""event":"first""#));
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried_then_dead_lettered() {
+    let receiver = Receiver::start("500 Internal Server Error").await;
+    let dir = TestDir::new(&[]);
+
+    webhook::notify(
+        "derailment",
+        NETWORK,
+        json!({"number": 6001}),
+        &options(
+            &receiver,
+            &[("webhook_retries", "2"), ("webhook_retry_delay", "0")],
+        ),
+        dir.database(),
+    );
+
+    assert_eq!(receiver.wait(3).await.len(), 3);
+
+    let dead_letters = time::timeout(TIMEOUT, async {
+        loop {
+            let dead_letters = dir.read("webhook_dead_letters");
+
+            if !dead_letters.is_empty() {
+                return dead_letters;
+            }
+
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("Timed out waiting for the dead letter.");
+
+    assert!(dead_letters.contains(&receiver.url()));
+    assert!(dead_letters.contains("derailment"));
+    assert!(dead_letters.contains("HTTP 500"));
+}